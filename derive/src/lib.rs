@@ -0,0 +1,150 @@
+#![deny(warnings)]
+
+//! Provides the `DynError` derive macro for the [`dyn-fmt`](https://docs.rs/dyn-fmt) crate.
+//!
+//! Use it through the `dyn_fmt::DynError` re-export, available with the `"derive"` feature of `dyn-fmt`.
+
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::parse::ParseStream;
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Error, Fields, Ident, LitStr, Token};
+
+/// Implements `DynError`, `Display`, and `Error` for a struct or an enum
+/// with the message template given in the `#[dyn_error("...")]` attribute of the struct or every variant.
+///
+/// The template refers to named fields by name, e.g. `{user_id}`, and to tuple fields by index, e.g. `{0}`.
+/// The message key, used to override the template from a catalog, is the type name for a struct,
+/// and `Type.Variant` for an enum variant, unless specified as `#[dyn_error("...", key = "...")]`.
+/// Every field should implement `Display`, except fields marked with `#[dyn_error(skip)]`,
+/// which are not passed, or passed as empty positional arguments to keep the indices of tuple fields.
+#[proc_macro_derive(DynError, attributes(dyn_error))]
+pub fn derive_dyn_error(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input).unwrap_or_else(|e| e.to_compile_error()).into()
+}
+
+struct Message {
+    template: LitStr,
+    key: Option<LitStr>,
+}
+
+fn message(attrs: &[Attribute], span: Span, default_key: String) -> Result<(LitStr, LitStr), Error> {
+    let mut message = None;
+    for attr in attrs.iter().filter(|x| x.path().is_ident("dyn_error")) {
+        if message.is_some() { return Err(Error::new(attr.span(), "duplicate dyn_error attribute")); }
+        message = Some(attr.parse_args_with(|input: ParseStream| {
+            let template = input.parse()?;
+            let mut key = None;
+            if input.parse::<Option<Token![,]>>()?.is_some() && !input.is_empty() {
+                let name: Ident = input.parse()?;
+                if name != "key" { return Err(Error::new(name.span(), "expected `key`")); }
+                input.parse::<Token![=]>()?;
+                key = Some(input.parse()?);
+                input.parse::<Option<Token![,]>>()?;
+            }
+            Ok(Message { template, key })
+        })?);
+    }
+    let Some(message) = message else {
+        return Err(Error::new(span, "missing #[dyn_error(\"...\")] message template"));
+    };
+    let key = message.key.unwrap_or_else(|| LitStr::new(&default_key, span));
+    Ok((message.template, key))
+}
+
+fn is_skipped(attrs: &[Attribute]) -> Result<bool, Error> {
+    let Some(attr) = attrs.iter().find(|x| x.path().is_ident("dyn_error")) else { return Ok(false); };
+    let name: Ident = attr.parse_args()?;
+    if name != "skip" { return Err(Error::new(name.span(), "expected `skip`")); }
+    Ok(true)
+}
+
+/// Returns the pattern binding the fields, and the statements adding them to `args`.
+fn bind_fields(fields: &Fields) -> Result<(TokenStream, TokenStream), Error> {
+    let mut bindings = Vec::new();
+    let mut pushes = Vec::new();
+    for (index, field) in fields.iter().enumerate() {
+        let skip = is_skipped(&field.attrs)?;
+        match &field.ident {
+            Some(name) => {
+                if skip { continue; }
+                let key = name.to_string();
+                let key = key.strip_prefix("r#").unwrap_or(&key);
+                bindings.push(quote! { #name });
+                pushes.push(quote! { args.push_named(#key, #name); });
+            },
+            None => {
+                let name = format_ident!("field_{}", index);
+                if skip {
+                    bindings.push(quote! { _ });
+                    pushes.push(quote! { args.push(&""); });
+                } else {
+                    bindings.push(quote! { #name });
+                    pushes.push(quote! { args.push(#name); });
+                }
+            },
+        }
+    }
+    let pattern = match fields {
+        Fields::Named(_) => quote! { { #(#bindings,)* .. } },
+        Fields::Unnamed(_) => quote! { ( #(#bindings),* ) },
+        Fields::Unit => quote! { },
+    };
+    Ok((pattern, quote! { #(#pushes)* }))
+}
+
+fn expand(input: &DeriveInput) -> Result<TokenStream, Error> {
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    let mut keys = Vec::new();
+    let mut templates = Vec::new();
+    let mut args = Vec::new();
+    match &input.data {
+        Data::Struct(data) => {
+            let (template, key) = message(&input.attrs, name.span(), name.to_string())?;
+            let (pattern, pushes) = bind_fields(&data.fields)?;
+            keys.push(quote! { #name #pattern => #key });
+            templates.push(quote! { #name #pattern => #template });
+            args.push(quote! { #name #pattern => { #pushes } });
+        },
+        Data::Enum(data) => {
+            for variant in &data.variants {
+                let ident = &variant.ident;
+                let (template, key) = message(&variant.attrs, ident.span(), format!("{}.{}", name, ident))?;
+                let (pattern, pushes) = bind_fields(&variant.fields)?;
+                keys.push(quote! { #name::#ident #pattern => #key });
+                templates.push(quote! { #name::#ident #pattern => #template });
+                args.push(quote! { #name::#ident #pattern => { #pushes } });
+            }
+        },
+        Data::Union(data) => return Err(Error::new(data.union_token.span(), "DynError cannot be derived for a union")),
+    }
+    Ok(quote! {
+        impl #impl_generics ::dyn_fmt::DynError for #name #type_generics #where_clause {
+            #[allow(unused_variables)]
+            fn message_key(&self) -> &'static str {
+                match self { #(#keys,)* }
+            }
+
+            #[allow(unused_variables)]
+            fn message_template(&self) -> &'static str {
+                match self { #(#templates,)* }
+            }
+
+            fn message_args(&self) -> ::dyn_fmt::MessageArgs<'_> {
+                let mut args = ::dyn_fmt::MessageArgs::new();
+                match self { #(#args,)* }
+                args
+            }
+        }
+
+        impl #impl_generics ::core::fmt::Display for #name #type_generics #where_clause {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                ::dyn_fmt::DynError::fmt_message(self, f)
+            }
+        }
+
+        impl #impl_generics ::std::error::Error for #name #type_generics #where_clause { }
+    })
+}
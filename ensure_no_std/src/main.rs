@@ -22,5 +22,8 @@ pub fn main(_argc: isize, _argv: *const *const u8) -> isize {
     let mut buf: ArrayString<128> = ArrayString::new();
     assert!(dyn_write!(&mut buf, "{}a{}b{}c", &[1, 2, 3]).is_ok());
     assert_eq!(&buf, "1a2b3c");
+    let args = dyn_fmt::Arguments::new("{}a{}b{}c", &[1, 2, 3]);
+    assert_eq!(args.format_array::<6>().unwrap().as_str(), "1a2b3c");
+    assert!(args.format_array::<5>().is_err());
     0
 }
@@ -0,0 +1,38 @@
+/* The C interface of the dyn-fmt library, built with the "capi" feature. */
+
+#ifndef DYN_FMT_H
+#define DYN_FMT_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef enum {
+    DYNFMT_OK = 0,
+    DYNFMT_NULL_POINTER = 1,
+    DYNFMT_INVALID_UTF8 = 2,
+    DYNFMT_INVALID_TEMPLATE = 3,
+    DYNFMT_BUFFER_TOO_SMALL = 4
+} dynfmt_status;
+
+/*
+ * Formats the tmpl template with n zero-terminated UTF-8 string arguments from the args array
+ * into the out buffer of cap bytes, including the terminating zero.
+ *
+ * If len is not NULL, the full output length, not including the terminating zero,
+ * is stored there on DYNFMT_OK and on DYNFMT_BUFFER_TOO_SMALL.
+ * A truncated output is cut at a character boundary and terminated with zero, if cap is not zero.
+ * args may be NULL if n is zero, and out may be NULL if cap is zero.
+ */
+dynfmt_status dynfmt_format(const char *tmpl, const char *const *args, size_t n, char *out, size_t cap, size_t *len);
+
+/* Returns a static zero-terminated description of the status. */
+const char *dynfmt_status_message(dynfmt_status status);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,74 @@
+use crate::{Arguments, formatted_len};
+use allocator_api2::alloc::Allocator;
+use allocator_api2::vec::Vec;
+use core::fmt::{self, Display, Write};
+
+/// Appends the formatted output to a byte vector.
+struct VecWriter<'v, A: Allocator>(&'v mut Vec<u8, A>);
+
+impl<'v, A: Allocator> Write for VecWriter<'v, A> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.extend_from_slice(s.as_bytes());
+        Ok(())
+    }
+}
+
+impl<'a, F: AsRef<str>, T: Display + ?Sized + 'a, I: IntoIterator<Item=&'a T> + Clone> Arguments<'a, F, T, I> {
+    /// Formats the arguments into a vector allocated in `alloc`, which holds valid UTF-8.
+    /// The output is measured first, so the vector is allocated once.
+    ///
+    /// The [`Allocator`] trait is the one of the [`allocator-api2`](https://docs.rs/allocator-api2) crate,
+    /// which is the unstable `core` trait if its `"nightly"` feature is enabled.
+    ///
+    /// Available with the `"allocator_api"` feature.
+    /// # Examples:
+    /// ```rust
+    /// use allocator_api2::alloc::Global;
+    /// let s = dyn_fmt::Arguments::new("{}-{:>3}", &[1, 2]).format_in(Global);
+    /// assert_eq!(std::str::from_utf8(&s).unwrap(), "1-  2");
+    /// ```
+    pub fn format_in<A: Allocator>(&self, alloc: A) -> Vec<u8, A> {
+        let mut res = Vec::with_capacity_in(formatted_len(self), alloc);
+        self.write_to_vec(&mut res);
+        res
+    }
+
+    /// Appends the formatted arguments to a vector, which may use any allocator.
+    ///
+    /// Available with the `"allocator_api"` feature.
+    pub fn write_to_vec<A: Allocator>(&self, buf: &mut Vec<u8, A>) {
+        write!(VecWriter(buf), "{}", self).expect("a Display implementation returned an error unexpectedly");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Arguments;
+    use allocator_api2::alloc::{AllocError, Allocator, Global, Layout};
+    use allocator_api2::vec::Vec;
+    use core::cell::Cell;
+    use core::ptr::NonNull;
+
+    struct Counting<'c>(&'c Cell<usize>);
+
+    unsafe impl<'c> Allocator for Counting<'c> {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            self.0.set(self.0.get() + 1);
+            Global.allocate(layout)
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) { Global.deallocate(ptr, layout) }
+    }
+
+    #[test]
+    fn format_in() {
+        let count = Cell::new(0);
+        let s = Arguments::new("{:*^9}|{}", &["abc", "я"]).format_in(Counting(&count));
+        assert_eq!(&s[..], "***abc***|я".as_bytes());
+        assert_eq!((s.len(), s.capacity(), count.get()), (12, 12, 1));
+        let mut buf = Vec::new_in(Counting(&count));
+        Arguments::new("{}", &[1]).write_to_vec(&mut buf);
+        Arguments::new("+{}", &[2]).write_to_vec(&mut buf);
+        assert_eq!(&buf[..], b"1+2");
+    }
+}
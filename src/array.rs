@@ -0,0 +1,87 @@
+use crate::Arguments;
+#[cfg(feature = "std")]
+use crate::FormatArguments;
+use arrayvec::{ArrayString, CapacityError};
+use core::fmt::{self, Display, Write};
+
+/// Forwards to the string, remembering whether an error was caused by running out of capacity.
+struct ArrayWriter<'s, const N: usize> {
+    buf: &'s mut ArrayString<N>,
+    overflow: bool,
+}
+
+impl<'s, const N: usize> Write for ArrayWriter<'s, N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.buf.try_push_str(s).map_err(|_| {
+            self.overflow = true;
+            fmt::Error
+        })
+    }
+}
+
+/// Formats a value into a fixed-capacity string on the stack.
+fn format_array<const N: usize>(value: &(impl Display + ?Sized)) -> Result<ArrayString<N>, CapacityError> {
+    let mut buf = ArrayString::new();
+    let mut writer = ArrayWriter { buf: &mut buf, overflow: false };
+    if write!(writer, "{}", value).is_err() {
+        if writer.overflow { return Err(CapacityError::new(())); }
+        panic!("a Display implementation returned an error unexpectedly");
+    }
+    Ok(buf)
+}
+
+impl<'a, F: AsRef<str>, T: Display + ?Sized + 'a, I: IntoIterator<Item=&'a T> + Clone> Arguments<'a, F, T, I> {
+    /// Formats the arguments into an [`ArrayString`] with capacity of `N` bytes,
+    /// returning [`CapacityError`] if the output does not fit.
+    ///
+    /// Available with the `"arrayvec"` feature.
+    /// # Examples:
+    /// ```rust
+    /// let args = dyn_fmt::Arguments::new("{}a{}b{}c", &[1, 2, 3]);
+    /// assert_eq!(args.format_array::<8>().unwrap().as_str(), "1a2b3c");
+    /// assert!(args.format_array::<4>().is_err());
+    /// ```
+    pub fn format_array<const N: usize>(&self) -> Result<ArrayString<N>, CapacityError> { format_array(self) }
+}
+
+#[cfg(feature = "std")]
+impl<'f, 'a, T: Display + ?Sized + 'a, I: IntoIterator<Item=&'a T> + Clone> FormatArguments<'f, 'a, T, I> {
+    /// Renders the template into an [`ArrayString`] with capacity of `N` bytes,
+    /// see [`Arguments::format_array`].
+    ///
+    /// Available with the `"arrayvec"` feature.
+    pub fn format_array<const N: usize>(&self) -> Result<ArrayString<N>, CapacityError> { format_array(self) }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Arguments;
+    use core::fmt::{self, Display};
+
+    #[test]
+    fn format_array() {
+        let args = Arguments::new("{:*^7}|{}", &["abc", "я"]);
+        assert_eq!(args.format_array::<10>().unwrap().as_str(), "**abc**|я");
+        assert!(args.format_array::<9>().is_err());
+        assert_eq!(Arguments::new("", &[0u8; 0]).format_array::<0>().unwrap().as_str(), "");
+    }
+
+    #[test]
+    #[should_panic(expected = "a Display implementation returned an error unexpectedly")]
+    fn format_array_error() {
+        struct Failing;
+
+        impl Display for Failing {
+            fn fmt(&self, _: &mut fmt::Formatter) -> fmt::Result { Err(fmt::Error) }
+        }
+
+        let _ = Arguments::new("{}", &[Failing]).format_array::<8>();
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn format_arguments() {
+        let format = crate::Format::parse("{1}-{0:>3}").unwrap();
+        assert_eq!(format.args(&[1, 2]).format_array::<5>().unwrap().as_str(), "2-  1");
+    }
+}
@@ -0,0 +1,141 @@
+//! Renders a template with values from the command line.
+//!
+//! Available with the `"cli"` feature.
+
+use dyn_fmt::{ArgSource, Format};
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: dyn-fmt [OPTIONS] [TEMPLATE] [VALUE]...
+
+Renders TEMPLATE with the positional VALUEs and the named values given with --arg.
+If TEMPLATE is omitted or is `-`, it is read from the standard input and output as is,
+otherwise the output is followed by a line break.
+
+Options:
+  -a, --arg NAME=VALUE  Set the named argument
+  -c, --check           Only validate the template
+  -h, --help            Print this help
+  --                    Treat the remaining arguments as positional
+";
+
+#[derive(Debug, Default, PartialEq)]
+struct Command {
+    template: Option<String>,
+    positional: Vec<String>,
+    named: HashMap<String, String>,
+    check: bool,
+    help: bool,
+}
+
+impl ArgSource for Command {
+    type Arg = String;
+
+    fn len(&self) -> usize { self.positional.len() }
+
+    fn get(&self, index: usize) -> Option<&String> { self.positional.get(index) }
+
+    fn get_named(&self, name: &str) -> Option<&String> { self.named.get(name) }
+
+    fn for_each_name(&self, f: &mut dyn FnMut(&str)) { self.named.keys().for_each(|x| f(x)) }
+}
+
+fn parse_command(args: impl IntoIterator<Item=String>) -> Result<Command, String> {
+    let mut command = Command::default();
+    let mut free = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => command.help = true,
+            "-c" | "--check" => command.check = true,
+            "-a" | "--arg" => {
+                let value = args.next().ok_or_else(|| format!("{} requires NAME=VALUE", arg))?;
+                let (name, value) = value.split_once('=').ok_or_else(|| format!("expected NAME=VALUE, found '{}'", value))?;
+                command.named.insert(name.into(), value.into());
+            },
+            "--" => { free.extend(args.by_ref()); },
+            s if s.starts_with("--arg=") => {
+                let (name, value) = s["--arg=".len() ..].split_once('=').ok_or_else(|| format!("expected NAME=VALUE, found '{}'", s))?;
+                command.named.insert(name.into(), value.into());
+            },
+            s if s.len() > 1 && s.starts_with('-') => return Err(format!("unknown option '{}'", s)),
+            _ => free.push(arg),
+        }
+    }
+    let mut free = free.into_iter();
+    command.template = free.next().filter(|x| x != "-");
+    command.positional = free.collect();
+    Ok(command)
+}
+
+fn run(command: &Command) -> Result<(), String> {
+    let template = match &command.template {
+        Some(template) => template.clone(),
+        None => {
+            let mut template = String::new();
+            io::stdin().read_to_string(&mut template).map_err(|e| format!("cannot read the template: {}", e))?;
+            template
+        },
+    };
+    let format = Format::parse(template).map_err(|e| format!("invalid template: {}", e))?;
+    if command.check { return Ok(()); }
+    let (mut output, report) = format.render_with_report(command);
+    for index in report.missing() {
+        eprintln!("warning: missing argument {}", index);
+    }
+    for name in report.missing_names() {
+        eprintln!("warning: missing argument '{}'", name);
+    }
+    if command.template.is_some() { output.push('\n'); }
+    io::stdout().write_all(output.as_bytes()).map_err(|e| format!("cannot write the output: {}", e))
+}
+
+fn main() -> ExitCode {
+    let command = match parse_command(std::env::args().skip(1)) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, USAGE);
+            return ExitCode::from(2);
+        },
+    };
+    if command.help {
+        print!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+    match run(&command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Command, parse_command};
+    use std::collections::HashMap;
+
+    fn parse(args: &[&str]) -> Result<Command, String> { parse_command(args.iter().map(|x| x.to_string())) }
+
+    #[test]
+    fn command_line() {
+        let command = parse(&["-a", "x=1=2", "{} {x}", "--check", "--arg=y=", "a", "--", "-b", "--arg"]).unwrap();
+        assert_eq!(command, Command {
+            template: Some("{} {x}".into()),
+            positional: vec!["a".into(), "-b".into(), "--arg".into()],
+            named: HashMap::from([("x".into(), "1=2".into()), ("y".into(), "".into())]),
+            check: true,
+            help: false,
+        });
+        assert_eq!(parse(&["-", "v"]).unwrap().template, None);
+        assert_eq!(parse(&[]).unwrap(), Command::default());
+        assert_eq!(parse(&["--arg"]).unwrap_err(), "--arg requires NAME=VALUE");
+        assert_eq!(parse(&["-a", "x"]).unwrap_err(), "expected NAME=VALUE, found 'x'");
+        assert_eq!(parse(&["-x"]).unwrap_err(), "unknown option '-x'");
+        let command = parse(&["{0:>3}|{name}", "7", "--arg", "name=n"]).unwrap();
+        assert_eq!(dyn_fmt::Format::parse(command.template.clone().unwrap()).unwrap().render_from(&command), "  7|n");
+    }
+}
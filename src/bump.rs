@@ -0,0 +1,65 @@
+use crate::{Arguments, formatted_len};
+#[cfg(feature = "std")]
+use crate::FormatArguments;
+use bumpalo::Bump;
+use bumpalo::collections::String;
+use core::fmt::{Display, Write};
+
+/// Formats a value into a string allocated in the arena, measuring it first to allocate once.
+fn format_bump<'b>(value: &(impl Display + ?Sized), bump: &'b Bump) -> String<'b> {
+    let mut res = String::with_capacity_in(formatted_len(value), bump);
+    write!(res, "{}", value).expect("a Display implementation returned an error unexpectedly");
+    res
+}
+
+impl<'a, F: AsRef<str>, T: Display + ?Sized + 'a, I: IntoIterator<Item=&'a T> + Clone> Arguments<'a, F, T, I> {
+    /// Formats the arguments into a string allocated in the [`Bump`] arena.
+    ///
+    /// Padding with a custom fill and measuring the output are done by formatting arguments
+    /// repeatedly rather than buffering them, so the string is the only allocation,
+    /// unless an argument or a [`Presentation`](crate::Presentation) allocates itself.
+    /// Resetting the arena per request or frame frees all strings at once.
+    ///
+    /// Available with the `"bumpalo"` feature.
+    /// # Examples:
+    /// ```rust
+    /// let mut bump = bumpalo::Bump::new();
+    /// for frame in 0 .. 3 {
+    ///     let s = dyn_fmt::Arguments::new("frame {:*>3}", &[frame]).format_bump(&bump);
+    ///     assert_eq!(s, format!("frame **{}", frame));
+    ///     drop(s);
+    ///     bump.reset();
+    /// }
+    /// ```
+    pub fn format_bump<'b>(&self, bump: &'b Bump) -> String<'b> { format_bump(self, bump) }
+}
+
+#[cfg(feature = "std")]
+impl<'f, 'a, T: Display + ?Sized + 'a, I: IntoIterator<Item=&'a T> + Clone> FormatArguments<'f, 'a, T, I> {
+    /// Renders the template into a string allocated in the [`Bump`] arena,
+    /// see [`Arguments::format_bump`].
+    ///
+    /// Available with the `"bumpalo"` feature.
+    pub fn format_bump<'b>(&self, bump: &'b Bump) -> String<'b> { format_bump(self, bump) }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Arguments;
+    use bumpalo::Bump;
+
+    #[test]
+    fn format_bump() {
+        let bump = Bump::new();
+        let s = Arguments::new("{:*^9}|{}", &["abc", "я"]).format_bump(&bump);
+        assert_eq!((s.as_str(), s.capacity()), ("***abc***|я", 12));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn format_arguments() {
+        let bump = Bump::new();
+        let format = crate::Format::parse("{1}-{0:>3}").unwrap();
+        assert_eq!(format.args(&[1, 2]).format_bump(&bump), "2-  1");
+    }
+}
@@ -0,0 +1,191 @@
+use crate::seq::present;
+use core::cell::Cell;
+use core::fmt::{self, Display, Write};
+
+thread_local! {
+    /// The encoding of the presentation being applied, taken by the outermost [`Bytes`].
+    pub(crate) static ENCODING: Cell<Option<Encoding>> = const { Cell::new(None) };
+}
+
+/// A binary encoding of [`Bytes`], selected by the [`Encode`](crate::Presentation::Encode) presentation.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
+#[non_exhaustive]
+pub enum Encoding {
+    /// `hex`, lowercase hexadecimal digits, e.g. `0aff`. The default.
+    #[default]
+    Hex,
+    /// `HEX`, uppercase hexadecimal digits, e.g. `0AFF`.
+    UpperHex,
+    /// `base64`, the standard base64 alphabet with `=` padding, e.g. `+/8=`.
+    Base64,
+    /// `base64(nopad)`, the standard base64 alphabet without padding, e.g. `+/8`.
+    Base64NoPad,
+    /// `base64(url)`, the URL-safe base64 alphabet with `=` padding, e.g. `-_8=`.
+    Base64Url,
+    /// `base64(url,nopad)`, the URL-safe base64 alphabet without padding, e.g. `-_8`.
+    Base64UrlNoPad,
+    /// `hexdump` or `hexdump(n)`, lines of the offset, `n` (16 by default) bytes in hexadecimal digits,
+    /// and the same bytes as ASCII characters, with `.` in place of unprintable ones:
+    ///
+    /// ```text
+    /// 00000000  47 45 54 20 2f 20 48 54  54 50 2f 31 2e 31 0d 0a  |GET / HTTP/1.1..|
+    /// 00000010  48 6f 73 74 3a 20 61                              |Host: a|
+    /// ```
+    ///
+    /// The lines are separated by `\n`, without a trailing line break.
+    Hexdump(u8),
+}
+
+impl Encoding {
+    pub(crate) fn parse(s: &str) -> Option<Encoding> {
+        match s {
+            "hex" => Some(Encoding::Hex),
+            "HEX" => Some(Encoding::UpperHex),
+            "base64" => Some(Encoding::Base64),
+            "base64(nopad)" => Some(Encoding::Base64NoPad),
+            "base64(url)" => Some(Encoding::Base64Url),
+            "base64(url,nopad)" => Some(Encoding::Base64UrlNoPad),
+            "hexdump" => Some(Encoding::Hexdump(16)),
+            _ => {
+                let n = s.strip_prefix("hexdump(")?.strip_suffix(')')?;
+                if !n.bytes().all(|b| b.is_ascii_digit()) { return None; }
+                n.parse().ok().filter(|&n| n != 0).map(Encoding::Hexdump)
+            },
+        }
+    }
+
+    /// Formats the argument, which is expected to be [`Bytes`], with the encoding,
+    /// returning `None` if it is not `Bytes`.
+    pub(crate) fn encode<T: Display + ?Sized>(&self, arg: &T) -> Option<String> { present(&ENCODING, *self, arg) }
+
+    fn encode_bytes(&self, bytes: &[u8]) -> String {
+        let (alphabet, pad): (&[u8; 64], bool) = match self {
+            Encoding::Hex => return bytes.iter().map(|b| format!("{:02x}", b)).collect(),
+            Encoding::UpperHex => return bytes.iter().map(|b| format!("{:02X}", b)).collect(),
+            Encoding::Base64 => (BASE64, true),
+            Encoding::Base64NoPad => (BASE64, false),
+            Encoding::Base64Url => (BASE64_URL, true),
+            Encoding::Base64UrlNoPad => (BASE64_URL, false),
+            &Encoding::Hexdump(n) => return hexdump(bytes, n.into()),
+        };
+        let mut res = String::with_capacity((bytes.len() + 2) / 3 * 4);
+        for chunk in bytes.chunks(3) {
+            let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+            for i in 0 ..= chunk.len() {
+                res.push(char::from(alphabet[(n >> (18 - 6 * i) & 0x3F) as usize]));
+            }
+            if pad {
+                for _ in chunk.len() .. 3 { res.push('='); }
+            }
+        }
+        res
+    }
+}
+
+fn hexdump(bytes: &[u8], line_len: usize) -> String {
+    let mut res = String::new();
+    for (i, line) in bytes.chunks(line_len).enumerate() {
+        if i != 0 { res.push('\n'); }
+        write!(res, "{:08x} ", i * line_len).unwrap();
+        for j in 0 .. line_len {
+            if j % 8 == 0 { res.push(' '); }
+            match line.get(j) {
+                Some(b) => write!(res, "{:02x} ", b).unwrap(),
+                None => res.push_str("   "),
+            }
+        }
+        res.push_str(" |");
+        res.extend(line.iter().map(|&b| if b == b' ' || b.is_ascii_graphic() { char::from(b) } else { '.' }));
+        res.push('|');
+    }
+    res
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+const BASE64_URL: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+impl Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Encoding::Hex => "hex",
+            Encoding::UpperHex => "HEX",
+            Encoding::Base64 => "base64",
+            Encoding::Base64NoPad => "base64(nopad)",
+            Encoding::Base64Url => "base64(url)",
+            Encoding::Base64UrlNoPad => "base64(url,nopad)",
+            Encoding::Hexdump(16) => "hexdump",
+            Encoding::Hexdump(n) => return write!(f, "hexdump({})", n),
+        })
+    }
+}
+
+/// A binary argument, e.g. `&[u8]` or a digest array, displayed as lowercase hexadecimal digits,
+/// or in the [`Encoding`] of the [`Encode`](crate::Presentation::Encode) presentation,
+/// e.g. `{:HEX}` or `{:base64(url,nopad)}`.
+///
+/// The spec, other than the presentation, is applied to the encoded text.
+///
+/// Unavailable in `no_std` environment.
+/// # Examples:
+/// ```rust
+/// use dyn_fmt::{Arguments, Bytes};
+/// let digest = [0xde, 0xad, 0xbe, 0xef];
+/// let s = Arguments::new("{} {:HEX} {:base64}", &[Bytes(digest); 3]).to_string();
+/// assert_eq!(s, "deadbeef DEADBEEF 3q2+7w==");
+/// ```
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
+pub struct Bytes<T>(pub T);
+
+impl<T: AsRef<[u8]>> Display for Bytes<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let encoding = ENCODING.with(|x| x.take()).unwrap_or_default();
+        f.pad(&encoding.encode_bytes(self.0.as_ref()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Arguments, Bytes, Format, Spec};
+    use std::collections::HashMap;
+
+    #[test]
+    fn encode() {
+        let b64 = |bytes: &[u8]| Arguments::new("{:base64}|{:base64(nopad)}", &[Bytes(bytes); 2]).to_string();
+        assert_eq!(b64(b""), "|");
+        assert_eq!(b64(b"f"), "Zg==|Zg");
+        assert_eq!(b64(b"fo"), "Zm8=|Zm8");
+        assert_eq!(b64(b"foo"), "Zm9v|Zm9v");
+        assert_eq!(b64(b"foob"), "Zm9vYg==|Zm9vYg");
+        assert_eq!(b64(b"fooba"), "Zm9vYmE=|Zm9vYmE");
+        assert_eq!(b64(b"foobar"), "Zm9vYmFy|Zm9vYmFy");
+        let bytes = Bytes(vec![0xfb, 0xff, 0x01]);
+        let s = Arguments::new("{:base64}|{:base64(url)}|{:base64(url,nopad)}|{:hex}|{:HEX}|{:>8}|{:.2hex}", &[&bytes; 7]).to_string();
+        assert_eq!(s, "+/8B|-_8B|-_8B|fbff01|FBFF01|  fbff01|fbff01");
+        assert_eq!(Arguments::new("{:hex}|{:base64}", &["ab", "cd"]).to_string(), "ab|cd");
+        let format = Format::parse("sha256={digest:base64(url,nopad)}").unwrap();
+        assert_eq!(format.render_from(HashMap::from([("digest", Bytes(&[0u8; 4]))])), "sha256=AAAAAA");
+        for s in ["hex", "HEX", "base64", "base64(nopad)", "base64(url)", "base64(url,nopad)"] {
+            assert_eq!(Spec::parse(s).unwrap().to_string(), s);
+        }
+        assert!(Spec::parse("base64(nopad,url)").is_none());
+    }
+
+    #[test]
+    fn hexdump() {
+        let request = Bytes(b"GET / HTTP/1.1\r\nHost: a");
+        assert_eq!(Arguments::new("{:hexdump}", &[request]).to_string(), "\
+            00000000  47 45 54 20 2f 20 48 54  54 50 2f 31 2e 31 0d 0a  |GET / HTTP/1.1..|\n\
+            00000010  48 6f 73 74 3a 20 61                              |Host: a|\
+        ");
+        assert_eq!(Arguments::new("{:hexdump(4)}", &[Bytes([0, 0x7f, b'~', 0xff, b'x'])]).to_string(), "\
+            00000000  00 7f 7e ff  |..~.|\n\
+            00000004  78           |x|\
+        ");
+        assert_eq!(Arguments::new("[{:hexdump}]", &[Bytes(b"")]).to_string(), "[]");
+        assert_eq!(Spec::parse("hexdump").unwrap().to_string(), "hexdump");
+        assert_eq!(Spec::parse("hexdump(8)").unwrap().to_string(), "hexdump(8)");
+        assert!(Spec::parse("hexdump(0)").is_none());
+        assert!(Spec::parse("hexdump(256)").is_none());
+    }
+}
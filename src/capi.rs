@@ -0,0 +1,123 @@
+//! The C interface, declared in `include/dyn_fmt.h`.
+//!
+//! The functions use the same template syntax and engine as [`Format`]:
+//! a template is parsed strictly, and arguments are positional strings.
+//!
+//! To link the library into a C or C++ program, build it as a static or a dynamic library, e.g.
+//! `cargo rustc --release --features capi --crate-type staticlib`.
+//!
+//! Available with the `"capi"` feature.
+
+use crate::Format;
+use core::ffi::{c_char, CStr};
+use core::ptr;
+
+/// The result code of [`dynfmt_format`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[repr(C)]
+pub enum DynfmtStatus {
+    /// The output is written.
+    Ok = 0,
+    /// The template, the argument array, one of the arguments, or the output buffer is `NULL`.
+    NullPointer = 1,
+    /// The template or one of the arguments is not valid UTF-8.
+    InvalidUtf8 = 2,
+    /// The template is malformed, see [`Format::parse`].
+    InvalidTemplate = 3,
+    /// The output does not fit the buffer, the truncated output is written.
+    BufferTooSmall = 4,
+}
+
+/// Formats the `tmpl` template with `n` string arguments from the `args` array
+/// into the `out` buffer of `cap` bytes, including the terminating zero.
+///
+/// If `len` is not `NULL`, the full output length, not including the terminating zero,
+/// is stored there on success and on [`BufferTooSmall`](DynfmtStatus::BufferTooSmall),
+/// so a caller can allocate a sufficient buffer and retry.
+/// A truncated output is cut at a character boundary and always terminated with zero, if `cap` is not zero.
+/// `args` may be `NULL` if `n` is zero, and `out` may be `NULL` if `cap` is zero.
+///
+/// # Safety
+///
+/// `tmpl` and each of the `n` elements of `args` should be `NULL`
+/// or point to a zero-terminated string, `out` should be `NULL` or point to `cap` writable bytes,
+/// and `len` should be `NULL` or point to a writable `size_t`.
+#[no_mangle]
+pub unsafe extern "C" fn dynfmt_format(
+    tmpl: *const c_char,
+    args: *const *const c_char,
+    n: usize,
+    out: *mut c_char,
+    cap: usize,
+    len: *mut usize,
+) -> DynfmtStatus {
+    if tmpl.is_null() || (args.is_null() && n != 0) || (out.is_null() && cap != 0) {
+        return DynfmtStatus::NullPointer;
+    }
+    let Ok(tmpl) = CStr::from_ptr(tmpl).to_str() else { return DynfmtStatus::InvalidUtf8; };
+    let mut values = Vec::with_capacity(n);
+    for i in 0 .. n {
+        let arg = *args.add(i);
+        if arg.is_null() { return DynfmtStatus::NullPointer; }
+        let Ok(arg) = CStr::from_ptr(arg).to_str() else { return DynfmtStatus::InvalidUtf8; };
+        values.push(arg);
+    }
+    let Ok(format) = Format::parse(tmpl) else { return DynfmtStatus::InvalidTemplate; };
+    let s = format.render_from(values.as_slice());
+    if !len.is_null() { *len = s.len(); }
+    if cap == 0 { return if s.is_empty() { DynfmtStatus::Ok } else { DynfmtStatus::BufferTooSmall }; }
+    let mut end = s.len().min(cap - 1);
+    while !s.is_char_boundary(end) { end -= 1; }
+    ptr::copy_nonoverlapping(s.as_ptr(), out.cast::<u8>(), end);
+    *out.add(end) = 0;
+    if end < s.len() { DynfmtStatus::BufferTooSmall } else { DynfmtStatus::Ok }
+}
+
+/// Returns a static zero-terminated description of the status.
+#[no_mangle]
+pub extern "C" fn dynfmt_status_message(status: DynfmtStatus) -> *const c_char {
+    let s: &'static [u8] = match status {
+        DynfmtStatus::Ok => b"ok\0",
+        DynfmtStatus::NullPointer => b"null pointer\0",
+        DynfmtStatus::InvalidUtf8 => b"invalid UTF-8\0",
+        DynfmtStatus::InvalidTemplate => b"invalid template\0",
+        DynfmtStatus::BufferTooSmall => b"buffer too small\0",
+    };
+    s.as_ptr().cast()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+
+    fn format(tmpl: &[u8], args: &[&str], cap: usize) -> (DynfmtStatus, String, usize) {
+        let tmpl = CString::new(tmpl).unwrap();
+        let args = args.iter().map(|&x| CString::new(x).unwrap()).collect::<Vec<_>>();
+        let ptrs = args.iter().map(|x| x.as_ptr()).collect::<Vec<_>>();
+        let mut out = vec![0x7f as c_char; cap];
+        let mut len = usize::MAX;
+        let status = unsafe { dynfmt_format(tmpl.as_ptr(), ptrs.as_ptr(), ptrs.len(), out.as_mut_ptr(), cap, &mut len) };
+        let text = if cap == 0 || status == DynfmtStatus::InvalidTemplate || status == DynfmtStatus::InvalidUtf8 {
+            String::new()
+        } else {
+            unsafe { CStr::from_ptr(out.as_ptr()) }.to_str().unwrap().to_string()
+        };
+        (status, text, len)
+    }
+
+    #[test]
+    fn format_to_buffer() {
+        assert_eq!(format(b"{} = {:>4}", &["x", "1"], 16), (DynfmtStatus::Ok, "x =    1".into(), 8));
+        assert_eq!(format(b"{}{1}", &["ab", "c"], 4), (DynfmtStatus::Ok, "abc".into(), 3));
+        assert_eq!(format("{} ist {}".as_bytes(), &["Größe", "gut"], 6), (DynfmtStatus::BufferTooSmall, "Grö".into(), 15));
+        assert_eq!(format(b"abc", &[], 0), (DynfmtStatus::BufferTooSmall, String::new(), 3));
+        assert_eq!(format(b"{:q}", &["x"], 8).0, DynfmtStatus::InvalidTemplate);
+        assert_eq!(format(b"\xff{}", &["x"], 8).0, DynfmtStatus::InvalidUtf8);
+        let tmpl = CString::new("{}").unwrap();
+        let status = unsafe { dynfmt_format(tmpl.as_ptr(), ptr::null(), 1, ptr::null_mut(), 0, ptr::null_mut()) };
+        assert_eq!(status, DynfmtStatus::NullPointer);
+        let message = unsafe { CStr::from_ptr(dynfmt_status_message(DynfmtStatus::InvalidTemplate)) };
+        assert_eq!(message.to_str().unwrap(), "invalid template");
+    }
+}
@@ -0,0 +1,163 @@
+use crate::Format;
+use core::fmt::Display;
+use std::collections::HashMap;
+
+/// A set of parsed templates indexed by message key and, optionally, by locale.
+///
+/// Messages added with [`insert`](Catalog::insert) do not belong to any locale
+/// and are used when a message is not found in the requested locale and its fallbacks.
+/// Unavailable in `no_std` environment.
+/// # Examples:
+/// ```rust
+/// use dyn_fmt::{Catalog, Format};
+/// let mut catalog = Catalog::new();
+/// catalog.insert("user.greeting", Format::parse("Hello, {}!").unwrap());
+/// catalog.insert_localized("de", "user.greeting", Format::parse("Hallo, {}!").unwrap());
+/// catalog.set_fallback("de-AT", "de");
+/// assert_eq!(catalog.format("user.greeting", &["Anna"]).unwrap(), "Hello, Anna!");
+/// assert_eq!(catalog.format_localized("de-AT", "user.greeting", &["Anna"]).unwrap(), "Hallo, Anna!");
+/// assert_eq!(catalog.format_localized("fr", "user.greeting", &["Anna"]).unwrap(), "Hello, Anna!");
+/// assert!(catalog.format("user.farewell", &["Anna"]).is_none());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Catalog {
+    messages: HashMap<String, Format>,
+    locales: HashMap<String, Locale>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Locale {
+    messages: HashMap<String, Format>,
+    fallback: Option<String>,
+}
+
+impl Catalog {
+    /// Creates an empty catalog.
+    pub fn new() -> Self { Self::default() }
+
+    /// Adds a message which does not belong to any locale, returning the replaced one, if any.
+    pub fn insert(&mut self, key: impl Into<String>, format: Format) -> Option<Format> {
+        self.messages.insert(key.into(), format)
+    }
+
+    /// Adds a message to the specified locale, returning the replaced one, if any.
+    pub fn insert_localized(&mut self, locale: impl Into<String>, key: impl Into<String>, format: Format) -> Option<Format> {
+        self.locales.entry(locale.into()).or_default().messages.insert(key.into(), format)
+    }
+
+    /// Removes a message which does not belong to any locale.
+    pub fn remove(&mut self, key: &str) -> Option<Format> {
+        self.messages.remove(key)
+    }
+
+    /// Removes a message from the specified locale.
+    pub fn remove_localized(&mut self, locale: &str, key: &str) -> Option<Format> {
+        self.locales.get_mut(locale)?.messages.remove(key)
+    }
+
+    /// Makes messages missing in `locale` to be looked up in `fallback` first.
+    /// Fallbacks are chained: if `fallback` has its own fallback, it is consulted next, and so on.
+    pub fn set_fallback(&mut self, locale: impl Into<String>, fallback: impl Into<String>) {
+        self.locales.entry(locale.into()).or_default().fallback = Some(fallback.into());
+    }
+
+    /// Returns a message which does not belong to any locale.
+    pub fn get(&self, key: &str) -> Option<&Format> {
+        let format = self.messages.get(key);
+        #[cfg(feature = "metrics")]
+        crate::metrics::record_lookup(format.is_some());
+        format
+    }
+
+    /// Returns a message from the specified locale only, or a message which does not belong to any locale,
+    /// if `locale` is `None`.
+    pub(crate) fn get_exact(&self, locale: Option<&str>, key: &str) -> Option<&Format> {
+        match locale {
+            None => self.messages.get(key),
+            Some(locale) => self.locales.get(locale)?.messages.get(key),
+        }
+    }
+
+    /// Returns a message for the specified locale, following the fallback chain
+    /// and finally resorting to messages which do not belong to any locale.
+    pub fn get_localized(&self, locale: &str, key: &str) -> Option<&Format> {
+        let mut locale = locale;
+        for _ in 0 ..= self.locales.len() {
+            let Some(l) = self.locales.get(locale) else { break; };
+            if let Some(format) = l.messages.get(key) {
+                #[cfg(feature = "metrics")]
+                crate::metrics::record_lookup(true);
+                return Some(format);
+            }
+            let Some(fallback) = &l.fallback else { break; };
+            locale = fallback;
+        }
+        self.get(key)
+    }
+
+    /// Renders a message which does not belong to any locale.
+    /// Returns `None` if there is no message with the key.
+    pub fn format<'a, T: Display + ?Sized + 'a>(
+        &self,
+        key: &str,
+        args: impl IntoIterator<Item=&'a T> + Clone
+    ) -> Option<String> {
+        Some(self.get(key)?.render(args))
+    }
+
+    /// Renders a message for the specified locale, see [`get_localized`](Catalog::get_localized).
+    /// Returns `None` if there is no message with the key.
+    pub fn format_localized<'a, T: Display + ?Sized + 'a>(
+        &self,
+        locale: &str,
+        key: &str,
+        args: impl IntoIterator<Item=&'a T> + Clone
+    ) -> Option<String> {
+        Some(self.get_localized(locale, key)?.render(args))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Catalog, Format};
+
+    fn format(s: &str) -> Format { Format::parse(s).unwrap() }
+
+    #[test]
+    fn fallback_chain() {
+        let mut catalog = Catalog::new();
+        catalog.insert("a", format("a{}"));
+        catalog.insert("b", format("b{}"));
+        catalog.insert("c", format("c{}"));
+        catalog.insert_localized("pt", "b", format("pt-b{}"));
+        catalog.insert_localized("pt", "c", format("pt-c{}"));
+        catalog.insert_localized("pt-BR", "c", format("pt-BR-c{}"));
+        catalog.set_fallback("pt-BR", "pt");
+        assert_eq!(catalog.format_localized("pt-BR", "a", &[1]).unwrap(), "a1");
+        assert_eq!(catalog.format_localized("pt-BR", "b", &[1]).unwrap(), "pt-b1");
+        assert_eq!(catalog.format_localized("pt-BR", "c", &[1]).unwrap(), "pt-BR-c1");
+        assert_eq!(catalog.format_localized("pt", "c", &[1]).unwrap(), "pt-c1");
+        assert!(catalog.format_localized("pt", "d", &[1]).is_none());
+    }
+
+    #[test]
+    fn fallback_cycle() {
+        let mut catalog = Catalog::new();
+        catalog.set_fallback("x", "y");
+        catalog.set_fallback("y", "x");
+        catalog.insert("a", format("a"));
+        assert_eq!(catalog.format_localized("x", "a", &[0]).unwrap(), "a");
+        assert!(catalog.format_localized("x", "b", &[0]).is_none());
+    }
+
+    #[test]
+    fn remove() {
+        let mut catalog = Catalog::new();
+        catalog.insert("a", format("a"));
+        catalog.insert_localized("en", "a", format("en-a"));
+        assert_eq!(catalog.remove_localized("en", "a").unwrap().as_str(), "en-a");
+        assert_eq!(catalog.format_localized("en", "a", &[0]).unwrap(), "a");
+        assert!(catalog.remove("a").is_some());
+        assert!(catalog.get_localized("en", "a").is_none());
+    }
+}
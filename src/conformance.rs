@@ -0,0 +1,147 @@
+//! Checks whether a template renders the same as the [`format!`](std::format) macro would.
+//!
+//! The crate follows the `format!` semantics for the common syntax, but extends it,
+//! and [`Arguments`] renders malformed templates leniently instead of rejecting them.
+//! [`check`] lists the differences for a template and its arguments,
+//! so a corpus of templates written for `format!` can be verified after upgrading the crate.
+//!
+//! Unavailable in `no_std` environment.
+
+use crate::{Align, Arguments, Format, ParseError, ParseOptions, Presentation};
+use crate::parse::{Token, Tokens};
+use core::fmt::{self, Display};
+use core::ops::Range;
+
+/// A difference between rendering a template with [`Arguments`] and with [`format!`](std::format).
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum Divergence {
+    /// `format!` rejects the template, while [`Arguments`] renders it leniently.
+    Rejected(ParseError),
+    /// `format!` rejects a brace which neither starts a placeholder nor is doubled,
+    /// while the crate drops it.
+    StrayBrace(Range<usize>),
+    /// The template uses syntax which `format!` does not have, e.g. a comment or the `url` presentation.
+    Extension {
+        /// The byte range of the fragment.
+        span: Range<usize>,
+        /// A short description of the syntax, e.g. `"comment"`.
+        syntax: &'static str,
+    },
+    /// The argument with the index is not used, which `format!` rejects.
+    UnusedArgument(usize),
+    /// A placeholder refers to the argument with the index, which is not provided.
+    /// `format!` rejects it, while the crate substitutes empty string.
+    MissingArgument(usize),
+    /// A placeholder refers to an argument by the name. `format!` captures such a variable,
+    /// which cannot be provided by positional arguments, so the crate substitutes empty string.
+    NamedArgument(String),
+    /// [`Arguments`] renders the template differently from `format!`.
+    Output {
+        /// The `format!` output.
+        expected: String,
+        /// The [`Arguments`] output.
+        actual: String,
+    },
+}
+
+impl Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Divergence::Rejected(e) => write!(f, "rejected by format!: {}", e),
+            Divergence::StrayBrace(span) => write!(f, "stray brace at {}", span.start),
+            Divergence::Extension { span, syntax } => write!(f, "{} at {} .. {} is not supported by format!", syntax, span.start, span.end),
+            Divergence::UnusedArgument(index) => write!(f, "argument {} is never used", index),
+            Divergence::MissingArgument(index) => write!(f, "argument {} is missing", index),
+            Divergence::NamedArgument(name) => write!(f, "named argument '{}' is not provided", name),
+            Divergence::Output { expected, actual } => write!(f, "expected {:?}, got {:?}", expected, actual),
+        }
+    }
+}
+
+/// Lists the differences between rendering the template with [`Arguments`]
+/// and with [`format!`](std::format) given the same arguments, in order of appearance.
+///
+/// The `format!` output is assembled by substituting each argument with the `format!` macro
+/// according to its spec. Templates `format!` would reject are reported only with
+/// [`Divergence::Rejected`], since there is no output to compare with.
+/// # Examples:
+/// ```rust
+/// use dyn_fmt::conformance::{check, Divergence};
+/// assert_eq!(check("{:>4}|{:+.1}", &[1.5, 2.0]), []);
+/// assert_eq!(check("{}{}", &[1]), [Divergence::MissingArgument(1)]);
+/// assert_eq!(check("{} {0}", &[1]), [Divergence::Output { expected: "1 1".into(), actual: "1 0".into() }]);
+/// ```
+pub fn check<T: Display>(fmt: &str, args: &[T]) -> Vec<Divergence> {
+    let format = match Format::parse(fmt) {
+        Ok(format) => format,
+        Err(e) => return vec![Divergence::Rejected(e)],
+    };
+    let mut res = Vec::new();
+    let mut end = 0;
+    for (span, token) in Tokens::explicit(fmt, ParseOptions::new()) {
+        if span.start > end && !matches!(&fmt[end .. span.start], "{" | "}") {
+            res.push(Divergence::Extension { span: end .. span.start, syntax: "raw block" });
+        }
+        end = span.end;
+        match token {
+            Token::Comment => res.push(Divergence::Extension { span, syntax: "comment" }),
+            Token::StrayBrace => res.push(Divergence::StrayBrace(span)),
+            Token::Arg(_, spec, counts) => {
+                if !counts.is_empty() {
+                    res.push(Divergence::Extension { span: span.clone(), syntax: "width or precision given by an argument" });
+                }
+                if spec.align() == Some(Align::Decimal) {
+                    res.push(Divergence::Extension { span: span.clone(), syntax: "decimal alignment" });
+                }
+                match spec.presentation() {
+                    None | Some(Presentation::LowerExp | Presentation::UpperExp | Presentation::Pointer) => { },
+                    Some(_) => res.push(Divergence::Extension { span, syntax: "presentation" }),
+                }
+            },
+            Token::Literal | Token::Escaped | Token::Error(_) => { },
+        }
+    }
+    if end != fmt.len() {
+        res.push(Divergence::Extension { span: end .. fmt.len(), syntax: "raw block" });
+    }
+    let (expected, report) = format.render_with_report(args);
+    res.extend(report.unused().iter().map(|&index| Divergence::UnusedArgument(index)));
+    res.extend(report.missing().iter().map(|&index| Divergence::MissingArgument(index)));
+    res.extend(report.missing_names().iter().map(|name| Divergence::NamedArgument(name.clone())));
+    let actual = Arguments::new(fmt, args).to_string();
+    if actual != expected {
+        res.push(Divergence::Output { expected, actual });
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, Divergence};
+    use crate::ParseErrorKind;
+
+    #[test]
+    fn conformance() {
+        assert_eq!(check("plain {{}} {:*^7.2} {:#?}", &[1.0]), [
+            Divergence::Rejected(crate::ParseError::new(ParseErrorKind::InvalidSpec, 20 .. 25)),
+        ]);
+        assert_eq!(check("{:*^7.2} {:08.3e} {:p}", &[1.0; 3]), []);
+        assert_eq!(check("{:>5}", &["ab", "cd"]), [Divergence::UnusedArgument(1)]);
+        assert_eq!(check("a}b {}", &[1]), [Divergence::StrayBrace(1 .. 2)]);
+        assert_eq!(check("{#x#}{% raw %}{}{% endraw %}{name}{:url}", &["a b"]), [
+            Divergence::Extension { span: 0 .. 5, syntax: "comment" },
+            Divergence::Extension { span: 5 .. 14, syntax: "raw block" },
+            Divergence::Extension { span: 16 .. 28, syntax: "raw block" },
+            Divergence::Extension { span: 34 .. 40, syntax: "presentation" },
+            Divergence::NamedArgument("name".into()),
+            Divergence::Output { expected: "{}a%20b".into(), actual: "{}name{:url".into() },
+        ]);
+        assert_eq!(check("{:d6.1} {:{1}}", &[1, 3]), [
+            Divergence::Extension { span: 0 .. 7, syntax: "decimal alignment" },
+            Divergence::Extension { span: 8 .. 14, syntax: "width or precision given by an argument" },
+            Divergence::Output { expected: "   1     3".into(), actual: "   1   :1}".into() },
+        ]);
+        assert_eq!(Divergence::UnusedArgument(2).to_string(), "argument 2 is never used");
+    }
+}
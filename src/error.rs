@@ -0,0 +1,160 @@
+use crate::{ArgSource, Catalog, Format};
+use core::fmt::{self, Display};
+
+/// An error, or any other value, with a message rendered from a template with named arguments,
+/// which may be overridden at runtime with a template from a [`Catalog`].
+///
+/// Usually implemented with the `DynError` derive macro, available with the `"derive"` feature,
+/// which also implements [`Display`] with [`fmt_message`](DynError::fmt_message),
+/// and [`Error`](std::error::Error). The macro takes the template from the `#[dyn_error("...")]` attribute
+/// of a struct or every enum variant. The template refers to named fields by name, and to tuple fields by index.
+/// The message key is the type name for a struct, and `Type.Variant` for an enum variant,
+/// unless specified as `#[dyn_error("...", key = "...")]`. Every field should implement [`Display`],
+/// except fields marked with `#[dyn_error(skip)]`,
+/// which are not passed, or passed as empty positional arguments to keep the indices of tuple fields.
+///
+/// Unavailable in `no_std` environment.
+/// # Examples:
+/// ```rust
+/// # #[cfg(feature = "derive")] {
+/// use dyn_fmt::{Catalog, DynError, Format};
+///
+/// #[derive(Debug, DynError)]
+/// enum QuotaError {
+///     #[dyn_error("user {user_id} exceeded quota {limit}")]
+///     Exceeded { user_id: u64, limit: u32 },
+///     #[dyn_error("unknown user {0}", key = "unknown_user")]
+///     Unknown(u64),
+/// }
+///
+/// let e = QuotaError::Exceeded { user_id: 7, limit: 100 };
+/// assert_eq!(e.to_string(), "user 7 exceeded quota 100");
+/// let mut catalog = Catalog::new();
+/// catalog.insert("QuotaError.Exceeded", Format::parse("quota of {limit} exceeded by #{user_id}").unwrap());
+/// assert_eq!(e.message_in(&catalog), "quota of 100 exceeded by #7");
+/// assert_eq!(QuotaError::Unknown(3).message_in(&catalog), "unknown user 3");
+/// # }
+/// ```
+pub trait DynError {
+    /// Returns the key of the message template in a [`Catalog`].
+    fn message_key(&self) -> &'static str;
+
+    /// Returns the default message template.
+    fn message_template(&self) -> &'static str;
+
+    /// Returns the message arguments.
+    fn message_args(&self) -> MessageArgs<'_>;
+
+    /// Renders the message with the default template.
+    ///
+    /// A malformed template is rendered as is, without substituting arguments.
+    fn fmt_message(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let template = self.message_template();
+        match Format::parse(template) {
+            Ok(format) => f.write_str(&format.render_from(self.message_args())),
+            Err(_) => f.write_str(template),
+        }
+    }
+
+    /// Renders the message with the template from the catalog, see [`Catalog::get`],
+    /// or with the default template, if the catalog has no message with the key.
+    fn message_in(&self, catalog: &Catalog) -> String {
+        match catalog.get(self.message_key()) {
+            Some(format) => format.render_from(self.message_args()),
+            None => DefaultMessage(self).to_string(),
+        }
+    }
+
+    /// Renders the message with the template from the catalog for the specified locale,
+    /// see [`Catalog::get_localized`], or with the default template, if the catalog has no message with the key.
+    fn message_localized(&self, catalog: &Catalog, locale: &str) -> String {
+        match catalog.get_localized(locale, self.message_key()) {
+            Some(format) => format.render_from(self.message_args()),
+            None => DefaultMessage(self).to_string(),
+        }
+    }
+}
+
+struct DefaultMessage<'a, E: DynError + ?Sized>(&'a E);
+
+impl<'a, E: DynError + ?Sized> Display for DefaultMessage<'a, E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { self.0.fmt_message(f) }
+}
+
+/// The positional and named arguments of a [`DynError`] message.
+///
+/// Unavailable in `no_std` environment.
+#[derive(Default)]
+pub struct MessageArgs<'a> {
+    positional: Vec<&'a dyn Display>,
+    named: Vec<(&'static str, &'a dyn Display)>,
+}
+
+impl<'a> MessageArgs<'a> {
+    /// Creates an empty argument list.
+    pub fn new() -> Self { Self::default() }
+
+    /// Appends the next positional argument.
+    pub fn push(&mut self, arg: &'a dyn Display) { self.positional.push(arg); }
+
+    /// Adds the named argument.
+    pub fn push_named(&mut self, name: &'static str, arg: &'a dyn Display) { self.named.push((name, arg)); }
+}
+
+impl<'a> ArgSource for MessageArgs<'a> {
+    type Arg = dyn Display + 'a;
+
+    fn len(&self) -> usize { self.positional.len() }
+
+    fn get(&self, index: usize) -> Option<&Self::Arg> { self.positional.get(index).copied() }
+
+    fn get_named(&self, name: &str) -> Option<&Self::Arg> {
+        self.named.iter().find(|x| x.0 == name).map(|x| x.1)
+    }
+
+    fn for_each_name(&self, f: &mut dyn FnMut(&str)) { self.named.iter().for_each(|x| f(x.0)) }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive() {
+        use crate::{Catalog, DynError, Format};
+        use core::fmt::{Debug, Display};
+
+        #[derive(Debug, DynError)]
+        #[dyn_error("{path}: {count} of {total}")]
+        struct Progress<'a, T: Display + Debug> {
+            path: &'a str,
+            count: T,
+            total: T,
+        }
+
+        #[derive(Debug, DynError)]
+        enum Error {
+            #[dyn_error("no access")]
+            Access,
+            #[dyn_error("{0} -> {2}", key = "moved")]
+            Moved(u32, #[dyn_error(skip)] (), String),
+            #[dyn_error("{type} x{}")]
+            Bad { r#type: char },
+        }
+
+        let e = Progress { path: "a.txt", count: 1, total: 3 };
+        assert_eq!(e.to_string(), "a.txt: 1 of 3");
+        assert_eq!(e.message_key(), "Progress");
+        assert_eq!(Error::Access.to_string(), "no access");
+        assert_eq!(Error::Access.message_key(), "Error.Access");
+        assert_eq!(Error::Moved(1, (), "b".into()).to_string(), "1 -> b");
+        assert_eq!(Error::Bad { r#type: 'q' }.to_string(), "q x");
+        let mut catalog = Catalog::new();
+        catalog.insert("moved", Format::parse("{2} <- {0}{1}").unwrap());
+        catalog.insert_localized("de", "Error.Access", Format::parse("kein Zugriff").unwrap());
+        assert_eq!(Error::Moved(1, (), "b".into()).message_in(&catalog), "b <- 1");
+        assert_eq!(Error::Access.message_in(&catalog), "no access");
+        assert_eq!(Error::Access.message_localized(&catalog, "de"), "kein Zugriff");
+        let e: Box<dyn std::error::Error> = Box::new(Error::Access);
+        assert_eq!(e.to_string(), "no access");
+    }
+}
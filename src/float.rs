@@ -0,0 +1,154 @@
+use core::fmt::{self, Display};
+use core::str;
+
+/// A float argument formatted with the [`ryu`](https://docs.rs/ryu) crate.
+///
+/// The output is the same as of the float [`Display`] implementation for every [`Spec`](crate::Spec),
+/// but the shortest representation of the number is found by `ryu`,
+/// which is smaller and faster than the algorithm used by `core`.
+/// Specs with a precision, as well as NaN and infinities, are formatted by `core`.
+///
+/// Available with the `"ryu"` feature.
+/// # Examples:
+/// ```rust
+/// use dyn_fmt::{Arguments, Float};
+/// let args = [Float(0.1 + 0.2), Float(1e21), Float(-2.5)];
+/// assert_eq!(Arguments::new("{} {} {:+07}", &args).to_string(), "0.30000000000000004 1000000000000000000000 -0002.5");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+pub struct Float<T>(pub T);
+
+/// A float argument formatted as the shortest string which parses back to the same number,
+/// e.g. `0.1`, `1e21`, or `5e-324`, as produced by [`ryu`](https://docs.rs/ryu).
+///
+/// Unlike [`Float`], it does not avoid the exponent notation, so the output is compact
+/// for numbers of any magnitude, which suits machine-readable output such as JSON or CSV.
+/// The precision of the spec is ignored, other flags are applied as for integers.
+/// NaN and infinities are formatted as `NaN`, `inf`, and `-inf`.
+///
+/// Available with the `"ryu"` feature.
+/// # Examples:
+/// ```rust
+/// use dyn_fmt::{Arguments, Shortest};
+/// let args = [Shortest(0.1 + 0.2), Shortest(1e21), Shortest(2.0)];
+/// assert_eq!(Arguments::new("{:.2} {} {:>5}", &args).to_string(), "0.30000000000000004 1e21   2.0");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+pub struct Shortest<T>(pub T);
+
+/// The longest positional representation of a float: `f64::MIN_POSITIVE` has 308 zeros after the point.
+const MAX_LEN: usize = 400;
+
+/// Converts the `ryu` output of a non-negative number, e.g. `1.5e-7`, into the positional notation, e.g. `0.00000015`.
+fn positional<'a>(s: &str, buf: &'a mut [u8; MAX_LEN]) -> &'a str {
+    let (mantissa, exp) = match s.find('e') {
+        Some(e) => (&s[.. e], s[e + 1 ..].parse::<isize>().unwrap()),
+        None => (s, 0),
+    };
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let mut digits = [0; 32];
+    let mut len = 0;
+    for b in int.bytes().chain(frac.bytes()) {
+        digits[len] = b;
+        len += 1;
+    }
+    let mut point = int.len() as isize + exp;
+    let leading = digits[.. len].iter().take_while(|&&b| b == b'0').count();
+    let digits = &digits[leading .. len];
+    point -= leading as isize;
+    let digits = &digits[.. digits.len() - digits.iter().rev().take_while(|&&b| b == b'0').count()];
+    let mut pos = 0;
+    let mut push = |b: u8| {
+        buf[pos] = b;
+        pos += 1;
+    };
+    if digits.is_empty() {
+        push(b'0');
+    } else if point <= 0 {
+        push(b'0');
+        push(b'.');
+        for _ in 0 .. -point { push(b'0'); }
+        digits.iter().for_each(|&b| push(b));
+    } else if point as usize >= digits.len() {
+        digits.iter().for_each(|&b| push(b));
+        for _ in digits.len() .. point as usize { push(b'0'); }
+    } else {
+        digits[.. point as usize].iter().for_each(|&b| push(b));
+        push(b'.');
+        digits[point as usize ..].iter().for_each(|&b| push(b));
+    }
+    str::from_utf8(&buf[.. pos]).unwrap()
+}
+
+macro_rules! float_display {
+    ($($t:ty),*) => {
+        $(
+            impl Display for Float<$t> {
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    if f.precision().is_some() || !self.0.is_finite() { return self.0.fmt(f); }
+                    let mut ryu = ryu::Buffer::new();
+                    let s = ryu.format_finite(self.0);
+                    let (non_negative, s) = match s.strip_prefix('-') {
+                        Some(s) => (false, s),
+                        None => (true, s),
+                    };
+                    f.pad_integral(non_negative, "", positional(s, &mut [0; MAX_LEN]))
+                }
+            }
+
+            impl Display for Shortest<$t> {
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    let mut ryu = ryu::Buffer::new();
+                    let s = ryu.format(self.0);
+                    let (non_negative, s) = match s.strip_prefix('-') {
+                        Some(s) => (false, s),
+                        None => (true, s),
+                    };
+                    f.pad_integral(non_negative, "", s)
+                }
+            }
+        )*
+    };
+}
+
+float_display!(f32, f64);
+
+#[cfg(test)]
+mod tests {
+    use crate::{Arguments, Float, Shortest};
+
+    #[test]
+    fn positional() {
+        let mut buf = [0; super::MAX_LEN];
+        for (ryu, expected) in [
+            ("0.0", "0"), ("1.0", "1"), ("1.5e-7", "0.00000015"), ("1e16", "10000000000000000"),
+            ("123.456", "123.456"), ("1.2345e2", "123.45"), ("1e-5", "0.00001"), ("0.001", "0.001"),
+        ] {
+            assert_eq!(super::positional(ryu, &mut buf), expected);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn shortest() {
+        let args = [Shortest(1.5e-7), Shortest(-0.0), Shortest(f64::MAX), Shortest(f64::NAN), Shortest(f64::NEG_INFINITY)];
+        assert_eq!(Arguments::new("{} {:+} {:.1} {} {}", &args).to_string(), "1.5e-7 -0.0 1.7976931348623157e308 NaN -inf");
+        assert_eq!(Arguments::new("{:+08}|{:*^7}", &[Shortest(0.1f32), Shortest(1e30f32)]).to_string(), "+00000.1|*1e30**");
+        for value in [0.1 + 0.2, 5e-324, 123456.789, -1e-300] {
+            let s = Arguments::new("{:.0}", &[Shortest(value)]).to_string();
+            assert_eq!(s.parse::<f64>().unwrap(), value);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn conformance() {
+        for value in [0.0, -0.0, 1.0, -2.5, 0.1 + 0.2, 1e21, 1.5e-7, f64::MAX, f64::MIN_POSITIVE, 5e-324, f64::NAN, f64::INFINITY] {
+            for spec in ["", "+", "08", "+08", "*^12", "<6", "#", ".3", "*>+10.2"] {
+                let fmt = format!("{{:{}}}", spec);
+                assert_eq!(Arguments::new(&fmt, &[Float(value)]).to_string(), Arguments::new(&fmt, &[value]).to_string(), "{} {:?}", fmt, value);
+                assert_eq!(Arguments::new(&fmt, &[Float(value as f32)]).to_string(), Arguments::new(&fmt, &[value as f32]).to_string(), "{} {:?}", fmt, value);
+            }
+        }
+    }
+}
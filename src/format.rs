@@ -0,0 +1,1411 @@
+use crate::{ArgRef, ArgSource, FmtEvent, FormatOptions, ParseOptions, Segment, SyntaxEvent, SyntaxFeature, Tokenizer, to_string_measured};
+use crate::parse::{Numbering, ParseError, Token, Tokens, is_name, unescape};
+use crate::spec::{Spec, WithSpec, count_arg};
+use core::fmt::{self, Display, Write};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use core::ops::Range;
+use core::str::FromStr;
+
+/// A parsed format string, which can be rendered many times with different arguments.
+///
+/// In contrast with [`Arguments`](crate::Arguments), which renders any format string leniently,
+/// `Format` reports malformed fragments (such as an unterminated comment) when it is created.
+///
+/// `Format` is immutable and `Send + Sync`, and its parts are reference-counted,
+/// so cloning it is cheap. Parse templates once, e.g. at startup,
+/// and render them concurrently from any number of threads.
+/// Unavailable in `no_std` environment.
+/// # Examples:
+/// ```rust
+/// use dyn_fmt::Format;
+/// use std::thread;
+/// let format = Format::parse("worker {}").unwrap();
+/// let workers: Vec<_> = (0 .. 4).map(|i| {
+///     let format = format.clone();
+///     thread::spawn(move || format.render(&[i]))
+/// }).collect();
+/// let names: Vec<String> = workers.into_iter().map(|x| x.join().unwrap()).collect();
+/// assert_eq!(names, ["worker 0", "worker 1", "worker 2", "worker 3"]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Format {
+    fmt: Arc<str>,
+    /// Literal text with escape sequences replaced, or the format string itself if they are not enabled.
+    text: Arc<str>,
+    pieces: Arc<[Piece]>,
+    /// The format string range of each piece.
+    spans: Arc<[Range<usize>]>,
+    options: ParseOptions,
+}
+
+#[derive(Clone, Debug)]
+enum Piece {
+    /// A range of `Format::text`.
+    Literal(Range<usize>),
+    Arg(Arg, Spec, Counts),
+}
+
+/// A placeholder argument, with `{}` resolved to the index.
+#[derive(Clone, Debug)]
+enum Arg {
+    Index(usize),
+    Name(Range<usize>),
+}
+
+/// The arguments giving the width and the precision of a placeholder.
+#[derive(Clone, Debug, Default)]
+struct Counts {
+    width: Option<Arg>,
+    precision: Option<Arg>,
+}
+
+/// Sets the width and the precision given by arguments.
+/// Arguments which are not provided or are not non-negative integers are ignored.
+fn resolve_spec<'x, A: Display + ?Sized + 'x>(spec: &Spec, counts: &Counts, mut get: impl FnMut(&Arg) -> Option<&'x A>) -> Spec {
+    let mut spec = *spec;
+    if let Some(width) = counts.width.as_ref().and_then(&mut get).and_then(count_arg) { spec.set_width(Some(width)); }
+    if let Some(precision) = counts.precision.as_ref().and_then(&mut get).and_then(count_arg) {
+        spec.set_precision(Some(precision));
+    }
+    spec
+}
+
+/// Provides random access to arguments given by an iterator,
+/// which is cheap if arguments are accessed sequentially.
+pub(crate) struct Positional<I: IntoIterator + Clone> {
+    args: I,
+    iter: I::IntoIter,
+    index: usize,
+}
+
+impl<I: IntoIterator + Clone> Positional<I> {
+    pub(crate) fn new(args: I) -> Self {
+        Positional { iter: args.clone().into_iter(), args, index: 0 }
+    }
+
+    pub(crate) fn get(&mut self, index: usize) -> Option<I::Item> {
+        if index != self.index {
+            self.iter = self.args.clone().into_iter();
+            self.index = index;
+            if index != 0 {
+                self.iter.nth(index - 1)?;
+            }
+        }
+        self.index += 1;
+        self.iter.next()
+    }
+}
+
+impl Format {
+    /// Parses a format string.
+    ///
+    /// In addition to the syntax supported by [`Arguments`](crate::Arguments),
+    /// placeholders can refer to arguments explicitly, by index (`{0}`, `{1:>5}`) or by name (`{name}`).
+    /// As in the [`format!`](std::format) macro, such placeholders do not affect which argument
+    /// the next `{}` placeholder refers to. See [`ArgRef`] for the rules for names.
+    /// # Examples:
+    /// ```rust
+    /// use dyn_fmt::Format;
+    /// let format = Format::parse("{}a{# a comment #}{}b{0}").unwrap();
+    /// assert_eq!(format.render(&[1, 2]), "1a2b1");
+    /// assert!(Format::parse("{}a{# unterminated").is_err());
+    /// assert!(Format::parse("{1st}").is_err());
+    /// ```
+    pub fn parse(fmt: impl Into<String>) -> Result<Self, ParseError> {
+        Format::parse_with(fmt, ParseOptions::new())
+    }
+
+    /// Parses a format string with the specified strictness.
+    /// # Examples:
+    /// ```rust
+    /// use dyn_fmt::{Format, ParseOptions};
+    /// assert!(Format::parse("{0 :>3 }").is_err());
+    /// let format = Format::parse_with("{ 0 :>3 }", ParseOptions::new().allow_whitespace(true)).unwrap();
+    /// assert_eq!(format.render(&[1]), "  1");
+    /// let format = Format::parse_with(r"{}\t{}\u{2192}", ParseOptions::new().allow_escapes(true)).unwrap();
+    /// assert_eq!(format.render(&[1, 2]), "1\t2\u{2192}");
+    /// ```
+    pub fn parse_with(fmt: impl Into<String>, options: ParseOptions) -> Result<Self, ParseError> {
+        #[cfg(feature = "metrics")]
+        crate::metrics::record_parse();
+        let fmt = fmt.into();
+        let mut text = String::new();
+        let mut pieces = Vec::new();
+        let mut spans = Vec::new();
+        let mut next = 0;
+        let mut numbering = Numbering::default();
+        let to_arg = |arg: ArgRef, next: &mut usize| match arg {
+            ArgRef::Next => {
+                *next += 1;
+                Arg::Index(*next - 1)
+            },
+            ArgRef::Index(index) => Arg::Index(index),
+            ArgRef::Name(name) => {
+                let start = name.as_ptr() as usize - fmt.as_ptr() as usize;
+                Arg::Name(start .. start + name.len())
+            },
+        };
+        for (span, token) in Tokens::explicit(&fmt, options) {
+            let count = pieces.len();
+            match token {
+                Token::Literal if options.allows_escapes() => {
+                    text.push_str(&fmt[span.clone()]);
+                    pieces.push(Piece::Literal(text.len() - span.len() .. text.len()));
+                },
+                Token::Literal => pieces.push(Piece::Literal(span.clone())),
+                Token::Escaped => {
+                    let start = text.len();
+                    unescape(&fmt[span.clone()], &mut text);
+                    pieces.push(Piece::Literal(start .. text.len()));
+                },
+                Token::Arg(arg, spec, counts) => {
+                    for arg in [Some(arg), counts.width, counts.precision].iter().flatten() {
+                        numbering.check(arg, &options).map_err(|kind| ParseError::new(kind, span.clone()))?;
+                    }
+                    let arg = to_arg(arg, &mut next);
+                    let counts = Counts {
+                        width: counts.width.map(|x| to_arg(x, &mut next)),
+                        precision: counts.precision.map(|x| to_arg(x, &mut next)),
+                    };
+                    pieces.push(Piece::Arg(arg, spec, counts));
+                },
+                Token::Comment | Token::StrayBrace => { },
+                Token::Error(kind) => return Err(ParseError::new(kind, span)),
+            }
+            if pieces.len() != count { spans.push(span); }
+        }
+        let fmt: Arc<str> = fmt.into();
+        let text = if options.allows_escapes() { text.into() } else { fmt.clone() };
+        Ok(Format { fmt, text, pieces: pieces.into(), spans: spans.into(), options })
+    }
+
+    /// Returns the source format string.
+    pub fn as_str(&self) -> &str { &self.fmt }
+
+    /// Returns the options the format string was parsed with.
+    pub fn options(&self) -> ParseOptions { self.options }
+
+    /// Combines the format with arguments into a [`Display`]able structure.
+    /// Extra arguments are ignored, missing arguments are replaced by empty string.
+    ///
+    /// The arguments are substituted according to [`FormatOptions::current_default`].
+    pub fn args<'a, T: Display + ?Sized + 'a, I: IntoIterator<Item=&'a T> + Clone>(
+        &self,
+        args: I
+    ) -> FormatArguments<'_, 'a, T, I> {
+        FormatArguments { format: self, args, options: FormatOptions::current_default() }
+    }
+
+    /// Creates a [`String`] replacing the placeholders using provided parameters in the order given.
+    /// Placeholders referring to arguments by name are replaced by empty string,
+    /// use [`render_from`](Format::render_from) to provide named arguments.
+    pub fn render<'a, T: Display + ?Sized + 'a>(&self, args: impl IntoIterator<Item=&'a T> + Clone) -> String {
+        to_string_measured(&self.args(args))
+    }
+
+    /// Creates a [`String`] replacing the placeholders using arguments from an [`ArgSource`],
+    /// which can provide named arguments in addition to positional ones.
+    ///
+    /// The arguments are substituted according to [`FormatOptions::current_default`],
+    /// the same as in the other `render_*` methods.
+    /// # Examples:
+    /// ```rust
+    /// use dyn_fmt::Format;
+    /// use std::collections::HashMap;
+    /// let args = HashMap::from([("user", "Anna"), ("count", "3")]);
+    /// let format = Format::parse("{user} has {count:>2} new messages").unwrap();
+    /// assert_eq!(format.render_from(&args), "Anna has  3 new messages");
+    /// ```
+    pub fn render_from(&self, args: impl ArgSource) -> String {
+        self.render_with_report(args).0
+    }
+
+    /// Same as [`render_from`](Format::render_from), but renders into a string allocated once and zeroed on drop,
+    /// for output holding secrets, e.g. tokens in webhook URLs or one-time codes.
+    ///
+    /// Buffers allocated by the arguments' own [`Display`] implementations are not scrubbed,
+    /// as well as those allocated by presentations, e.g. `{:url}`.
+    ///
+    /// Available with the `"zeroize"` feature.
+    /// # Examples:
+    /// ```rust
+    /// use dyn_fmt::Format;
+    /// use std::collections::HashMap;
+    /// let format = Format::parse("Your code is {code:>6}").unwrap();
+    /// let message = format.render_zeroizing(HashMap::from([("code", 9137)]));
+    /// assert_eq!(message.as_str(), "Your code is   9137");
+    /// ```
+    #[cfg(feature = "zeroize")]
+    pub fn render_zeroizing(&self, args: impl ArgSource) -> zeroize::Zeroizing<String> {
+        crate::to_scratch(&SourceArguments::new(self, args))
+    }
+
+    fn name(&self, span: &Range<usize>) -> &str { &self.fmt[span.clone()] }
+
+    fn write_arg(&self, res: &mut String, arg: &Arg) {
+        match *arg {
+            Arg::Index(index) => write!(res, "{}", index).unwrap(),
+            Arg::Name(ref span) => res.push_str(self.name(span)),
+        }
+    }
+
+    /// Returns the arguments of all placeholders, including widths and precisions given by arguments.
+    fn placeholder_args(&self) -> impl Iterator<Item=&Arg> + '_ {
+        self.pieces.iter().flat_map(|x| match x {
+            Piece::Literal(_) => [None, None, None],
+            Piece::Arg(arg, _, counts) => [Some(arg), counts.width.as_ref(), counts.precision.as_ref()],
+        }).flatten()
+    }
+
+    /// Returns the parsed template, with `{}` placeholders resolved to [`ArgRef::Index`].
+    pub(crate) fn segments(&self) -> impl Iterator<Item=Segment<'_>> + '_ {
+        self.pieces.iter().map(|piece| match piece {
+            Piece::Literal(span) => Segment::Literal(&self.text[span.clone()]),
+            Piece::Arg(arg, spec, counts) => {
+                let counts = crate::Counts {
+                    width: counts.width.as_ref().map(|x| self.arg_ref(x)),
+                    precision: counts.precision.as_ref().map(|x| self.arg_ref(x)),
+                };
+                Segment::Arg(self.arg_ref(arg), *spec, counts)
+            },
+        })
+    }
+
+    /// Same as [`render_from`](Format::render_from), but also reports which arguments were used.
+    /// Takes an [`ArgSource`], so the report can refer to arguments by index.
+    /// # Examples:
+    /// ```rust
+    /// use dyn_fmt::Format;
+    /// let (s, report) = Format::parse("{}a{}b").unwrap().render_with_report([1, 2, 3, 4]);
+    /// assert_eq!(s, "1a2b");
+    /// assert_eq!(report.used(), &[0, 1]);
+    /// assert_eq!(report.unused(), &[2, 3]);
+    /// ```
+    pub fn render_with_report(&self, args: impl ArgSource) -> (String, RenderReport) {
+        self.render_traced(args, |_, _| { })
+    }
+
+    /// Same as [`render_from`](Format::render_from), but also returns the [`SourceMap`]
+    /// telling which parts of the output come from literal text and which from arguments,
+    /// e.g. to highlight substituted values in a preview.
+    /// # Examples:
+    /// ```rust
+    /// use dyn_fmt::{Format, SpanOrigin};
+    /// use std::collections::HashMap;
+    /// let format = Format::parse("Hi, {name}! {:>3}").unwrap();
+    /// let (s, map) = format.render_with_map(HashMap::from([("name", "Ann")]));
+    /// assert_eq!(s, "Hi, Ann! ");
+    /// let span = map.span_at(5).unwrap();
+    /// assert_eq!((span.output(), span.template()), (4 .. 7, 4 .. 10));
+    /// assert_eq!(span.origin(), &SpanOrigin::Named("name".to_string()));
+    /// assert_eq!(map.span_at(7).unwrap().origin(), &SpanOrigin::Literal);
+    /// ```
+    pub fn render_with_map(&self, args: impl ArgSource) -> (String, SourceMap) {
+        let mut spans = Vec::with_capacity(self.pieces.len());
+        let (res, _) = self.render_traced(args, |piece, output| {
+            let origin = match &self.pieces[piece] {
+                Piece::Literal(_) => SpanOrigin::Literal,
+                &Piece::Arg(Arg::Index(index), _, _) => SpanOrigin::Arg(index),
+                Piece::Arg(Arg::Name(span), _, _) => SpanOrigin::Named(self.name(span).to_string()),
+            };
+            spans.push(MappedSpan { output, template: self.spans[piece].clone(), origin });
+        });
+        (res, SourceMap { spans })
+    }
+
+    /// Renders the template, calling `trace` with the index of each piece and the output range it produced.
+    fn render_traced(&self, args: impl ArgSource, mut trace: impl FnMut(usize, Range<usize>)) -> (String, RenderReport) {
+        let options = FormatOptions::current_default();
+        let mut used = vec![false; args.len()];
+        let mut report = RenderReport {
+            used: Vec::new(),
+            unused: Vec::new(),
+            missing: Vec::new(),
+            used_names: Vec::new(),
+            unused_names: Vec::new(),
+            missing_names: Vec::new(),
+        };
+        let mut res = String::new();
+        let mut get = |arg: &Arg| match *arg {
+            Arg::Index(index) => {
+                let arg = args.get(index);
+                if arg.is_none() {
+                    options.report(FmtEvent::MissingArgument(ArgRef::Index(index)));
+                    report.missing.push(index);
+                } else if let Some(used) = used.get_mut(index) {
+                    if !*used {
+                        *used = true;
+                        report.used.push(index);
+                    }
+                } else if !report.used.contains(&index) {
+                    report.used.push(index);
+                }
+                arg
+            },
+            Arg::Name(ref span) => {
+                let name = self.name(span);
+                let arg = args.get_named(name);
+                if arg.is_none() {
+                    options.report(FmtEvent::MissingArgument(ArgRef::Name(name)));
+                    report.missing_names.push(name.to_string());
+                } else if !report.used_names.iter().any(|x| x == name) {
+                    report.used_names.push(name.to_string());
+                }
+                arg
+            },
+        };
+        for (i, piece) in self.pieces.iter().enumerate() {
+            let start = res.len();
+            match piece {
+                Piece::Literal(span) => res.push_str(&self.text[span.clone()]),
+                Piece::Arg(arg, spec, counts) => {
+                    let spec = resolve_spec(spec, counts, &mut get);
+                    if let Some(arg) = get(arg) {
+                        write!(res, "{}", WithOptions { arg, spec: &spec, options: &options }).unwrap();
+                    }
+                },
+            }
+            trace(i, start .. res.len());
+        }
+        report.unused = (0 .. args.len()).filter(|&i| !used[i]).collect();
+        args.for_each_name(&mut |name| if !report.used_names.iter().any(|x| x == name) {
+            report.unused_names.push(name.to_string());
+        });
+        report.unused_names.sort();
+        report.unused_names.dedup();
+        (res, report)
+    }
+
+    /// Returns the template with named placeholders renamed according to `map`,
+    /// which pairs old names with new ones, e.g. to migrate a catalog after data model fields are renamed.
+    /// Names are replaced in widths and precisions given by arguments as well.
+    /// The rest of the format string, including comments, raw blocks, and the spelling of specs, is kept as is.
+    /// # Panics
+    ///
+    /// Panics if a new name is not a valid argument name, see [`ArgRef::Name`].
+    /// # Examples:
+    /// ```rust
+    /// use dyn_fmt::Format;
+    /// let format = Format::parse("{user:>{width}} {# user is shown #}{0} {count}").unwrap();
+    /// let renamed = format.rename(&[("user", "login"), ("width", "w"), ("missing", "x")]);
+    /// assert_eq!(renamed.as_str(), "{login:>{w}} {# user is shown #}{0} {count}");
+    /// ```
+    pub fn rename(&self, map: &[(&str, &str)]) -> Format {
+        for &(_, new) in map {
+            assert!(is_name(new), "invalid argument name `{}`", new);
+        }
+        let mut res = String::with_capacity(self.fmt.len());
+        let mut pos = 0;
+        for (_, token) in Tokens::explicit(&self.fmt, self.options) {
+            let Token::Arg(arg, _, counts) = token else { continue; };
+            for arg in [Some(arg), counts.width, counts.precision].into_iter().flatten() {
+                let ArgRef::Name(name) = arg else { continue; };
+                let Some(&(_, new)) = map.iter().find(|&&(old, _)| old == name) else { continue; };
+                let start = name.as_ptr() as usize - self.fmt.as_ptr() as usize;
+                res.push_str(&self.fmt[pos .. start]);
+                res.push_str(new);
+                pos = start + name.len();
+            }
+        }
+        res.push_str(&self.fmt[pos ..]);
+        Format::parse_with(res, self.options).expect("renamed template is valid")
+    }
+
+    /// Compares the template with another one structurally, e.g. to summarize changes in a review.
+    ///
+    /// Placeholders are matched by the arguments they refer to, keeping their order,
+    /// so a placeholder can be reported as added, removed, or changed, i.e. having a different spec.
+    /// Literal text between matched placeholders is compared after escape sequences are replaced,
+    /// and comments are ignored. Changes are listed in template order.
+    /// # Examples:
+    /// ```rust
+    /// use dyn_fmt::{Format, TemplateChange};
+    /// let old = Format::parse("Hello, {name}! You have {count} messages.").unwrap();
+    /// let new = Format::parse("Hi, {name}! You have {count:>3} messages from {sender}.").unwrap();
+    /// assert_eq!(old.diff(&new), [
+    ///     TemplateChange::LiteralEdited { old: 1 .. 5, new: 1 .. 2 },
+    ///     TemplateChange::Changed { old: 24 .. 31, new: 21 .. 31 },
+    ///     TemplateChange::LiteralEdited { old: 40 .. 40, new: 40 .. 54 },
+    ///     TemplateChange::Added { new: 46 .. 54 },
+    /// ]);
+    /// ```
+    pub fn diff(&self, other: &Format) -> Vec<TemplateChange> {
+        let this = self.placeholder_pieces();
+        let that = other.placeholder_pieces();
+        let same = |i: usize, j: usize| self.piece_arg(this[i]) == other.piece_arg(that[j]);
+        let width = that.len() + 1;
+        let mut lcs = vec![0usize; (this.len() + 1) * width];
+        for i in (0 .. this.len()).rev() {
+            for j in (0 .. that.len()).rev() {
+                lcs[i * width + j] = if same(i, j) {
+                    lcs[(i + 1) * width + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+                };
+            }
+        }
+        let mut changes = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        let (mut old_prev, mut new_prev) = (None, None);
+        loop {
+            let (old_gap, new_gap) = (i, j);
+            while i < this.len() || j < that.len() {
+                if i < this.len() && j < that.len() && same(i, j) { break; }
+                if j == that.len() || i < this.len() && lcs[(i + 1) * width + j] >= lcs[i * width + j + 1] {
+                    i += 1;
+                } else {
+                    j += 1;
+                }
+            }
+            let (old_next, new_next) = (this.get(i).copied(), that.get(j).copied());
+            changes.extend(this[old_gap .. i].iter().map(|&x| TemplateChange::Removed { old: self.spans[x].clone() }));
+            let literal_change = self.literal_change(old_prev, old_next, other, new_prev, new_next);
+            let added = that[new_gap .. j].iter().map(|&x| TemplateChange::Added { new: other.spans[x].clone() });
+            changes.extend(literal_change.into_iter().chain(added));
+            let (Some(old_next), Some(new_next)) = (old_next, new_next) else { break; };
+            if self.piece_shape(old_next) != other.piece_shape(new_next) {
+                changes.push(TemplateChange::Changed { old: self.spans[old_next].clone(), new: other.spans[new_next].clone() });
+            }
+            (old_prev, new_prev) = (Some(old_next), Some(new_next));
+            i += 1;
+            j += 1;
+        }
+        changes
+    }
+
+    /// Returns the optional syntax the template relies on,
+    /// so an application can reject templates using syntax it does not support.
+    /// # Examples:
+    /// ```rust
+    /// use dyn_fmt::{Format, SyntaxFeature};
+    /// let format = Format::parse("{# greeting #}Hello, {name:>{width}}! {0:.2e}").unwrap();
+    /// assert_eq!(format.required_features().into_iter().collect::<Vec<_>>(), [
+    ///     SyntaxFeature::ExplicitIndex,
+    ///     SyntaxFeature::Named,
+    ///     SyntaxFeature::CountArgs,
+    ///     SyntaxFeature::Presentation,
+    ///     SyntaxFeature::Comment,
+    /// ]);
+    /// assert!(Format::parse("{} of {:>5}").unwrap().required_features().is_empty());
+    /// ```
+    pub fn required_features(&self) -> BTreeSet<SyntaxFeature> {
+        let mut features = BTreeSet::new();
+        let mut depth = 0;
+        for (span, event) in Tokenizer::new(&self.fmt, self.options) {
+            let text = &self.fmt[span];
+            let feature = match event {
+                SyntaxEvent::Open => { depth += 1; None },
+                SyntaxEvent::Close => { depth -= 1; None },
+                SyntaxEvent::Index | SyntaxEvent::Name if depth > 1 => Some(SyntaxFeature::CountArgs),
+                SyntaxEvent::Index => Some(SyntaxFeature::ExplicitIndex),
+                SyntaxEvent::Name => Some(SyntaxFeature::Named),
+                SyntaxEvent::Presentation => Some(SyntaxFeature::Presentation),
+                SyntaxEvent::Align if text == "d" => Some(SyntaxFeature::DecimalAlign),
+                SyntaxEvent::Escape if text.starts_with('\\') => Some(SyntaxFeature::EscapeSequence),
+                SyntaxEvent::Comment => Some(SyntaxFeature::Comment),
+                SyntaxEvent::RawOpen => Some(SyntaxFeature::RawBlock),
+                SyntaxEvent::Whitespace => Some(SyntaxFeature::Whitespace),
+                SyntaxEvent::Unknown => Some(SyntaxFeature::UnknownFlags),
+                SyntaxEvent::StrayBrace => Some(SyntaxFeature::StrayBrace),
+                _ => None,
+            };
+            features.extend(feature);
+        }
+        features
+    }
+
+    fn placeholder_pieces(&self) -> Vec<usize> {
+        (0 .. self.pieces.len()).filter(|&i| matches!(self.pieces[i], Piece::Arg(..))).collect()
+    }
+
+    fn arg_ref(&self, arg: &Arg) -> ArgRef<'_> {
+        match *arg {
+            Arg::Index(index) => ArgRef::Index(index),
+            Arg::Name(ref span) => ArgRef::Name(self.name(span)),
+        }
+    }
+
+    fn piece_arg(&self, piece: usize) -> Option<ArgRef<'_>> {
+        match &self.pieces[piece] {
+            Piece::Literal(_) => None,
+            Piece::Arg(arg, ..) => Some(self.arg_ref(arg)),
+        }
+    }
+
+    /// The spec of a placeholder with the width and the precision given by arguments.
+    fn piece_shape(&self, piece: usize) -> Option<(Spec, Option<ArgRef<'_>>, Option<ArgRef<'_>>)> {
+        match &self.pieces[piece] {
+            Piece::Literal(_) => None,
+            Piece::Arg(_, spec, counts) => Some((
+                *spec,
+                counts.width.as_ref().map(|x| self.arg_ref(x)),
+                counts.precision.as_ref().map(|x| self.arg_ref(x)),
+            )),
+        }
+    }
+
+    /// Compares the literal text between two placeholders (or the template boundaries) with the other template,
+    /// returning the differing source ranges.
+    fn literal_change(
+        &self,
+        prev: Option<usize>,
+        next: Option<usize>,
+        other: &Format,
+        other_prev: Option<usize>,
+        other_next: Option<usize>
+    ) -> Option<TemplateChange> {
+        let text = |format: &Format, prev: Option<usize>, next: Option<usize>| {
+            let pieces = prev.map_or(0, |x| x + 1) .. next.unwrap_or(format.pieces.len());
+            format.pieces[pieces].iter().filter_map(|x| match x {
+                Piece::Literal(span) => Some(&format.text[span.clone()]),
+                Piece::Arg(..) => None,
+            }).collect::<String>()
+        };
+        if text(self, prev, next) == text(other, other_prev, other_next) { return None; }
+        let source = |format: &Format, prev: Option<usize>, next: Option<usize>| {
+            prev.map_or(0, |x| format.spans[x].end) .. next.map_or(format.fmt.len(), |x| format.spans[x].start)
+        };
+        let (old, new) = (source(self, prev, next), source(other, other_prev, other_next));
+        let (old_text, new_text) = (&self.fmt[old.clone()], &other.fmt[new.clone()]);
+        let prefix: usize = old_text.chars().zip(new_text.chars()).take_while(|(a, b)| a == b).map(|(a, _)| a.len_utf8()).sum();
+        let suffix: usize = old_text[prefix ..].chars().rev().zip(new_text[prefix ..].chars().rev())
+            .take_while(|(a, b)| a == b).map(|(a, _)| a.len_utf8()).sum();
+        Some(TemplateChange::LiteralEdited {
+            old: old.start + prefix .. old.end - suffix,
+            new: new.start + prefix .. new.end - suffix,
+        })
+    }
+
+    fn arg_indices(&self) -> BTreeSet<usize> {
+        self.placeholder_args().filter_map(|x| match *x { Arg::Index(index) => Some(index), _ => None }).collect()
+    }
+
+    fn arg_names(&self) -> BTreeSet<&str> {
+        self.placeholder_args().filter_map(|x| match x { Arg::Name(span) => Some(self.name(span)), _ => None }).collect()
+    }
+
+    /// Checks that `other` uses the same arguments as `self`,
+    /// e.g. that a translated template does not drop or add placeholders.
+    /// # Examples:
+    /// ```rust
+    /// use dyn_fmt::{Format, Mismatch};
+    /// let source = Format::parse("{} files in {}").unwrap();
+    /// assert!(source.compatible_with(&Format::parse("{} fichiers dans {}").unwrap()).is_ok());
+    /// assert_eq!(
+    ///     source.compatible_with(&Format::parse("{} fichiers").unwrap()),
+    ///     Err(vec![Mismatch::Missing { index: 1 }])
+    /// );
+    /// ```
+    pub fn compatible_with(&self, other: &Format) -> Result<(), Vec<Mismatch>> {
+        let (this, this_names) = (self.arg_indices(), self.arg_names());
+        let (other, other_names) = (other.arg_indices(), other.arg_names());
+        let mut mismatches: Vec<Mismatch> = this.difference(&other).map(|&index| Mismatch::Missing { index }).collect();
+        mismatches.extend(other.difference(&this).map(|&index| Mismatch::Extra { index }));
+        mismatches.extend(this_names.difference(&other_names).map(|&name| Mismatch::MissingName { name: name.to_string() }));
+        mismatches.extend(other_names.difference(&this_names).map(|&name| Mismatch::ExtraName { name: name.to_string() }));
+        if mismatches.is_empty() { Ok(()) } else { Err(mismatches) }
+    }
+
+    /// Replaces one class of placeholders, keeping the others, and returns the resulting template,
+    /// parsed with the same options. Literal text and substituted values are escaped,
+    /// so that the next stage outputs them as is. Kept placeholders refer to arguments explicitly,
+    /// e.g. the second `{}` becomes `{1}`. Placeholders whose argument is not provided are kept too.
+    /// The width and the precision given by arguments of the replaced class are resolved even in kept placeholders,
+    /// and a placeholder is replaced only if all its arguments are resolved.
+    /// # Examples:
+    /// ```rust
+    /// use dyn_fmt::{Format, Substitution};
+    /// use std::collections::HashMap;
+    /// let format = Format::parse("{host}: {} {{{}}}").unwrap();
+    /// let stage = format.substitute(HashMap::from([("host", "srv{1}")]), Substitution::Named);
+    /// assert_eq!(stage.as_str(), "srv{{1}}: {0} {{{1}}}");
+    /// assert_eq!(stage.render(&["up", "ok"]), "srv{1}: up {ok}");
+    /// ```
+    pub fn substitute(&self, args: impl ArgSource, only: Substitution) -> Format {
+        let escapes = self.options.allows_escapes();
+        let get = |arg: &Arg| match (arg, only) {
+            (&Arg::Index(index), Substitution::Positional) => args.get(index),
+            (Arg::Name(span), Substitution::Named) => args.get_named(self.name(span)),
+            _ => None,
+        };
+        let mut res = String::new();
+        for piece in self.pieces.iter() {
+            let (arg, spec, counts) = match piece {
+                Piece::Literal(span) => {
+                    Escaper { res: &mut res, escapes }.write_str(&self.text[span.clone()]).unwrap();
+                    continue;
+                },
+                Piece::Arg(arg, spec, counts) => (arg, spec, counts),
+            };
+            let spec = resolve_spec(spec, counts, get);
+            let width = counts.width.as_ref().filter(|_| spec.width().is_none());
+            let precision = counts.precision.as_ref().filter(|_| spec.precision().is_none());
+            if width.is_none() && precision.is_none() {
+                if let Some(arg) = get(arg) {
+                    write!(Escaper { res: &mut res, escapes }, "{}", WithSpec { arg, spec: &spec }).unwrap();
+                    continue;
+                }
+            }
+            res.push('{');
+            self.write_arg(&mut res, arg);
+            if !spec.is_default() || width.is_some() || precision.is_some() {
+                let mut flags = spec;
+                flags.set_width(None);
+                flags.set_precision(None);
+                flags.set_presentation(None);
+                write!(res, ":{}", flags).unwrap();
+                if let Some(width) = width {
+                    res.push('{');
+                    self.write_arg(&mut res, width);
+                    res.push('}');
+                } else if let Some(width) = spec.width() {
+                    write!(res, "{}", width).unwrap();
+                }
+                if let Some(precision) = precision {
+                    res.push_str(".{");
+                    self.write_arg(&mut res, precision);
+                    res.push('}');
+                } else if let Some(precision) = spec.precision() {
+                    write!(res, ".{}", precision).unwrap();
+                }
+                if let Some(presentation) = spec.presentation() { write!(res, "{}", presentation).unwrap(); }
+            }
+            res.push('}');
+        }
+        Format::parse_with(res, self.options).expect("substituted template is valid")
+    }
+}
+
+/// Generates a valid template mixing literal text, including escaped braces,
+/// comments, raw blocks, and placeholders referring to arguments by position, index, and name,
+/// with arbitrary specs, and with widths and precisions given by arguments in specs without a presentation.
+/// Use [`as_str`](Format::as_str) to get the template text.
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Format {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        const NAMES: &[&str] = &["a", "name", "user_id", "x0"];
+        fn arg(u: &mut arbitrary::Unstructured, res: &mut String, count: bool) -> arbitrary::Result<()> {
+            match u.int_in_range(u8::from(count) ..= 2)? {
+                0 => { },
+                1 => write!(res, "{}", u.int_in_range(0 ..= 7)?).unwrap(),
+                _ => res.push_str(u.choose(NAMES)?),
+            }
+            Ok(())
+        }
+        let mut res = String::new();
+        for _ in 0 .. u.int_in_range(0 ..= 12)? {
+            match u.int_in_range(0 ..= 5)? {
+                0 | 1 => {
+                    let text: &str = u.arbitrary()?;
+                    Escaper { res: &mut res, escapes: false }.write_str(text).unwrap();
+                },
+                2 => write!(res, "{{#{}#}}", u.arbitrary::<&str>()?.replace('#', "")).unwrap(),
+                3 => write!(res, "{{% raw %}}{}{{% endraw %}}", u.arbitrary::<&str>()?.replace('%', "")).unwrap(),
+                _ => {
+                    res.push('{');
+                    arg(u, &mut res, false)?;
+                    let spec: Option<Spec> = u.arbitrary()?;
+                    if let Some(mut spec) = spec {
+                        res.push(':');
+                        if u.ratio(1, 4)? {
+                            spec.set_width(None);
+                            spec.set_precision(None);
+                            spec.set_presentation(None);
+                            write!(res, "{}{{", spec).unwrap();
+                            arg(u, &mut res, true)?;
+                            res.push_str("}.{");
+                            arg(u, &mut res, true)?;
+                            res.push('}');
+                        } else {
+                            write!(res, "{}", spec).unwrap();
+                        }
+                    }
+                    res.push('}');
+                },
+            }
+        }
+        Ok(Format::parse(res).expect("generated template is valid"))
+    }
+}
+
+/// The class of placeholders replaced by [`Format::substitute`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum Substitution {
+    /// Replace placeholders referring to arguments by name, e.g. `{host}`.
+    Named,
+    /// Replace placeholders referring to arguments by index, i.e. `{}` and `{0}`.
+    Positional,
+}
+
+/// Escapes braces, and backslashes if escape sequences are enabled.
+struct Escaper<'a> {
+    res: &'a mut String,
+    escapes: bool,
+}
+
+impl<'a> Write for Escaper<'a> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            match c {
+                '{' | '}' => self.res.push(c),
+                '\\' if self.escapes => self.res.push(c),
+                _ => { },
+            }
+            self.res.push(c);
+        }
+        Ok(())
+    }
+}
+
+/// A difference between the placeholders of two templates, reported by [`Format::compatible_with`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum Mismatch {
+    /// The argument is used by the original template, but not by the other one.
+    Missing { index: usize },
+    /// The argument is used by the other template, but not by the original one.
+    Extra { index: usize },
+    /// The named argument is used by the original template, but not by the other one.
+    MissingName { name: String },
+    /// The named argument is used by the other template, but not by the original one.
+    ExtraName { name: String },
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Mismatch::Missing { index } => write!(f, "argument {} is missing", index),
+            Mismatch::Extra { index } => write!(f, "argument {} is extra", index),
+            Mismatch::MissingName { name } => write!(f, "argument `{}` is missing", name),
+            Mismatch::ExtraName { name } => write!(f, "argument `{}` is extra", name),
+        }
+    }
+}
+
+/// A structural difference between two templates, reported by [`Format::diff`].
+///
+/// Ranges refer to the format strings, see [`Format::as_str`]:
+/// `old` to the format string of the template `diff` is called on, `new` to the other one.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum TemplateChange {
+    /// A placeholder is present in the other template only.
+    Added { new: Range<usize> },
+    /// A placeholder is not present in the other template.
+    Removed { old: Range<usize> },
+    /// A placeholder refers to the same argument, but has a different spec.
+    Changed { old: Range<usize>, new: Range<usize> },
+    /// Literal text between matched placeholders differs. The ranges exclude the common prefix and suffix,
+    /// so they can be empty if the text is inserted or deleted, and can include placeholders added or removed in between.
+    LiteralEdited { old: Range<usize>, new: Range<usize> },
+}
+
+/// Describes how arguments were consumed by [`Format::render_with_report`].
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct RenderReport {
+    used: Vec<usize>,
+    unused: Vec<usize>,
+    missing: Vec<usize>,
+    used_names: Vec<String>,
+    unused_names: Vec<String>,
+    missing_names: Vec<String>,
+}
+
+impl RenderReport {
+    /// Indices of the arguments which appear in the output, in order of their first appearance.
+    pub fn used(&self) -> &[usize] { &self.used }
+
+    /// Indices of the arguments which do not appear in the output, in ascending order.
+    pub fn unused(&self) -> &[usize] { &self.unused }
+
+    /// Indices of the arguments referenced by placeholders but not provided,
+    /// in order of appearance. Such placeholders are replaced by empty string.
+    pub fn missing(&self) -> &[usize] { &self.missing }
+
+    /// Names of the arguments which appear in the output, in order of their first appearance.
+    pub fn used_names(&self) -> &[String] { &self.used_names }
+
+    /// Names of the arguments which do not appear in the output, in ascending order.
+    /// Only the names enumerated by [`ArgSource::for_each_name`] are reported.
+    pub fn unused_names(&self) -> &[String] { &self.unused_names }
+
+    /// Names of the arguments referenced by placeholders but not provided,
+    /// in order of appearance. Such placeholders are replaced by empty string.
+    pub fn missing_names(&self) -> &[String] { &self.missing_names }
+}
+
+/// The origins of the output parts, returned by [`Format::render_with_map`].
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct SourceMap {
+    spans: Vec<MappedSpan>,
+}
+
+impl SourceMap {
+    /// The spans in output order, one for each literal fragment and each placeholder of the template,
+    /// including placeholders whose argument is missing, which produce empty spans.
+    pub fn spans(&self) -> &[MappedSpan] { &self.spans }
+
+    /// Returns the non-empty span containing the output byte offset.
+    pub fn span_at(&self, offset: usize) -> Option<&MappedSpan> {
+        let i = self.spans.partition_point(|x| x.output.end <= offset);
+        self.spans[i ..].iter().find(|x| !x.output.is_empty()).filter(|x| x.output.start <= offset)
+    }
+}
+
+/// A part of the output produced by one template fragment, see [`SourceMap`].
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct MappedSpan {
+    output: Range<usize>,
+    template: Range<usize>,
+    origin: SpanOrigin,
+}
+
+impl MappedSpan {
+    /// The byte range in the output.
+    pub fn output(&self) -> Range<usize> { self.output.clone() }
+
+    /// The byte range of the literal fragment or the placeholder in the format string.
+    pub fn template(&self) -> Range<usize> { self.template.clone() }
+
+    /// Whether the span is literal text or a substituted argument.
+    pub fn origin(&self) -> &SpanOrigin { &self.origin }
+}
+
+/// The origin of a [`MappedSpan`].
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum SpanOrigin {
+    /// Literal template text, including escaped braces.
+    Literal,
+    /// The argument with the index, referred to by `{}` or `{0}`.
+    Arg(usize),
+    /// The named argument.
+    Named(String),
+}
+
+impl FromStr for Format {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, ParseError> { Format::parse(s) }
+}
+
+/// A [`Format`] combined with its arguments.
+#[derive(Clone, Debug)]
+pub struct FormatArguments<'f, 'a, T: Display + ?Sized + 'a, I: IntoIterator<Item=&'a T> + Clone> {
+    format: &'f Format,
+    args: I,
+    options: FormatOptions,
+}
+
+impl<'f, 'a, T: Display + ?Sized + 'a, I: IntoIterator<Item=&'a T> + Clone> FormatArguments<'f, 'a, T, I> {
+    /// Sets the options controlling how arguments are substituted.
+    pub fn with_options(mut self, options: FormatOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+impl<'f, 'a, T: Display + ?Sized + 'a, I: IntoIterator<Item=&'a T> + Clone> Display for FormatArguments<'f, 'a, T, I> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut args = Positional::new(self.args.clone());
+        let mut get = |arg: &Arg| {
+            let (value, arg) = match *arg {
+                Arg::Index(index) => (args.get(index), ArgRef::Index(index)),
+                Arg::Name(ref span) => (None, ArgRef::Name(self.format.name(span))),
+            };
+            if value.is_none() { self.options.report(FmtEvent::MissingArgument(arg)); }
+            value
+        };
+        for piece in self.format.pieces.iter() {
+            match piece {
+                Piece::Literal(span) => self.format.text[span.clone()].fmt(f)?,
+                Piece::Arg(arg, spec, counts) => {
+                    let spec = resolve_spec(spec, counts, &mut get);
+                    if let Some(arg) = get(arg) {
+                        self.options.fmt_arg(arg, &spec, f)?;
+                    }
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A [`Format`] combined with an [`ArgSource`].
+pub(crate) struct SourceArguments<'f, A: ArgSource> {
+    format: &'f Format,
+    args: A,
+    options: FormatOptions,
+}
+
+impl<'f, A: ArgSource> SourceArguments<'f, A> {
+    /// Combines the format with the arguments substituted according to [`FormatOptions::current_default`].
+    pub(crate) fn new(format: &'f Format, args: A) -> Self {
+        SourceArguments { format, args, options: FormatOptions::current_default() }
+    }
+}
+
+impl<'f, A: ArgSource> Display for SourceArguments<'f, A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let get = |arg: &Arg| {
+            let (value, arg) = match *arg {
+                Arg::Index(index) => (self.args.get(index), ArgRef::Index(index)),
+                Arg::Name(ref span) => {
+                    let name = self.format.name(span);
+                    (self.args.get_named(name), ArgRef::Name(name))
+                },
+            };
+            if value.is_none() { self.options.report(FmtEvent::MissingArgument(arg)); }
+            value
+        };
+        for piece in self.format.pieces.iter() {
+            match piece {
+                Piece::Literal(span) => f.write_str(&self.format.text[span.clone()])?,
+                Piece::Arg(arg, spec, counts) => {
+                    let spec = resolve_spec(spec, counts, get);
+                    if let Some(arg) = get(arg) {
+                        self.options.fmt_arg(arg, &spec, f)?;
+                    }
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An argument formatted according to [`FormatOptions`].
+struct WithOptions<'x, T: Display + ?Sized> {
+    arg: &'x T,
+    spec: &'x Spec,
+    options: &'x FormatOptions,
+}
+
+impl<'x, T: Display + ?Sized> Display for WithOptions<'x, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { self.options.fmt_arg(self.arg, self.spec, f) }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ArgSource, Format, FormatOptions, Mismatch, ParseErrorKind, ParseOptions, SpanOrigin, Substitution, TemplateChange};
+    use core::fmt::Display;
+    use std::collections::{BTreeMap, HashMap};
+
+    const _: () = {
+        const fn assert_send_sync<T: Send + Sync>() { }
+        assert_send_sync::<Format>();
+    };
+
+    #[test]
+    fn shared() {
+        let format = Format::parse("{}-{}").unwrap();
+        let clone = format.clone();
+        assert!(core::ptr::eq(format.as_str(), clone.as_str()));
+        let rendered = std::thread::scope(|s| {
+            let format = &format;
+            let workers: Vec<_> = (0 .. 4).map(|i| s.spawn(move || format.render(&[i, i]))).collect();
+            workers.into_iter().map(|x| x.join().unwrap()).collect::<Vec<_>>()
+        });
+        assert_eq!(rendered, ["0-0", "1-1", "2-2", "3-3"]);
+    }
+
+    #[test]
+    fn render() {
+        let format = Format::parse("{}a{}b{}c").unwrap();
+        assert_eq!(format.render(&[1, 2, 3]), "1a2b3c");
+        assert_eq!(format.render(&[1, 2]), "1a2bc");
+        assert_eq!(Format::parse("{{}}{}").unwrap().render(&[1, 2]), "{}1");
+        assert_eq!("{{{}}}x{{y}}".parse::<Format>().unwrap().render(&[1]), "{1}x{y}");
+    }
+
+    #[test]
+    fn render_with_report() {
+        let format = Format::parse("{}-{}-{}").unwrap();
+        let (s, report) = format.render_with_report([1, 2]);
+        assert_eq!(s, "1-2-");
+        assert_eq!(report.used(), &[0, 1]);
+        assert!(report.unused().is_empty());
+        assert_eq!(report.missing(), &[2]);
+        let (s, report) = Format::parse("x").unwrap().render_with_report(["a", "b"]);
+        assert_eq!(s, "x");
+        assert!(report.used().is_empty());
+        assert_eq!(report.unused(), &[0, 1]);
+    }
+
+    #[test]
+    fn render_with_report_undercounted() {
+        struct Undercounted;
+
+        impl ArgSource for Undercounted {
+            type Arg = usize;
+            fn len(&self) -> usize { 1 }
+            fn get(&self, index: usize) -> Option<&usize> { [0, 1, 2].get(index) }
+        }
+
+        let (s, report) = Format::parse("{}{2}{2}{1}").unwrap().render_with_report(Undercounted);
+        assert_eq!(s, "0221");
+        assert_eq!(report.used(), &[0, 2, 1]);
+        assert!(report.unused().is_empty() && report.missing().is_empty());
+    }
+
+    #[test]
+    fn render_with_report_names() {
+        let format = Format::parse("{a} {b} {0} {a:>3}").unwrap();
+        let (s, report) = format.render_with_report(HashMap::from([("a", 1), ("c", 3), ("d", 4)]));
+        assert_eq!(s, "1     1");
+        assert_eq!(report.used_names(), ["a"]);
+        assert_eq!(report.unused_names(), ["c", "d"]);
+        assert_eq!(report.missing_names(), ["b"]);
+        assert_eq!(report.missing(), &[0]);
+        let (_, report) = format.render_with_report(["x"]);
+        assert!(report.used_names().is_empty() && report.unused_names().is_empty());
+        assert_eq!(report.used(), &[0]);
+        let (_, report) = Format::parse("{b}").unwrap().render_with_report(BTreeMap::from([("b", 1), ("a", 2)]));
+        assert_eq!((report.used_names(), report.unused_names()), (&["b".to_string()][..], &["a".to_string()][..]));
+    }
+
+    #[test]
+    fn compatible_with() {
+        let source = Format::parse("{}: {}").unwrap();
+        assert_eq!(source.compatible_with(&Format::parse("{# x #}{} - {}").unwrap()), Ok(()));
+        assert_eq!(
+            source.compatible_with(&Format::parse("{}: {} {}").unwrap()),
+            Err(vec![Mismatch::Extra { index: 2 }])
+        );
+        assert_eq!(
+            source.compatible_with(&Format::parse("no args").unwrap()),
+            Err(vec![Mismatch::Missing { index: 0 }, Mismatch::Missing { index: 1 }])
+        );
+        assert_eq!(
+            Format::parse("{name}: {1}").unwrap().compatible_with(&Format::parse("{1} ({title})").unwrap()),
+            Err(vec![Mismatch::MissingName { name: "name".into() }, Mismatch::ExtraName { name: "title".into() }])
+        );
+    }
+
+    #[test]
+    fn explicit_args() {
+        let format = Format::parse("{1}{}{0}{}{}{1:>3}").unwrap();
+        assert_eq!(format.render(&["a", "b"]), "baab  b");
+        let (s, report) = format.render_with_report(["a", "b"]);
+        assert_eq!(s, "baab  b");
+        assert_eq!(report.used(), &[1, 0]);
+        assert_eq!(report.missing(), &[2]);
+        let (s, report) = Format::parse("{x}-{y}-{x:*<3}").unwrap().render_with_report(BTreeMap::from([("x", 1)]));
+        assert_eq!(s, "1--1**");
+        assert_eq!(report.missing_names(), ["y"]);
+        assert_eq!(Format::parse("{x}{}").unwrap().render(&[1]), "1");
+    }
+
+    #[test]
+    fn invalid_args() {
+        for (fmt, kind, span) in [
+            ("a{1x}", ParseErrorKind::InvalidName, 1 .. 5),
+            ("{a b}", ParseErrorKind::InvalidName, 0 .. 5),
+            ("{_}", ParseErrorKind::InvalidName, 0 .. 3),
+            ("{99999999999999999999999}", ParseErrorKind::InvalidIndex, 0 .. 25),
+            ("{$x}", ParseErrorKind::ReservedSyntax, 0 .. 4),
+            ("{x:5", ParseErrorKind::UnterminatedPlaceholder, 0 .. 4),
+            ("{y:q}", ParseErrorKind::InvalidSpec, 0 .. 5),
+            ("a{:>99999999999999999999}", ParseErrorKind::CountOverflow, 1 .. 25),
+        ] {
+            let err = Format::parse(fmt).unwrap_err();
+            assert_eq!((err.kind(), err.span()), (kind, span), "{:?}", fmt);
+        }
+        assert_eq!(Format::parse("{ x} {-}").unwrap().render(&[1]), " x -");
+    }
+
+    #[test]
+    fn parse_options() {
+        let lenient = ParseOptions::lenient();
+        assert_eq!(Format::parse("{ x} {-}").unwrap().lint().len(), 4);
+        let format = Format::parse_with("{ x} {:y>4}", lenient).unwrap();
+        assert_eq!(format.render_from(BTreeMap::from([("x", 1)])), "1 ");
+        assert!(format.lint().is_empty());
+        assert_eq!(Format::parse_with("{:>4z}", lenient).unwrap().render(&[1]), "   1");
+        assert_eq!(Format::parse("{:>4z}").unwrap_err().kind(), ParseErrorKind::InvalidSpec);
+    }
+
+    #[test]
+    fn escapes() {
+        let escapes = ParseOptions::new().allow_escapes(true);
+        let format = Format::parse_with(r#"\t{}\n\\{{\u{1F600}\"{x}\'\0"#, escapes).unwrap();
+        assert_eq!(format.render_from(BTreeMap::from([("x", 1)])), "\t\n\\{\u{1F600}\"1'\0");
+        assert_eq!(Format::parse_with(r"{% raw %}\n{% endraw %}", escapes).unwrap().render(&[1]), r"\n");
+        assert_eq!(Format::parse(r"a\n").unwrap().render(&[1]), r"a\n");
+        for (fmt, span) in [(r"a\q", 1 .. 3), (r"\я", 0 .. 3), (r"\u{d800}", 0 .. 8), (r"\u{1234567}", 0 .. 10), (r"\u{}", 0 .. 3), (r"{}\u{1", 2 .. 6), ("\\", 0 .. 1)] {
+            let err = Format::parse_with(fmt, escapes).unwrap_err();
+            assert_eq!((err.kind(), err.span()), (ParseErrorKind::InvalidEscape, span), "{:?}", fmt);
+        }
+    }
+
+    #[test]
+    fn mixed_numbering() {
+        let strict = ParseOptions::new().forbid_mixed_numbering(true);
+        assert_eq!(Format::parse("{1}{}").unwrap().render(&[1, 2]), "21");
+        assert_eq!(Format::parse_with("{1}{0:>2}{x}", strict).unwrap().render(&[1, 2]), "2 1");
+        assert_eq!(Format::parse_with("{}{:>2}{x}", strict).unwrap().render(&[1, 2]), "1 2");
+        for (fmt, span) in [("{}{0}", 2 .. 5), ("a{1:<3}{:<3}", 7 .. 12), ("{x}{0}{x}{}", 9 .. 11)] {
+            let err = Format::parse_with(fmt, strict).unwrap_err();
+            assert_eq!((err.kind(), err.span()), (ParseErrorKind::MixedNumbering, span), "{:?}", fmt);
+        }
+    }
+
+    #[test]
+    fn substitute() {
+        let format = Format::parse("{x:>4}|{:<3}|{{}}|{y}|{}|{0:+}").unwrap();
+        let named = format.substitute(BTreeMap::from([("x", "{a}")]), Substitution::Named);
+        assert_eq!(named.as_str(), " {{a}}|{0:<3}|{{}}|{y}|{1}|{0:+}");
+        let positional = named.substitute([1, 2], Substitution::Positional);
+        assert_eq!(positional.as_str(), " {{a}}|1  |{{}}|{y}|2|+1");
+        assert_eq!(positional.render_from(BTreeMap::from([("y", "}")])), " {a}|1  |{}|}|2|+1");
+        assert_eq!(format.substitute([1], Substitution::Positional).as_str(), "{x:>4}|1  |{{}}|{y}|{1}|+1");
+        let escapes = ParseOptions::new().allow_escapes(true);
+        let format = Format::parse_with(r"\t{}\\{x}", escapes).unwrap();
+        let named = format.substitute(BTreeMap::from([("x", r"\n")]), Substitution::Named);
+        assert_eq!(named.as_str(), "\t{0}\\\\\\\\n");
+        assert_eq!(named.render(&[1]), r"	1\\n");
+    }
+
+    #[test]
+    fn counts() {
+        let format = Format::parse("{0:>{1}}|{x:^{w}.{p}}|{:{x}.{0}}").unwrap();
+        let args = BTreeMap::from([("x", "4"), ("w", "6"), ("p", "2")]);
+        assert_eq!(format.render_from(args), "|  4   |");
+        assert_eq!(format.render(&[3, 5]), "    3||3");
+        assert_eq!(Format::parse("{:.{1}}").unwrap().render::<dyn Display>([&1.23456 as _, &"x" as _]), "1.23456");
+        assert_eq!(Format::parse("{:>{1}}|{0:*<{2}}").unwrap().render(&[1, 3]), "  1|1");
+        let (s, report) = Format::parse("{2:0{1}.{0}}").unwrap().render_with_report([2.0, 6.0]);
+        assert_eq!(s, "");
+        assert_eq!(report.used(), &[1, 0]);
+        assert_eq!(report.missing(), &[2]);
+        assert_eq!(Format::parse("{:{<4}{:{<{0}}").unwrap().render(&[3, 2]), "3{{{2{{");
+        let format = Format::parse("{:>{w}}-{w:{0}}").unwrap();
+        assert_eq!(format.arg_names().into_iter().collect::<Vec<_>>(), ["w"]);
+        let named = format.substitute(BTreeMap::from([("w", 3)]), Substitution::Named);
+        assert_eq!(named.as_str(), "{0:>3}-{w:{0}}");
+        let positional = format.substitute([5], Substitution::Positional);
+        assert_eq!(positional.as_str(), "{0:>{w}}-{w:5}");
+        assert_eq!(positional.substitute(BTreeMap::from([("w", 3)]), Substitution::Named).as_str(), "{0:>3}-    3");
+        for (fmt, kind) in [("{:{}}", ParseErrorKind::InvalidSpec), ("{:{1x}}", ParseErrorKind::InvalidName), ("{:{w}", ParseErrorKind::UnterminatedPlaceholder)] {
+            assert_eq!(Format::parse(fmt).unwrap_err().kind(), kind, "{:?}", fmt);
+        }
+        assert_eq!(Format::parse_with("{}{:{0}}", ParseOptions::new().forbid_mixed_numbering(true)).unwrap_err().kind(), ParseErrorKind::MixedNumbering);
+    }
+
+    #[test]
+    fn count_limit() {
+        use crate::{AsStrFormatExt, Arguments};
+        assert_eq!(Format::parse("{:70000}").unwrap_err().kind(), ParseErrorKind::CountOverflow);
+        assert_eq!(Format::parse("{:.70000}").unwrap_err().kind(), ParseErrorKind::CountOverflow);
+        assert_eq!("{:70000}|{}".format(&[1]), ":70000|1");
+        assert_eq!(Arguments::new("{:.70000}|{}", &[1]).to_string(), ":.70000|1");
+        assert_eq!(Format::parse("{:>{0}}").unwrap().render(&[70000]).len(), 65535);
+        let args = vec!["100000".to_string(), "y".repeat(70000)];
+        assert_eq!(Format::parse("{1:.{0}}").unwrap().render_from(&args).len(), 65535);
+        assert_eq!(Format::parse("{1:{0}}").unwrap().render_from(["99999999999999999999999", "z"]).len(), 65535);
+    }
+
+    #[test]
+    fn isolate_bidi() {
+        let options = FormatOptions::new().isolate_bidi(true);
+        let format = Format::parse("{1:>3}: {0}{2}").unwrap();
+        assert_eq!(format.args(&["a", "b"]).with_options(options).to_string(), "\u{2068}  b\u{2069}: \u{2068}a\u{2069}");
+    }
+
+    #[test]
+    fn events() {
+        use crate::Arguments;
+        use std::cell::RefCell;
+        thread_local! {
+            static EVENTS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+        }
+        let options = FormatOptions::new().on_event(|event| EVENTS.with(|x| x.borrow_mut().push(format!("{:?}", event))));
+        let take = || EVENTS.with(|x| x.take());
+        let format = Format::parse("{1:>{w}} {0:.{2}}").unwrap();
+        assert_eq!(format.args(&[1.5]).with_options(options).to_string(), " 1.5");
+        assert_eq!(take(), ["MissingArgument(Name(\"w\"))", "MissingArgument(Index(1))", "MissingArgument(Index(2))"]);
+        assert_eq!(Arguments::new("{}{:q} {#", &[1]).with_options(options).to_string(), "1:q #");
+        assert_eq!(take(), [
+            "Malformed { kind: InvalidSpec, span: 2..6 }",
+            "Malformed { kind: UnterminatedComment, span: 7..9 }",
+        ]);
+        assert_eq!(Arguments::new("{}|{}", &[1]).with_options(options).to_string(), "1|");
+        assert_eq!(take(), ["MissingArgument(Index(1))"]);
+    }
+
+    #[test]
+    fn default_options() {
+        use crate::FormatSession;
+        use std::cell::RefCell;
+        thread_local! {
+            static EVENTS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+        }
+        let options = FormatOptions::new().truncation_indicator("…", true)
+            .on_event(|event| EVENTS.with(|x| x.borrow_mut().push(format!("{:?}", event))));
+        let take = || EVENTS.with(|x| x.take());
+        let format = Format::parse("{0:.3}|{x}").unwrap();
+        FormatOptions::set_thread_default(Some(options));
+        assert_eq!(format.render(&["truncated"]), "tr…|");
+        assert!(!take().is_empty());
+        assert_eq!(format.render_from(["truncated"]), "tr…|");
+        assert_eq!(format.render_with_report(["truncated"]).0, "tr…|");
+        assert_eq!(format.render_with_map(["truncated"]).0, "tr…|");
+        assert_eq!(FormatSession::new().push_format(&format, ["truncated"]).as_str(), "tr…|");
+        assert_eq!(take(), ["MissingArgument(Name(\"x\"))"; 4]);
+        FormatOptions::set_thread_default(None);
+        assert_eq!(format.render_from(["truncated"]), "tru|");
+        assert!(take().is_empty());
+    }
+
+    #[test]
+    fn specs() {
+        let format = Format::parse("[{:>5}|{:<5}|{:^5}|{:+}|{:05.1}|{:.2}|{:#}]").unwrap();
+        assert_eq!(format.render::<dyn Display>([&1 as _, &2 as _, &3 as _, &4 as _, &-5.25 as _, &"abc" as _, &6 as _]), "[    1|2    |  3  |+4|-05.2|ab|6]");
+        assert_eq!(Format::parse("{:*^7}").unwrap().render(&["ab"]), "**ab***");
+        assert_eq!(Format::parse("{:}").unwrap().render(&[1]), "1");
+    }
+
+    #[test]
+    fn invalid_specs() {
+        let err = Format::parse("a{:5").unwrap_err();
+        assert_eq!(err.kind(), ParseErrorKind::UnterminatedPlaceholder);
+        assert_eq!(err.span(), 1 .. 4);
+        let err = Format::parse("a{:5x}").unwrap_err();
+        assert_eq!(err.kind(), ParseErrorKind::InvalidSpec);
+        assert_eq!(err.span(), 1 .. 6);
+    }
+
+    #[test]
+    fn comments() {
+        let format = Format::parse("{# greeting #}Hello, {}!{## ##}").unwrap();
+        assert_eq!(format.render(&["world"]), "Hello, world!");
+        assert_eq!(Format::parse("{##}{#}#}{}").unwrap().render(&[1]), "1");
+        assert_eq!(Format::parse("{{# not a comment #}}").unwrap().render(&[1]), "{# not a comment #}");
+    }
+
+    #[test]
+    fn raw_blocks() {
+        let format = Format::parse(r#"{{"id": {}, "tags": {% raw %}{"a": {}}{% endraw %}}}"#).unwrap();
+        assert_eq!(format.render(&[7, 8]), r#"{"id": 7, "tags": {"a": {}}}"#);
+        assert_eq!(Format::parse("{%raw%}{}{%   endraw %}{}").unwrap().render(&[1]), "{}1");
+        assert_eq!(Format::parse("a{% raw %}{% endraw %}b").unwrap().render(&[1]), "ab");
+        assert_eq!(Format::parse("{% endraw %}").unwrap().render(&[1]), "% endraw %");
+    }
+
+    #[test]
+    fn unterminated_raw() {
+        let err = Format::parse("{}{% raw %}{}").unwrap_err();
+        assert_eq!(err.kind(), ParseErrorKind::UnterminatedRaw);
+        assert_eq!(err.span(), 2 .. 13);
+    }
+
+    #[test]
+    fn unterminated_comment() {
+        let err = Format::parse("ab{}{# comment }").unwrap_err();
+        assert_eq!(err.kind(), ParseErrorKind::UnterminatedComment);
+        assert_eq!(err.span(), 4 .. 16);
+        assert_eq!(err.to_string(), "unterminated comment at 4..16");
+    }
+
+    #[test]
+    fn source_map() {
+        let format = Format::parse_with(r"\t{{x{# c #}{1:>4}{}|{5}", ParseOptions::new().allow_escapes(true)).unwrap();
+        let (s, map) = format.render_with_map(["a", "b"]);
+        assert_eq!(s, "\t{x   ba|");
+        let spans: Vec<_> = map.spans().iter().map(|x| (x.output(), x.template(), x.origin().clone())).collect();
+        assert_eq!(spans, [
+            (0 .. 1, 0 .. 2, SpanOrigin::Literal),
+            (1 .. 3, 3 .. 5, SpanOrigin::Literal),
+            (3 .. 7, 12 .. 18, SpanOrigin::Arg(1)),
+            (7 .. 8, 18 .. 20, SpanOrigin::Arg(0)),
+            (8 .. 9, 20 .. 21, SpanOrigin::Literal),
+            (9 .. 9, 21 .. 24, SpanOrigin::Arg(5)),
+        ]);
+        assert_eq!(map.span_at(2).unwrap().origin(), &SpanOrigin::Literal);
+        assert_eq!(map.span_at(7).unwrap().origin(), &SpanOrigin::Arg(0));
+        assert!(map.span_at(9).is_none());
+        let (s, map) = Format::parse("{a}{b}").unwrap().render_with_map(HashMap::from([("b", 1)]));
+        assert_eq!(s, "1");
+        assert_eq!(map.span_at(0).unwrap().origin(), &SpanOrigin::Named("b".into()));
+        assert_eq!(map.spans()[0].origin(), &SpanOrigin::Named("a".into()));
+    }
+
+    #[test]
+    fn rename() {
+        let options = ParseOptions::new().allow_whitespace(true).allow_escapes(true);
+        let format = Format::parse_with(r"{ a }\t{b:{a}.{b}}{% raw %}{a}{% endraw %}{{a}}{ab}{c}", options).unwrap();
+        let renamed = format.rename(&[("a", "first"), ("b", "b"), ("c", "a"), ("c", "z")]);
+        assert_eq!(renamed.as_str(), r"{ first }\t{b:{first}.{b}}{% raw %}{a}{% endraw %}{{a}}{ab}{a}");
+        assert_eq!(renamed.options(), options);
+        let args = HashMap::from([("first", "1"), ("b", "2"), ("ab", "3"), ("a", "4")]);
+        assert_eq!(renamed.render_from(&args), "1\t2{a}{a}34");
+        assert_eq!(format.rename(&[]).as_str(), format.as_str());
+    }
+
+    #[test]
+    fn diff() {
+        let parse = |s: &str| Format::parse(s).unwrap();
+        assert_eq!(parse("{} and {}{# x #}").diff(&parse("{0} and {1:}")), []);
+        let escaped = Format::parse_with(r"a\t{{b", ParseOptions::new().allow_escapes(true)).unwrap();
+        assert_eq!(escaped.diff(&parse("a\t{{b")), []);
+        assert_eq!(parse("{a}{b}{c}").diff(&parse("{c}{a}{b}")), [
+            TemplateChange::Added { new: 0 .. 3 },
+            TemplateChange::Removed { old: 6 .. 9 },
+        ]);
+        assert_eq!(parse("{x:>5}!").diff(&parse("{x:>{w}}")), [
+            TemplateChange::Changed { old: 0 .. 6, new: 0 .. 8 },
+            TemplateChange::LiteralEdited { old: 6 .. 7, new: 8 .. 8 },
+        ]);
+        assert_eq!(parse("añb").diff(&parse("aäb")), [TemplateChange::LiteralEdited { old: 1 .. 3, new: 1 .. 3 }]);
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn render_zeroizing() {
+        let format = Format::parse("{token}/{0:.3}/{:*^7}").unwrap();
+        let args = HashMap::from([("token", "t0k3n")]);
+        assert_eq!(format.render_zeroizing(&args).as_str(), format.render_from(&args));
+        assert_eq!(format.render_zeroizing(["secret", "x"]).as_str(), "/sec/secret*");
+        let options = FormatOptions::new().truncation_indicator("…", true);
+        assert_eq!(format.args(&["secret"]).with_options(options).to_string(), "/se…/secret*");
+    }
+
+    #[test]
+    fn required_features() {
+        use crate::SyntaxFeature::*;
+        let features = |s: &str, options| Format::parse_with(s, options).unwrap().required_features().into_iter().collect::<Vec<_>>();
+        let options = ParseOptions::lenient().allow_escapes(true);
+        assert_eq!(features(r"{{a}}\t{ x :d8.2}{% raw %}{0}{% endraw %}{:>q5} }", options), [
+            Named, DecimalAlign, RawBlock, EscapeSequence, Whitespace, UnknownFlags, StrayBrace,
+        ]);
+        assert_eq!(features("{:.{1}}{:{w}}", ParseOptions::new()), [CountArgs]);
+        assert_eq!(features("{:*^+#08.3}{}", ParseOptions::new()), []);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid argument name `1st`")]
+    fn rename_to_invalid_name() {
+        Format::parse("{a}").unwrap().rename(&[("a", "1st")]);
+    }
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn arbitrary() {
+        use arbitrary::{Arbitrary, Unstructured};
+
+        let mut seed = 7u64;
+        let data: Vec<u8> = (0 .. 1 << 16).map(|_| {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (seed >> 56) as u8
+        }).collect();
+        let mut u = Unstructured::new(&data);
+        let args = HashMap::from([("a", 1.5), ("name", -2.0), ("user_id", 3.0), ("x0", 40.0)]);
+        while !u.is_empty() {
+            let format = Format::arbitrary(&mut u).unwrap();
+            let again = Format::parse(format.as_str()).unwrap();
+            assert_eq!(again.render(&[1, 2, 3]), format.render(&[1, 2, 3]));
+            format.render_from(&args);
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use core::fmt::{self, Alignment, Display, Write};
+
+/// An argument wrapping [`fmt::Arguments`], the result of the [`format_args!`](std::format_args) macro,
+/// so a statically formatted fragment can be nested into a runtime template without allocation.
+///
+/// In contrast with the plain [`fmt::Arguments`], which ignores the spec,
+/// the fragment is truncated by the precision and padded to the width as a string.
+/// # Examples:
+/// ```rust
+/// use dyn_fmt::{Arguments, Fragment};
+/// let (line, col) = (12, 5);
+/// let s = Arguments::new("[{:>7}] {:.3}", &[Fragment(format_args!("{}:{}", line, col)), Fragment(format_args!("{:?}", "ab"))]).to_string();
+/// assert_eq!(s, "[   12:5] \"ab");
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Fragment<'a>(pub fmt::Arguments<'a>);
+
+impl<'a> Display for Fragment<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.width().is_none() && f.precision().is_none() { return f.write_fmt(self.0); }
+        let mut counter = CharLimiter { inner: None::<&mut fmt::Formatter>, left: f.precision().unwrap_or(usize::MAX) };
+        let _ = counter.write_fmt(self.0);
+        let len = f.precision().unwrap_or(usize::MAX) - counter.left;
+        let padding = f.width().unwrap_or(0).saturating_sub(len);
+        let (left, right) = match f.align() {
+            None | Some(Alignment::Left) => (0, padding),
+            Some(Alignment::Center) => (padding / 2, (padding + 1) / 2),
+            Some(Alignment::Right) => (padding, 0),
+        };
+        let fill = f.fill();
+        for _ in 0 .. left { f.write_char(fill)?; }
+        let mut limiter = CharLimiter { inner: Some(&mut *f), left: len };
+        match limiter.write_fmt(self.0) {
+            Err(_) if limiter.left != 0 => return Err(fmt::Error),
+            _ => { },
+        }
+        for _ in 0 .. right { f.write_char(fill)?; }
+        Ok(())
+    }
+}
+
+/// Passes at most `left` characters to the inner writer, or only counts them if there is no writer.
+///
+/// Fails as soon as the limit is reached, to stop formatting the rest of the fragment.
+struct CharLimiter<W: Write> {
+    inner: Option<W>,
+    left: usize,
+}
+
+impl<W: Write> Write for CharLimiter<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let (end, count) = match s.char_indices().nth(self.left) {
+            Some((end, _)) => (end, self.left),
+            None => (s.len(), s.chars().count()),
+        };
+        if let Some(inner) = &mut self.inner { inner.write_str(&s[.. end])?; }
+        self.left -= count;
+        if end < s.len() { Err(fmt::Error) } else { Ok(()) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "std")]
+    #[test]
+    fn fragment() {
+        use crate::{Arguments, Format, Fragment};
+        use std::collections::HashMap;
+
+        let s = format!("{:>6}|{:*^7.3}|{}|{:.0}|", Fragment(format_args!("{}:{}", 1, 2)), Fragment(format_args!("{}ы{}", "ab", 3)), Fragment(format_args!("x")), Fragment(format_args!("y")));
+        assert_eq!(s, "   1:2|**abы**|x||");
+        let s = Arguments::new("{:<5}|{:^5}", &[Fragment(format_args!("{}", 1)), Fragment(format_args!("{}", 22))]).to_string();
+        assert_eq!(s, "1    | 22  ");
+        let format = Format::parse("at {pos:.<8}").unwrap();
+        assert_eq!(format.render_from(HashMap::from([("pos", Fragment(format_args!("{}:{}", 3, 4)))])), "at 3:4.....");
+    }
+}
@@ -0,0 +1,111 @@
+use crate::{ArgRef, FmtEvent, InvalidPlaceholders, ParseErrorKind, write_args};
+use crate::spec::fmt_arg;
+use core::cell::{Cell, RefCell};
+use core::fmt::{self, Display, Write};
+use core::ops::Range;
+
+/// The kind of an [`Issue`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum IssueKind {
+    /// The placeholder refers to an argument which is not provided. It is replaced by empty string.
+    MissingArgument { index: usize },
+    /// The fragment is malformed and is rendered as literal text.
+    Malformed(ParseErrorKind),
+}
+
+impl Display for IssueKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IssueKind::MissingArgument { index } => write!(f, "argument {} is missing", index),
+            IssueKind::Malformed(kind) => kind.fmt(f),
+        }
+    }
+}
+
+/// A problem found while rendering a format string,
+/// reported by [`format_with_issues`](crate::AsStrFormatExt::format_with_issues).
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Issue {
+    kind: IssueKind,
+    span: Range<usize>,
+}
+
+impl Issue {
+    /// The kind of the issue.
+    pub fn kind(&self) -> IssueKind { self.kind }
+
+    /// The byte range of the problematic fragment within the format string.
+    pub fn span(&self) -> Range<usize> { self.span.clone() }
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at {}..{}", self.kind, self.span.start, self.span.end)
+    }
+}
+
+pub(crate) fn format_with_issues<'a, T: Display + ?Sized + 'a>(
+    fmt: &str,
+    args: impl IntoIterator<Item=&'a T>
+) -> (String, Vec<Issue>) {
+    struct Rendering<'f, 'i, I> {
+        fmt: &'f str,
+        args: Cell<Option<I>>,
+        issues: RefCell<&'i mut Vec<Issue>>,
+    }
+
+    impl<'f, 'i, 'a, T: Display + ?Sized + 'a, I: IntoIterator<Item=&'a T>> Display for Rendering<'f, 'i, I> {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            let args = self.args.take().into_iter().flatten();
+            let mut issues = self.issues.borrow_mut();
+            let report = |event: FmtEvent, span| {
+                let kind = match event {
+                    FmtEvent::MissingArgument(ArgRef::Index(index)) => IssueKind::MissingArgument { index },
+                    FmtEvent::Malformed { kind, .. } => IssueKind::Malformed(kind),
+                    _ => unreachable!(),
+                };
+                issues.push(Issue { kind, span });
+            };
+            write_args(f, self.fmt, args, InvalidPlaceholders::Lenient, report, |arg, spec, f| fmt_arg(arg, spec, f))
+        }
+    }
+
+    let mut res = String::with_capacity(fmt.len());
+    let mut issues = Vec::new();
+    let rendering = Rendering { fmt, args: Cell::new(Some(args)), issues: RefCell::new(&mut issues) };
+    write!(res, "{}", rendering).unwrap();
+    (res, issues)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{AsStrFormatExt, IssueKind, ParseErrorKind};
+
+    fn issues(fmt: &str, args: &[i32]) -> (String, Vec<(IssueKind, core::ops::Range<usize>)>) {
+        let (s, issues) = fmt.format_with_issues(args);
+        (s, issues.into_iter().map(|x| (x.kind(), x.span())).collect())
+    }
+
+    #[test]
+    fn clean() {
+        assert_eq!(issues("{}-{:>3}", &[1, 2, 3]), ("1-  2".to_string(), vec![]));
+    }
+
+    #[test]
+    fn missing_arguments() {
+        assert_eq!(issues("{}-{:+}-{}", &[1]), ("1--".to_string(), vec![
+            (IssueKind::MissingArgument { index: 1 }, 3 .. 7),
+            (IssueKind::MissingArgument { index: 2 }, 8 .. 10),
+        ]));
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(issues("{:x} {}{# c", &[1, 2]), (":x 1# c".to_string(), vec![
+            (IssueKind::Malformed(ParseErrorKind::InvalidSpec), 0 .. 4),
+            (IssueKind::Malformed(ParseErrorKind::UnterminatedComment), 7 .. 11),
+        ]));
+        assert_eq!("a{:5".format_with_issues(&[1]).1[0].to_string(), "unterminated placeholder at 1..4");
+    }
+}
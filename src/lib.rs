@@ -7,16 +7,338 @@
 //! | [`format_args!`](std::format_args) | [`Arguments::new`](Arguments::new) |
 //! |       [`write!`](std::write)       |      [`dyn_write!`](dyn_write)     |
 //!
+//! **Format string syntax**
+//!
+//! * `{}` is replaced by the next argument.
+//! * `{:spec}` is replaced by the next argument formatted according to the [`Spec`],
+//!   e.g. `{:>8.2}` or `{:+}`, with the same meaning as in the [`format!`](std::format) macro.
+//! * `{0}`, `{name}`, `{0:spec}`, `{name:spec}` refer to arguments explicitly, by index or by name.
+//!   They are supported by [`Format`] only.
+//! * `{:{width}.{1}}` takes the width and the precision from arguments, supported by [`Format`] only.
+//!   As the width and the precision written in a spec, they are limited to 65535, larger values are clamped.
+//! * `{{` and `}}` produce literal braces.
+//! * `{# ... #}` is a comment, it is removed from the output.
+//! * `{% raw %} ... {% endraw %}` is a raw block, its content is output as is, without interpreting braces.
+//! * `\n`, `\t`, `\u{1F600}` and other escape sequences, if enabled with [`ParseOptions::allow_escapes`].
+//!
+//! [`Format::parse`] reports malformed fragments as errors,
+//! while [`Arguments`] and [`format`](AsStrFormatExt::format) render them as literal text.
+//!
 //! **Crate features**
 //!
 //! * `"std"`
-//! Enabled by default. Disable to make the library `#![no_std]`.
+//!   Enabled by default. Disable to make the library `#![no_std]`.
+//!   Implies `"syntax"`.
+//!
+//! * `"syntax"`
+//!   Enable all optional format string syntax, as the following features do.
+//!   Without them, `no_std` builds support literal text, `{}`, `{0}`, and specs with literal widths and precisions,
+//!   which keeps the code size small. Disabled syntax is rejected with [`ParseErrorKind::DisabledSyntax`]
+//!   by [`validate`] and [`Tokenizer`], and is reported as malformed and rendered as literal text by [`Arguments`].
+//!
+//! * `"syntax-named"`
+//!   Enable arguments referred to by name, e.g. `{name}`.
+//!
+//! * `"syntax-counts"`
+//!   Enable the width and the precision given by arguments, e.g. `{:{width}.{1}}`.
+//!
+//! * `"syntax-comments"`
+//!   Enable `{# ... #}` comments.
+//!
+//! * `"syntax-raw"`
+//!   Enable `{% raw %} ... {% endraw %}` blocks.
+//!
+//! * `"syntax-escapes"`
+//!   Enable escape sequences, see [`ParseOptions::allow_escapes`].
+//!
+//! * `"serde"`
+//!   Implement `Serialize` and `Deserialize` for [`Spec`] and the `Manifest` types.
+//!
+//! * `"arbitrary"`
+//!   Implement [`Arbitrary`](https://docs.rs/arbitrary/latest/arbitrary/trait.Arbitrary.html)
+//!   for [`Format`], [`Spec`], and its parts, generating valid templates for fuzzing and property testing.
+//!   Implies `"std"`.
+//!
+//! * `"derive"`
+//!   Provide the `DynError` derive macro implementing [`DynError`] and [`Display`]
+//!   for error types with message templates given in attributes.
+//!   Implies `"std"`.
+//!
+//! * `"memchr"`
+//!   Use the [`memchr`](https://docs.rs/memchr) crate to find braces in format strings.
+//!   Speeds up templates consisting mostly of literal text.
+//!   Without it, a portable word-at-a-time scan is used.
+//!
+//! * `"ryu"`
+//!   Provide the [`Float`] argument wrapper, which uses the [`ryu`](https://docs.rs/ryu) crate
+//!   to find the shortest representation of a float, with the same output as `core`,
+//!   and the [`Shortest`] wrapper for the compact round-trip float representation.
+//!
+//! * `"unicode-width"`
+//!   Provide [`WidthMetric::Cells`], which measures the width of arguments in terminal display cells
+//!   using the [`unicode-width`](https://docs.rs/unicode-width) crate.
+//!   Implies `"std"`.
+//!
+//! * `"locale"`
+//!   Provide the [`Currency`] presentation, e.g. `{:cur(EUR,de)}`,
+//!   which formats amounts with the symbol, grouping, and decimal places of a [`Locale`].
+//!   Implies `"std"`.
+//!
+//! * `"allocator_api"`
+//!   Provide [`Arguments::format_in`], which allocates the output in a custom allocator,
+//!   using the [`allocator-api2`](https://docs.rs/allocator-api2) crate.
+//!   Does not require `"std"`.
+//!
+//! * `"bumpalo"`
+//!   Provide [`Arguments::format_bump`], which allocates the output in a [`bumpalo`](https://docs.rs/bumpalo) arena.
+//!   Does not require `"std"`.
+//!
+//! * `"arrayvec"`
+//!   Provide [`Arguments::format_array`], which formats into a fixed-capacity
+//!   [`ArrayString`](https://docs.rs/arrayvec/latest/arrayvec/struct.ArrayString.html) on the stack.
+//!   Does not require `"std"`.
+//!
+//! * `"rhai"`
+//!   Provide [`register_rhai`], which adds the `format` function to a [Rhai](https://rhai.rs) engine.
+//!   Implies `"std"`.
+//!
+//! * `"mlua"`
+//!   Provide [`register_lua`], which adds the `format` function to a Lua 5.4 state
+//!   created with the [`mlua`](https://docs.rs/mlua) crate. Lua is built from the vendored sources.
+//!   Implies `"std"`.
+//!
+//! * `"pyo3"`
+//!   Provide [`register_python`], which adds the `Format` class and the `format` function
+//!   to a Python extension module built with the [`pyo3`](https://docs.rs/pyo3) crate.
+//!   Implies `"std"`.
+//!
+//! * `"wasm"`
+//!   Provide the [`wasm`] module with the `format` and `validate` functions
+//!   exported to JavaScript with [`wasm-bindgen`](https://docs.rs/wasm-bindgen).
+//!   Implies `"std"`.
+//!
+//! * `"capi"`
+//!   Provide the [`capi`] module with the C interface, `dynfmt_format`, declared in `include/dyn_fmt.h`.
+//!   Implies `"std"`.
+//!
+//! * `"cli"`
+//!   Build the `dyn-fmt` binary, which renders a template given as an argument or read from the standard input
+//!   with values given as arguments, e.g. `dyn-fmt '{} of {total}' 3 --arg total=5`,
+//!   or only validates it with `--check`.
+//!   Implies `"std"`.
+//!
+//! * `"zeroize"`
+//!   Provide [`Format::render_zeroizing`] and [`Arguments::to_zeroizing_string`] for rendering secrets,
+//!   which return the output wrapped in [`Zeroizing`](https://docs.rs/zeroize/latest/zeroize/struct.Zeroizing.html),
+//!   and scrub temporary copies of argument text made while rendering, using the [`zeroize`](https://docs.rs/zeroize) crate.
+//!   Implies `"std"`.
+//!
+//! * `"metrics"`
+//!   Provide the process-wide [`Metrics`] counters of parsed templates, catalog lookups, and formatted bytes.
+//!   Implies `"std"`.
 
 #[cfg(feature = "std")]
 extern crate core;
 
-use core::fmt::{self, Display};
-use core::hint::{unreachable_unchecked};
+mod parse;
+
+pub use parse::{ArgRef, Counts, ParseError, ParseErrorKind, ParseOptions, RESERVED_SIGILS, SyntaxFeature, validate};
+
+mod tokenizer;
+
+pub use tokenizer::{SyntaxEvent, Tokenizer};
+
+mod spec;
+
+pub use spec::{Align, Sign, Spec};
+
+mod source;
+
+pub use source::{ArgSource, ArgSourceIter};
+
+mod options;
+
+pub use options::{FmtEvent, FormatOptions, InvalidPlaceholders};
+
+#[cfg(feature = "std")]
+pub use options::WidthMetric;
+
+#[cfg(feature = "ryu")]
+mod float;
+
+#[cfg(feature = "ryu")]
+pub use float::{Float, Shortest};
+
+#[cfg(feature = "std")]
+mod presentation;
+
+#[cfg(feature = "std")]
+pub use presentation::Presentation;
+
+#[cfg(feature = "std")]
+mod seq;
+
+#[cfg(feature = "std")]
+pub use seq::{Pair, Pairs, Separator, Seq};
+
+#[cfg(feature = "std")]
+mod bytes;
+
+#[cfg(feature = "std")]
+pub use bytes::{Bytes, Encoding};
+
+#[cfg(feature = "std")]
+mod quote;
+
+#[cfg(feature = "std")]
+pub use quote::{Csv, Quote, Quoted, SqlQuote};
+
+mod ptr;
+
+pub use ptr::Ptr;
+
+mod fragment;
+
+pub use fragment::Fragment;
+
+mod writer;
+
+pub use writer::SliceWriter;
+
+mod value;
+
+pub use value::Value;
+
+#[cfg(feature = "locale")]
+mod locale;
+
+#[cfg(feature = "locale")]
+pub use locale::{Currency, Locale};
+
+#[cfg(feature = "allocator_api")]
+mod allocator;
+
+#[cfg(feature = "bumpalo")]
+mod bump;
+
+#[cfg(feature = "arrayvec")]
+mod array;
+
+#[cfg(feature = "metrics")]
+mod metrics;
+
+#[cfg(feature = "metrics")]
+pub use metrics::Metrics;
+
+#[cfg(feature = "std")]
+mod format;
+
+#[cfg(feature = "std")]
+pub use format::{Format, FormatArguments, MappedSpan, Mismatch, RenderReport, SourceMap, SpanOrigin, Substitution, TemplateChange};
+
+#[cfg(feature = "std")]
+mod issue;
+
+#[cfg(feature = "std")]
+pub use issue::{Issue, IssueKind};
+
+#[cfg(feature = "std")]
+mod manifest;
+
+#[cfg(feature = "std")]
+pub use manifest::{Manifest, ManifestArg, ManifestPlaceholder};
+
+#[cfg(feature = "std")]
+mod lint;
+
+#[cfg(feature = "std")]
+pub use lint::{Lint, LintKind};
+
+#[cfg(feature = "std")]
+mod catalog;
+
+#[cfg(feature = "std")]
+pub use catalog::Catalog;
+
+#[cfg(feature = "std")]
+mod session;
+
+#[cfg(feature = "std")]
+pub use session::FormatSession;
+
+#[cfg(feature = "std")]
+mod stream;
+
+#[cfg(feature = "std")]
+pub use stream::{Parser, Segment, format_stream};
+
+#[cfg(feature = "std")]
+mod registry;
+
+#[cfg(feature = "std")]
+pub use registry::{AnyDisplay, FormatterRegistry, RegistryArguments};
+
+#[cfg(feature = "std")]
+pub mod conformance;
+
+#[cfg(any(feature = "rhai", feature = "mlua", feature = "pyo3", feature = "wasm"))]
+mod script;
+
+#[cfg(feature = "rhai")]
+pub use script::register_rhai;
+
+#[cfg(feature = "mlua")]
+pub use script::register_lua;
+
+#[cfg(feature = "pyo3")]
+pub use script::register_python;
+
+#[cfg(feature = "capi")]
+pub mod capi;
+
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(feature = "std")]
+mod memo;
+
+#[cfg(feature = "std")]
+pub use memo::Memoized;
+
+#[cfg(feature = "std")]
+mod message;
+
+#[cfg(feature = "std")]
+pub use message::LazyMessage;
+
+#[cfg(feature = "std")]
+mod error;
+
+#[cfg(feature = "std")]
+pub use error::{DynError, MessageArgs};
+
+#[cfg(feature = "derive")]
+pub use dyn_fmt_derive::DynError;
+
+#[cfg(all(test, feature = "derive"))]
+extern crate self as dyn_fmt;
+
+#[cfg(feature = "std")]
+mod store;
+
+#[cfg(feature = "std")]
+mod pack;
+
+#[cfg(feature = "std")]
+pub use pack::{TemplatePack, compile_templates, template_pack_source};
+
+#[cfg(feature = "std")]
+pub use store::{LoadError, TemplateStore};
+
+use core::fmt::{self, Display, Write};
+use core::marker::PhantomData;
+use core::ops::Range;
+use parse::{Token, Tokens};
 
 #[doc(hidden)]
 pub use core::write as std_write;
@@ -35,7 +357,21 @@ pub trait AsStrFormatExt: AsRef<str> {
     /// assert_eq!("{}a{}b{}c".format(&[1, 2]), "1a2bc"); // missing arguments are replaced by empty string
     /// assert_eq!("{{}}{}".format(&[1, 2]), "{}1");
     fn format<'a, T: Display + ?Sized + 'a>(&self, args: impl IntoIterator<Item=&'a T> + Clone) -> String {
-        format!("{}", Arguments::new(self, args))
+        to_string_measured(&Arguments::new(self, args))
+    }
+
+    /// Same as [`format`](AsStrFormatExt::format), but also returns the [`Issue`]s found while rendering,
+    /// such as missing arguments or malformed placeholders, which are otherwise silently ignored.
+    /// # Examples:
+    /// ```rust
+    /// use dyn_fmt::{AsStrFormatExt, IssueKind};
+    /// let (s, issues) = "{} of {}".format_with_issues(&[1]);
+    /// assert_eq!(s, "1 of ");
+    /// assert_eq!(issues[0].kind(), IssueKind::MissingArgument { index: 1 });
+    /// assert_eq!(issues[0].span(), 6 .. 8);
+    /// ```
+    fn format_with_issues<'a, T: Display + ?Sized + 'a>(&self, args: impl IntoIterator<Item=&'a T>) -> (String, Vec<Issue>) {
+        issue::format_with_issues(self.as_ref(), args)
     }
 }
 
@@ -69,18 +405,63 @@ macro_rules! dyn_write {
     }
 }
 
+/// Creates a [`LazyMessage`] from a format string and arguments, rendered only when displayed.
+/// Intended for the `context` method of `anyhow::Context` and similar error context traits.
+/// Unavailable in `no_std` environment.
+///
+/// The format string may be a `&'static str` or a [`String`], the arguments should be owned values
+/// implementing [`Display`], [`Send`], and [`Sync`].
+///
+/// # Examples:
+/// ```rust
+/// use anyhow::Context;
+/// use dyn_fmt::dyn_context;
+/// let template = String::from("cannot parse {} as {}");
+/// let res = "x".parse::<u8>().context(dyn_context!(template, "x", "u8"));
+/// assert_eq!(res.unwrap_err().to_string(), "cannot parse x as u8");
+/// ```
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! dyn_context {
+    ($fmt:expr $(, $args:expr)* $(,)?) => {
+        $crate::LazyMessage::new($fmt)$(.arg($args))*
+    }
+}
+
+/// Includes the [`TemplatePack`] written into `OUT_DIR` by a build script with [`compile_templates`].
+/// The argument is the file name without the `.rs` extension.
+/// Unavailable in `no_std` environment.
+///
+/// # Examples:
+/// ```rust,ignore
+/// static MESSAGES: dyn_fmt::TemplatePack = dyn_fmt::include_templates!("messages");
+///
+/// fn greet(name: &str) -> String {
+///     MESSAGES.catalog().format("greeting", &[name]).unwrap()
+/// }
+/// ```
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! include_templates {
+    ($name:literal) => {
+        include!(concat!(env!("OUT_DIR"), "/", $name, ".rs"))
+    }
+}
+
 /// This structure represents a format string combined with its arguments.
 /// In contrast with [`fmt::Arguments`] this structure can be easily and safely created at runtime.
 #[derive(Clone, Debug)]
 pub struct Arguments<'a, F: AsRef<str>, T: Display + ?Sized + 'a, I: IntoIterator<Item=&'a T> + Clone> {
     fmt: F,
-    args: I
+    args: I,
+    options: FormatOptions,
 }
 
 impl<'a, F: AsRef<str>, T: Display + ?Sized + 'a, I: IntoIterator<Item=&'a T> + Clone> Arguments<'a, F, T, I> {
     /// Creates a new instance of a [`Display`]able structure, representing formatted arguments.
     /// A runtime analog of [`format_args!`](std::format_args) macro.
     /// Extra arguments are ignored, missing arguments are replaced by empty string.
+    /// The arguments are substituted according to [`FormatOptions::current_default`].
     /// # Examples:
     /// ```rust
     /// dyn_fmt::Arguments::new("{}a{}b{}c", &[1, 2, 3]); // "1a2b3c"
@@ -88,80 +469,200 @@ impl<'a, F: AsRef<str>, T: Display + ?Sized + 'a, I: IntoIterator<Item=&'a T> +
     /// dyn_fmt::Arguments::new("{}a{}b{}c", &[1, 2]); // "1a2bc"
     /// dyn_fmt::Arguments::new("{{}}{}", &[1, 2]); // "{}1"
     /// ```
-    pub fn new(fmt: F, args: I) -> Self { Arguments { fmt, args } }
+    pub fn new(fmt: F, args: I) -> Self { Arguments { fmt, args, options: FormatOptions::current_default() } }
+
+    /// Sets the options controlling how arguments are substituted.
+    pub fn with_options(mut self, options: FormatOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Returns the length in bytes of the formatted output.
+    /// Formats all arguments to measure them, without allocating.
+    /// # Examples:
+    /// ```rust
+    /// assert_eq!(dyn_fmt::Arguments::new("{}ab{:>4}", &[1, 2]).formatted_len(), 7);
+    /// ```
+    pub fn formatted_len(&self) -> usize { formatted_len(self) }
+
+    /// Renders the arguments into a string allocated once and zeroed on drop, for output holding secrets.
+    ///
+    /// Buffers allocated by the arguments' own [`Display`] implementations are not scrubbed,
+    /// as well as those allocated by presentations, e.g. `{:url}`.
+    ///
+    /// Available with the `"zeroize"` feature.
+    /// # Examples:
+    /// ```rust
+    /// let url = dyn_fmt::Arguments::new("https://hooks/{}?code={}", &["a1", "9137"]).to_zeroizing_string();
+    /// assert_eq!(url.as_str(), "https://hooks/a1?code=9137");
+    /// ```
+    #[cfg(feature = "zeroize")]
+    pub fn to_zeroizing_string(&self) -> zeroize::Zeroizing<String> { to_scratch(self) }
 }
 
 impl<'a, F: AsRef<str>, T: Display + ?Sized + 'a, I: IntoIterator<Item=&'a T> + Clone> Display for Arguments<'a, F, T, I> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        #[derive(Eq, PartialEq)]
-        enum Brace { Left, Right }
-        fn as_brace(c: u8) -> Option<Brace> {
-            match c {
-                b'{' => Some(Brace::Left),
-                b'}' => Some(Brace::Right),
-                _ => None
-            }
-        }
-        let mut args = self.args.clone().into_iter();
-        let mut fmt = self.fmt.as_ref();
-        let mut piece_end = 0;
-        enum State { Piece, Arg }
-        let mut state = State::Piece;
-        loop {
-            match state {
-                State::Piece => match fmt.as_bytes()[piece_end ..].first() {
-                    None => {
-                        fmt.fmt(f)?;
-                        break;
-                    },
-                    Some(&b) => match as_brace(b) {
-                        Some(b) => {
-                            fmt[.. piece_end].fmt(f)?;
-                            fmt = &fmt[(piece_end + 1) ..];
-                            if fmt.is_empty() { break; }
-                            match b {
-                                Brace::Left => {
-                                    piece_end = 0;
-                                    state = State::Arg;
-                                },
-                                Brace::Right => {
-                                    piece_end = 1;
-                                    state = State::Piece;
-                                }
-                            };
-                        },
-                        None => {
-                            piece_end += 1;
-                        }
-                    }
-                },
-                State::Arg => match fmt.as_bytes().first() {
-                    None => unsafe { unreachable_unchecked() },
-                    Some(&b'}') => {
-                        if let Some(arg) = args.next() {
-                            arg.fmt(f)?;
-                        }
-                        fmt = &fmt[1 ..];
-                        state = State::Piece;
-                    },
-                    Some(_) => {
-                        piece_end = 1;
-                        state = State::Piece;
-                    }
-                },
-            }
+        let options = &self.options;
+        write_args(
+            f,
+            self.fmt.as_ref(),
+            self.args.clone(),
+            options.invalid_placeholders_policy(),
+            |event, _| options.report(event),
+            |arg, spec, f| options.fmt_arg(arg, spec, f)
+        )
+    }
+}
+
+/// Same as [`Arguments`], but owns the argument container,
+/// e.g. a [`Vec`], an array, or any other `AsRef<[T]>` value,
+/// so it can be built from a temporary and returned from a function.
+pub struct OwnedArguments<F: AsRef<str>, T: Display, A: AsRef<[T]>> {
+    fmt: F,
+    args: A,
+    options: FormatOptions,
+    phantom: PhantomData<fn() -> T>,
+}
+
+impl<F: AsRef<str>, T: Display, A: AsRef<[T]>> OwnedArguments<F, T, A> {
+    /// Creates a new instance of a [`Display`]able structure, representing formatted arguments.
+    /// The arguments are substituted according to [`FormatOptions::current_default`].
+    /// # Examples:
+    /// ```rust
+    /// use dyn_fmt::OwnedArguments;
+    /// fn greeting(name: &str) -> OwnedArguments<&'static str, String, Vec<String>> {
+    ///     OwnedArguments::new("Hello, {}{}", vec![name.to_uppercase(), "!".into()])
+    /// }
+    /// assert_eq!(greeting("world").to_string(), "Hello, WORLD!");
+    /// assert_eq!(OwnedArguments::new("{}a{}b", [1, 2]).to_string(), "1a2b");
+    /// ```
+    pub fn new(fmt: F, args: A) -> Self {
+        OwnedArguments { fmt, args, options: FormatOptions::current_default(), phantom: PhantomData }
+    }
+
+    /// Sets the options controlling how arguments are substituted.
+    pub fn with_options(mut self, options: FormatOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Returns the length in bytes of the formatted output.
+    /// Formats all arguments to measure them, without allocating.
+    pub fn formatted_len(&self) -> usize { formatted_len(self) }
+
+    /// Returns the owned arguments.
+    pub fn into_args(self) -> A { self.args }
+}
+
+impl<F: AsRef<str> + Clone, T: Display, A: AsRef<[T]> + Clone> Clone for OwnedArguments<F, T, A> {
+    fn clone(&self) -> Self {
+        OwnedArguments { fmt: self.fmt.clone(), args: self.args.clone(), options: self.options, phantom: PhantomData }
+    }
+}
+
+impl<F: AsRef<str> + fmt::Debug, T: Display, A: AsRef<[T]> + fmt::Debug> fmt::Debug for OwnedArguments<F, T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OwnedArguments")
+            .field("fmt", &self.fmt)
+            .field("args", &self.args)
+            .field("options", &self.options)
+            .finish()
+    }
+}
+
+impl<F: AsRef<str>, T: Display, A: AsRef<[T]>> Display for OwnedArguments<F, T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(&Arguments::new(self.fmt.as_ref(), self.args.as_ref()).with_options(self.options), f)
+    }
+}
+
+/// Renders a format string, formatting every argument with `fmt_arg`.
+/// Malformed fragments are rendered as literal text, except invalid placeholders
+/// rendered according to the `invalid` policy.
+/// Missing arguments and malformed fragments are passed to `report` with their spans.
+pub(crate) fn write_args<'a, T: ?Sized + 'a>(
+    f: &mut fmt::Formatter,
+    fmt: &str,
+    args: impl IntoIterator<Item=&'a T>,
+    invalid: InvalidPlaceholders,
+    mut report: impl FnMut(FmtEvent, Range<usize>),
+    mut fmt_arg: impl FnMut(&T, &Spec, &mut fmt::Formatter) -> fmt::Result
+) -> fmt::Result {
+    let mut args = args.into_iter();
+    let mut index = 0;
+    let tokens = Tokens::new(fmt);
+    let tokens = if invalid == InvalidPlaceholders::Lenient { tokens } else { tokens.whole_placeholders() };
+    for (span, token) in tokens {
+        match token {
+            Token::Literal | Token::Escaped => fmt[span].fmt(f)?,
+            Token::Arg(_, spec, _) => {
+                match args.next() {
+                    Some(arg) => fmt_arg(arg, &spec, f)?,
+                    None => report(FmtEvent::MissingArgument(ArgRef::Index(index)), span),
+                }
+                index += 1;
+            },
+            Token::Error(kind) => {
+                if kind.in_placeholder() { invalid.write(&fmt[span.clone()], f)?; }
+                report(FmtEvent::Malformed { kind, span: span.clone() }, span);
+            },
+            Token::Comment | Token::StrayBrace => { },
         }
+    }
+    Ok(())
+}
+
+struct ByteCounter(usize);
+
+impl Write for ByteCounter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.len();
         Ok(())
     }
 }
 
+/// Returns the length in bytes of the value [`Display`] output.
+pub(crate) fn formatted_len(value: &(impl Display + ?Sized)) -> usize {
+    let mut counter = ByteCounter(0);
+    write!(counter, "{}", value).expect("a Display implementation returned an error unexpectedly");
+    counter.0
+}
+
+/// A temporary copy of an argument text, scrubbed on drop with the `"zeroize"` feature.
+#[cfg(all(feature = "std", feature = "zeroize"))]
+pub(crate) type Scratch = zeroize::Zeroizing<String>;
+
+/// A temporary copy of an argument text, scrubbed on drop with the `"zeroize"` feature.
+#[cfg(all(feature = "std", not(feature = "zeroize")))]
+pub(crate) type Scratch = String;
+
+/// Converts a value to a [`Scratch`] string allocated once, so no copies of the text are left
+/// in reallocated memory.
+#[cfg(feature = "std")]
+pub(crate) fn to_scratch(value: &(impl Display + ?Sized)) -> Scratch {
+    let mut res = Scratch::from(String::with_capacity(formatted_len(value)));
+    write!(res, "{}", value).expect("a Display implementation returned an error unexpectedly");
+    res
+}
+
+/// Converts a value to a [`String`] allocated once, with the capacity measured by a preceding formatting pass.
+/// It is usually faster than repeated reallocations while the string grows.
+#[cfg(feature = "std")]
+pub(crate) fn to_string_measured(value: &(impl Display + ?Sized)) -> String {
+    let mut res = String::with_capacity(formatted_len(value));
+    write!(res, "{}", value).expect("a Display implementation returned an error unexpectedly");
+    #[cfg(feature = "metrics")]
+    metrics::record_bytes(res.len());
+    res
+}
+
 #[cfg(test)]
 mod tests {
     use crate as dyn_fmt;
     #[cfg(feature = "std")]
     use crate::AsStrFormatExt;
     use core::fmt::{self, Write, Display};
-    use core::str::{self};
+    use crate::SliceWriter;
 
     #[cfg(feature = "std")]
     #[test]
@@ -180,41 +681,21 @@ mod tests {
         assert_eq!(format.format(&[2, 3, 4]), "2a3b4c");
     }
 
-    struct Writer<'a> {
-        buf: &'a mut str,
-        len: usize,
-    }
-    
-    impl<'a> fmt::Write for Writer<'a> {
-        fn write_str(&mut self, s: &str) -> fmt::Result {
-            let buf = &mut self.buf[self.len ..];
-            assert!(buf.len() >= s.len());
-            let buf = &mut buf[.. s.len()];
-            unsafe { buf.as_bytes_mut() }.copy_from_slice(s.as_bytes());
-            self.len += s.len();
-            Ok(())
-        }
-    }
-
     #[test]
     fn test_write() {
         let mut buf = [0u8; 128];
-        let buf = str::from_utf8_mut(&mut buf).unwrap();
-        let mut writer = Writer { buf, len: 0 };
+        let mut writer = SliceWriter::new(&mut buf);
         dyn_write!(&mut writer, "{}a{}b{}c", &[1, 2, 3]).unwrap();
-        let len = writer.len;
-        assert_eq!("1a2b3c", &buf[.. len]);
+        assert_eq!("1a2b3c", writer.as_str());
     }
 
     #[test]
     fn write_args() {
         let args_format = dyn_fmt::Arguments::new("{}{}{}", &[1, 2, 3]);
         let mut buf = [0u8; 128];
-        let buf = str::from_utf8_mut(&mut buf).unwrap();
-        let mut writer = Writer { buf, len: 0 };
+        let mut writer = SliceWriter::new(&mut buf);
         write!(&mut writer, "{}", args_format).unwrap();
-        let len = writer.len;
-        assert_eq!("123", &buf[.. len]);
+        assert_eq!("123", writer.as_str());
     }
 
     #[test]
@@ -222,11 +703,109 @@ mod tests {
         let args: &'static [&'static dyn Display] = &[&1, &2, &3];
         let args_format = dyn_fmt::Arguments::new("{}{}{}", args.iter().copied());
         let mut buf = [0u8; 128];
-        let buf = str::from_utf8_mut(&mut buf).unwrap();
-        let mut writer = Writer { buf, len: 0 };
+        let mut writer = SliceWriter::new(&mut buf);
         write!(&mut writer, "{}", args_format).unwrap();
-        let len = writer.len;
-        assert_eq!("123", &buf[.. len]);
+        assert_eq!("123", writer.as_str());
+    }
+
+    #[test]
+    fn write_owned_args() {
+        fn args(n: i32) -> dyn_fmt::OwnedArguments<&'static str, i32, [i32; 3]> {
+            dyn_fmt::OwnedArguments::new("{}-{}-{}", [n, n + 1, n + 2])
+        }
+        let mut buf = [0u8; 128];
+        let mut writer = SliceWriter::new(&mut buf);
+        write!(&mut writer, "{}", args(1)).unwrap();
+        assert_eq!(writer.as_str(), "1-2-3");
+        assert_eq!(args(9).formatted_len(), 7);
+        assert_eq!(args(0).into_args(), [0, 1, 2]);
+    }
+
+    #[cfg(feature = "syntax-comments")]
+    #[test]
+    fn invalid_placeholders() {
+        use dyn_fmt::{FormatOptions, InvalidPlaceholders};
+        let options = |policy| FormatOptions::new().invalid_placeholders(policy);
+        let mut buf = [0u8; 128];
+        let mut writer = SliceWriter::new(&mut buf);
+        let args = dyn_fmt::Arguments::new("{0} {} {a:>5} {{x}} {:99999999999999999999}{#c#} {", &[1, 2]);
+        write!(&mut writer, "{}", args.clone().with_options(options(InvalidPlaceholders::Keep))).unwrap();
+        assert_eq!(writer.as_str(), "{0} 1 {a:>5} {x} {:99999999999999999999} ");
+        writer.clear();
+        write!(&mut writer, "{}", args.clone().with_options(options(InvalidPlaceholders::Marker("?")))).unwrap();
+        assert_eq!(writer.as_str(), "? 1 ? {x} ? ");
+        writer.clear();
+        write!(&mut writer, "{}", args.clone()).unwrap();
+        assert_eq!(writer.as_str(), "0 1 a:>5 {x} :99999999999999999999{#c# ");
+        assert!(write!(&mut writer, "{}", args.with_options(options(InvalidPlaceholders::Error))).is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn thread_default_options() {
+        use dyn_fmt::{FormatOptions, InvalidPlaceholders};
+        let keep = FormatOptions::new().invalid_placeholders(InvalidPlaceholders::Keep);
+        FormatOptions::set_thread_default(Some(keep));
+        assert_eq!(FormatOptions::current_default(), keep);
+        assert_eq!("{x} {}".format(&[1]), "{x} 1");
+        assert_eq!(dyn_fmt::Arguments::new("{x}", &[1]).with_options(FormatOptions::new()).to_string(), "x");
+        std::thread::spawn(|| assert_eq!("{x}".format(&[1]), "x")).join().unwrap();
+        FormatOptions::set_thread_default(None);
+        assert_eq!("{x} {}".format(&[1]), "x 1");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn truncation_indicator() {
+        use dyn_fmt::FormatOptions;
+        let args: [&dyn Display; 5] = [&"абвгдеж", &"абв", &1.23456, &123456, &"ab"];
+        let s = |options| dyn_fmt::Arguments::new("{:.3} {:.3} {:.3} {:.3} {:*^6.1}", &args).with_options(options).to_string();
+        assert_eq!(s(FormatOptions::new()), "абв абв 1.235 123456 **a***");
+        assert_eq!(s(FormatOptions::new().truncation_indicator("…", true)), "аб… абв 1.235 123456 **…***");
+        assert_eq!(s(FormatOptions::new().truncation_indicator("...", false)), "абв... абв 1.235 123456 *a...*");
+        assert_eq!(s(FormatOptions::new().truncation_indicator("", false)), s(FormatOptions::new()));
+        let options = FormatOptions::new().truncation_indicator("…", false).isolate_bidi(true);
+        assert_eq!(dyn_fmt::Arguments::new("{:.1}", &["ab"]).with_options(options).to_string(), "\u{2068}a…\u{2069}");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn width_metric() {
+        use dyn_fmt::{FormatOptions, WidthMetric};
+        struct Plain;
+        impl Display for Plain {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { f.write_str("ab") }
+        }
+        let args: [&dyn Display; 6] = [&"日本", &"ü", &-12, &Plain, &true, &"äöü"];
+        let s = |options| dyn_fmt::Arguments::new("{:>5}|{:*^6}|{:5}|{:4}|{:.2}|{:.3}", &args).with_options(options).to_string();
+        assert_eq!(s(FormatOptions::new()), "   日本|**ü***|  -12|ab|tr|äöü");
+        let bytes = FormatOptions::new().width_metric(WidthMetric::Bytes);
+        assert_eq!(bytes.metric(), WidthMetric::Bytes);
+        assert_eq!(s(bytes), "日本|**ü**|  -12|  ab|tr|ä");
+        assert_eq!(s(bytes.truncation_indicator("~", true)), "日本|**ü**|  -12|  ab|t~|ä~");
+        assert_eq!(WidthMetric::Bytes.prefix("äöü", 5), "äö");
+        assert_eq!(WidthMetric::Chars.len("äöü"), 3);
+        #[cfg(feature = "unicode-width")]
+        {
+            let cells = FormatOptions::new().width_metric(WidthMetric::Cells);
+            assert_eq!(s(cells), " 日本|**ü***|  -12|  ab|tr|äöü");
+            assert_eq!(WidthMetric::Cells.prefix("日本語", 5), "日本");
+        }
+    }
+
+    #[test]
+    fn formatted_len() {
+        assert_eq!(dyn_fmt::Arguments::new("", &[1]).formatted_len(), 0);
+        assert_eq!(dyn_fmt::Arguments::new("{}{{", &[-10]).formatted_len(), 4);
+        assert_eq!(dyn_fmt::Arguments::new("{:*^5}я", &["ы"]).formatted_len(), 8);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn format_allocates_once() {
+        let s = "{}, {:>10}!".format(&["Hello", "world"]);
+        assert_eq!(s, "Hello,      world!");
+        assert_eq!(s.capacity(), s.len());
     }
 
     #[cfg(feature = "std")]
@@ -235,79 +814,95 @@ mod tests {
         let args: &'static [&'static dyn Display] = &[&1, &2, &3];
         let args_format = "{}{}{}".format(args.iter().copied());
         let mut buf = [0u8; 128];
-        let buf = str::from_utf8_mut(&mut buf).unwrap();
-        let mut writer = Writer { buf, len: 0 };
+        let mut writer = SliceWriter::new(&mut buf);
         write!(&mut writer, "{}", args_format).unwrap();
-        let len = writer.len;
-        assert_eq!("123", &buf[.. len]);
+        assert_eq!("123", writer.as_str());
     }
 
     #[test]
     fn write_str() {
         let args_format = dyn_fmt::Arguments::new("abcd{}абвгд{}{}", &[1, 2, 3]);
         let mut buf = [0u8; 128];
-        let buf = str::from_utf8_mut(&mut buf).unwrap();
-        let mut writer = Writer { buf, len: 0 };
+        let mut writer = SliceWriter::new(&mut buf);
         write!(&mut writer, "{}", args_format).unwrap();
-        let len = writer.len;
-        assert_eq!("abcd1абвгд23", &buf[.. len]);
+        assert_eq!("abcd1абвгд23", writer.as_str());
     }
 
     #[test]
     fn complex_case_1() {
         let args_format = dyn_fmt::Arguments::new("{{}}x{{}{}}y{", &[1, 2, 3]);
         let mut buf = [0u8; 128];
-        let buf = str::from_utf8_mut(&mut buf).unwrap();
-        let mut writer = Writer { buf, len: 0 };
+        let mut writer = SliceWriter::new(&mut buf);
         write!(&mut writer, "{}", args_format).unwrap();
-        let len = writer.len;
-        assert_eq!("{}x{{}y", &buf[.. len]);
+        assert_eq!("{}x{{}y", writer.as_str());
     }
 
     #[test]
     fn complex_case_2() {
         let args_format = dyn_fmt::Arguments::new("{{{}}}x{y}", &[1, 2, 3]);
         let mut buf = [0u8; 128];
-        let buf = str::from_utf8_mut(&mut buf).unwrap();
-        let mut writer = Writer { buf, len: 0 };
+        let mut writer = SliceWriter::new(&mut buf);
         write!(&mut writer, "{}", args_format).unwrap();
-        let len = writer.len;
-        assert_eq!("{1}xy", &buf[.. len]);
+        assert_eq!("{1}xy", writer.as_str());
     }
 
     #[test]
     fn complex_case_3() {
         let args_format = dyn_fmt::Arguments::new("{{{}}}x{{}", &[1, 2, 3]);
         let mut buf = [0u8; 128];
-        let buf = str::from_utf8_mut(&mut buf).unwrap();
-        let mut writer = Writer { buf, len: 0 };
+        let mut writer = SliceWriter::new(&mut buf);
         write!(&mut writer, "{}", args_format).unwrap();
-        let len = writer.len;
-        assert_eq!("{1}x{", &buf[.. len]);
+        assert_eq!("{1}x{", writer.as_str());
     }
 
     #[test]
     fn fmt_lifetime() {
-        fn display<'a, 'b>(f: &'a str, i: &'a [u8], buf: &'b mut str) -> &'b str {
+        fn display<'a, 'b>(f: &'a str, i: &'a [u8], buf: &'b mut [u8]) -> &'b str {
             let args_format = dyn_fmt::Arguments::new(f, i);
-            let mut writer = Writer { buf, len: 0 };
+            let mut writer = SliceWriter::new(buf);
             write!(&mut writer, "{}", args_format).unwrap();
-            let len = writer.len;
-            &buf[.. len]
+            writer.into_str()
         }
         let mut buf = [0u8; 128];
-        let buf = str::from_utf8_mut(&mut buf).unwrap();
-        let res = display("{}", &[0], buf);
+        let res = display("{}", &[0], &mut buf);
         assert_eq!("0", res);
     }
 
     #[test]
     fn write_macros() {
         let mut buf = [0u8; 128];
-        let buf = str::from_utf8_mut(&mut buf).unwrap();
-        let mut writer = Writer { buf, len: 0 };
+        let mut writer = SliceWriter::new(&mut buf);
         dyn_write!(&mut writer, "abcd{}абвгд{}{}", &[1, 2, 3]).unwrap();
-        let len = writer.len;
-        assert_eq!("abcd1абвгд23", &buf[.. len]);
+        assert_eq!("abcd1абвгд23", writer.as_str());
+    }
+
+    #[cfg(feature = "syntax-comments")]
+    #[test]
+    fn comments() {
+        let args_format = dyn_fmt::Arguments::new("{# x #}a{}b{##}{}{# y", &[1, 2, 3]);
+        let mut buf = [0u8; 128];
+        let mut writer = SliceWriter::new(&mut buf);
+        write!(&mut writer, "{}", args_format).unwrap();
+        assert_eq!("a1b2# y", writer.as_str());
+    }
+
+    #[test]
+    fn spec_is_delivered_to_arg() {
+        struct Probe;
+
+        impl Display for Probe {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(
+                    f, "{:?} {:?} {} {} {}",
+                    f.width(), f.precision(), f.sign_plus(), f.alternate(), f.sign_aware_zero_pad()
+                )
+            }
+        }
+
+        let args_format = dyn_fmt::Arguments::new("{:+#08.3}|{:<5}", &[Probe, Probe]);
+        let mut buf = [0u8; 128];
+        let mut writer = SliceWriter::new(&mut buf);
+        write!(&mut writer, "{}", args_format).unwrap();
+        assert_eq!("Some(8) Some(3) true true true|Some(5) None false false false", writer.as_str());
     }
 }
@@ -0,0 +1,194 @@
+use crate::{ArgRef, Format, Spec};
+use crate::parse::{Token, Tokens, is_name};
+use core::fmt::{self, Display};
+use core::ops::Range;
+
+/// The kind of a [`Lint`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum LintKind {
+    /// A brace which neither starts a placeholder nor is escaped with a second brace.
+    /// It is dropped from the output, which often means a brace is missing.
+    StrayBrace,
+    /// A placeholder with zero width, e.g. `{:00}`, which has no effect.
+    ZeroWidth,
+    /// A placeholder referring to an argument by index, when the argument just before it is not used
+    /// by any placeholder, width, or precision, e.g. `{2}` in `{0} {2}`.
+    /// The skipped argument is still required, and it is often a typo in the index.
+    SkippedIndex,
+    /// A placeholder repeating the first one, when all placeholders are the same, e.g. the second `{0}` in `{0}-{0}`.
+    /// Only one argument is used then, which often means an index was not updated after copying a placeholder.
+    DuplicateOnly,
+    /// An argument name or index followed by a stray closing brace, e.g. `name}` in `Hello, name}!`,
+    /// which is output as is and likely misses an opening brace.
+    MissingBrace,
+}
+
+impl Display for LintKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LintKind::StrayBrace => write!(f, "stray brace, use `{{{{` or `}}}}` to output a brace"),
+            LintKind::ZeroWidth => write!(f, "zero width has no effect"),
+            LintKind::SkippedIndex => write!(f, "the previous argument is not used"),
+            LintKind::DuplicateOnly => write!(f, "all placeholders are the same"),
+            LintKind::MissingBrace => write!(f, "likely missing opening brace"),
+        }
+    }
+}
+
+/// A suspicious fragment of a format string, reported by [`Format::lint`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Lint {
+    kind: LintKind,
+    span: Range<usize>,
+}
+
+impl Lint {
+    /// The kind of the lint.
+    pub fn kind(&self) -> LintKind { self.kind }
+
+    /// The byte range of the suspicious fragment within the format string.
+    pub fn span(&self) -> Range<usize> { self.span.clone() }
+}
+
+impl Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at {}..{}", self.kind, self.span.start, self.span.end)
+    }
+}
+
+impl Format {
+    /// Checks the format string for fragments which are valid, but likely unintended.
+    ///
+    /// The lints are ordered by their position in the format string.
+    /// # Examples:
+    /// ```rust
+    /// use dyn_fmt::{Format, LintKind};
+    /// let lints = Format::parse("{}a}b").unwrap().lint();
+    /// assert_eq!(lints.len(), 1);
+    /// assert_eq!(lints[0].kind(), LintKind::StrayBrace);
+    /// assert_eq!(lints[0].span(), 3 .. 4);
+    /// let lints = Format::parse("{0} {2}, name}").unwrap().lint();
+    /// let kinds: Vec<_> = lints.iter().map(|x| x.kind()).collect();
+    /// assert_eq!(kinds, [LintKind::SkippedIndex, LintKind::MissingBrace]);
+    /// ```
+    pub fn lint(&self) -> Vec<Lint> {
+        let fmt = self.as_str();
+        let mut lints = Vec::new();
+        let mut next = 0;
+        let mut index = |arg: ArgRef| match arg {
+            ArgRef::Next => {
+                next += 1;
+                Some(next - 1)
+            },
+            ArgRef::Index(index) => Some(index),
+            ArgRef::Name(_) => None,
+        };
+        let mut used = Vec::new();
+        let mut placeholders: Vec<(Option<usize>, Spec, bool, Range<usize>)> = Vec::new();
+        let mut open_brace = false;
+        for (span, token) in Tokens::explicit(fmt, self.options()) {
+            match token {
+                Token::StrayBrace if &fmt[span.clone()] == "{" => {
+                    open_brace = true;
+                    lints.push(Lint { kind: LintKind::StrayBrace, span });
+                },
+                Token::StrayBrace => {
+                    let word = missing_brace_word(&fmt[.. span.start]).filter(|_| !open_brace);
+                    open_brace = false;
+                    lints.push(match word {
+                        Some(start) => Lint { kind: LintKind::MissingBrace, span: start .. span.end },
+                        None => Lint { kind: LintKind::StrayBrace, span },
+                    });
+                },
+                Token::Arg(arg, spec, counts) => {
+                    open_brace = false;
+                    if spec.width() == Some(0) { lints.push(Lint { kind: LintKind::ZeroWidth, span: span.clone() }); }
+                    let arg = index(arg);
+                    let counts = [counts.width, counts.precision].map(|x| x.map(&mut index));
+                    for &i in [arg].iter().chain(counts.iter().flatten()).flatten() {
+                        if used.len() <= i { used.resize(i + 1, false); }
+                        used[i] = true;
+                    }
+                    placeholders.push((arg, spec, counts.iter().all(Option::is_none), span));
+                },
+                _ => { },
+            }
+        }
+        for (arg, _, _, span) in &placeholders {
+            let Some(i) = *arg else { continue; };
+            let first = placeholders.iter().find(|x| x.0 == *arg).is_some_and(|x| x.3 == *span);
+            if first && i != 0 && !used[i - 1] { lints.push(Lint { kind: LintKind::SkippedIndex, span: span.clone() }); }
+        }
+        if let [(Some(_), spec, true, _), rest @ ..] = &placeholders[..] {
+            if !rest.is_empty() && rest.iter().all(|x| (x.0, x.1, x.2) == (placeholders[0].0, *spec, true)) {
+                lints.extend(rest.iter().map(|x| Lint { kind: LintKind::DuplicateOnly, span: x.3.clone() }));
+            }
+        }
+        lints.sort_by_key(|x| x.span.start);
+        lints
+    }
+}
+
+/// Returns the start of the argument name or index ending the text, if it is preceded by whitespace.
+fn missing_brace_word(s: &str) -> Option<usize> {
+    let start = s.trim_end_matches(|c: char| c.is_alphanumeric() || c == '_').len();
+    let word = &s[start ..];
+    let arg = is_name(word) || !word.is_empty() && word.bytes().all(|b| b.is_ascii_digit());
+    (arg && s[.. start].ends_with(char::is_whitespace)).then_some(start)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Format, LintKind};
+
+    fn lints(fmt: &str) -> Vec<(LintKind, core::ops::Range<usize>)> {
+        Format::parse(fmt).unwrap().lint().into_iter().map(|x| (x.kind(), x.span())).collect()
+    }
+
+    #[test]
+    fn clean() {
+        assert!(lints("{{}}{}a{# c #}{% raw %}{{{% endraw %}").is_empty());
+    }
+
+    #[test]
+    fn zero_width() {
+        assert_eq!(lints("{:0}{:00}{:<00.3}"), [(LintKind::ZeroWidth, 4 .. 9), (LintKind::ZeroWidth, 9 .. 17)]);
+    }
+
+    #[test]
+    fn stray_braces() {
+        assert_eq!(lints("{ x}"), [(LintKind::StrayBrace, 0 .. 1), (LintKind::StrayBrace, 3 .. 4)]);
+        assert_eq!(lints("a}{"), [(LintKind::StrayBrace, 1 .. 2)]);
+        assert_eq!(lints("{}{"), [(LintKind::StrayBrace, 2 .. 3)]);
+        assert_eq!(lints("{% if %}"), [(LintKind::StrayBrace, 0 .. 1), (LintKind::StrayBrace, 7 .. 8)]);
+    }
+
+    #[test]
+    fn skipped_index() {
+        assert_eq!(lints("{0} {2} {2} {1}{3}{5}"), [(LintKind::SkippedIndex, 18 .. 21)]);
+        assert_eq!(lints("{1} {name}"), [(LintKind::SkippedIndex, 0 .. 3)]);
+        assert!(lints("{1} {}").is_empty());
+        assert_eq!(lints("{1}"), [(LintKind::SkippedIndex, 0 .. 3)]);
+        assert!(lints("{2:{0}.{1}}{name}").is_empty());
+        assert!(lints("{} {} {1}").is_empty());
+    }
+
+    #[test]
+    fn duplicate_only() {
+        assert_eq!(lints("{0}-{0} {}"), [(LintKind::DuplicateOnly, 4 .. 7), (LintKind::DuplicateOnly, 8 .. 10)]);
+        assert!(lints("{0}").is_empty());
+        assert!(lints("{0} = {0:>4}").is_empty());
+        assert!(lints("{0:{1}} {0:{1}}").is_empty());
+        assert!(lints("{a} {a}").is_empty());
+        assert!(lints("{0} {0} {1}").is_empty());
+    }
+
+    #[test]
+    fn missing_brace() {
+        assert_eq!(lints("Hello, name}!"), [(LintKind::MissingBrace, 7 .. 12)]);
+        assert_eq!(lints("{} 1}"), [(LintKind::MissingBrace, 3 .. 5)]);
+        assert_eq!(lints("a 1x}"), [(LintKind::StrayBrace, 4 .. 5)]);
+        assert_eq!(lints("x _}"), [(LintKind::StrayBrace, 3 .. 4)]);
+    }
+}
@@ -0,0 +1,151 @@
+use core::fmt::{self, Display};
+use core::str;
+
+/// The conventions of a locale for formatting numbers and currency amounts.
+///
+/// Available with the `"locale"` feature.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
+#[non_exhaustive]
+pub enum Locale {
+    /// English, `-$1,234.56`.
+    #[default]
+    En,
+    /// German, `-1.234,56 €`.
+    De,
+    /// French, `-1 234,56 €`, grouped with narrow no-break spaces.
+    Fr,
+    /// Russian, `-1 234,56 ₽`, grouped with no-break spaces.
+    Ru,
+}
+
+impl Locale {
+    fn parse(s: &str) -> Option<Locale> {
+        match s {
+            "en" => Some(Locale::En),
+            "de" => Some(Locale::De),
+            "fr" => Some(Locale::Fr),
+            "ru" => Some(Locale::Ru),
+            _ => None,
+        }
+    }
+
+    /// The tag used in specs, e.g. `de` in `{:cur(EUR,de)}`.
+    pub fn tag(&self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::De => "de",
+            Locale::Fr => "fr",
+            Locale::Ru => "ru",
+        }
+    }
+
+    fn decimal_separator(&self) -> char {
+        match self {
+            Locale::En => '.',
+            Locale::De | Locale::Fr | Locale::Ru => ',',
+        }
+    }
+
+    fn group_separator(&self) -> char {
+        match self {
+            Locale::En => ',',
+            Locale::De => '.',
+            Locale::Fr => '\u{202F}',
+            Locale::Ru => '\u{A0}',
+        }
+    }
+
+    fn symbol_first(&self) -> bool { *self == Locale::En }
+}
+
+/// A currency presentation, `cur(CODE)` or `cur(CODE,locale)`, e.g. `{:cur(EUR,de)}`.
+///
+/// The argument is an amount in major units, e.g. `1234.5` for `$1,234.50`.
+/// It is rounded half to even to the number of minor digits of the currency
+/// (two for most currencies), grouped, and combined with the currency symbol
+/// according to the [`Locale`], English by default. Currencies without a known symbol
+/// are shown by their ISO 4217 code.
+///
+/// Available with the `"locale"` feature.
+/// # Examples:
+/// ```rust
+/// use dyn_fmt::Arguments;
+/// let s = Arguments::new("{:cur(USD)} / {:cur(EUR,de)} / {:cur(JPY)}", &[1234.5, -1234.5, 1234.5]).to_string();
+/// assert_eq!(s, "$1,234.50 / -1.234,50\u{A0}€ / ¥1,234");
+/// ```
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Currency {
+    code: [u8; 3],
+    locale: Locale,
+}
+
+impl Currency {
+    pub(crate) fn parse(s: &str) -> Option<Currency> {
+        let s = s.strip_prefix("cur(")?.strip_suffix(')')?;
+        let (code, locale) = match s.split_once(',') {
+            Some((code, locale)) => (code, Locale::parse(locale)?),
+            None => (s, Locale::En),
+        };
+        let code: [u8; 3] = code.as_bytes().try_into().ok()?;
+        if !code.iter().all(|b| b.is_ascii_uppercase()) { return None; }
+        Some(Currency { code, locale })
+    }
+
+    /// The ISO 4217 currency code, e.g. `USD`.
+    pub fn code(&self) -> &str { str::from_utf8(&self.code).unwrap() }
+
+    /// The locale used to format amounts.
+    pub fn locale(&self) -> Locale { self.locale }
+
+    /// The currency symbol, or `None` if the code is used instead.
+    fn symbol(&self) -> Option<&'static str> {
+        Some(match &self.code {
+            b"USD" => "$",
+            b"EUR" => "€",
+            b"GBP" => "£",
+            b"JPY" | b"CNY" => "¥",
+            b"RUB" => "₽",
+            b"INR" => "₹",
+            b"KRW" => "₩",
+            b"UAH" => "₴",
+            b"ILS" => "₪",
+            _ => return None,
+        })
+    }
+
+    /// The number of digits after the decimal point.
+    pub(crate) fn minor_digits(&self) -> usize {
+        match &self.code {
+            b"JPY" | b"KRW" | b"CLP" | b"ISK" | b"VND" => 0,
+            b"BHD" | b"IQD" | b"JOD" | b"KWD" | b"LYD" | b"OMR" | b"TND" => 3,
+            _ => 2,
+        }
+    }
+
+    /// Combines the rounded amount digits with the currency symbol.
+    pub(crate) fn present(&self, int: &[u8], frac: &[u8]) -> String {
+        let locale = self.locale;
+        let mut amount = String::with_capacity(int.len() * 4 / 3 + frac.len() + 2);
+        for (i, &b) in int.iter().enumerate() {
+            if i != 0 && (int.len() - i) % 3 == 0 { amount.push(locale.group_separator()); }
+            amount.push(char::from(b));
+        }
+        if !frac.is_empty() {
+            amount.push(locale.decimal_separator());
+            amount.extend(frac.iter().map(|&b| char::from(b)));
+        }
+        match (self.symbol(), locale.symbol_first()) {
+            (Some(symbol), true) => format!("{}{}", symbol, amount),
+            (None, true) => format!("{}\u{A0}{}", self.code(), amount),
+            (symbol, false) => format!("{}\u{A0}{}", amount, symbol.unwrap_or(self.code())),
+        }
+    }
+}
+
+impl Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "cur({}", self.code())?;
+        if self.locale != Locale::En { write!(f, ",{}", self.locale.tag())?; }
+        write!(f, ")")
+    }
+}
@@ -0,0 +1,177 @@
+use crate::{ArgRef, Format, Segment, Spec};
+use crate::spec::WithSpec;
+use core::fmt::{self, Display, Write};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The argument a placeholder of a [`Manifest`] refers to.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[non_exhaustive]
+pub enum ManifestArg {
+    /// A positional argument. Placeholders `{}` are resolved to indices as well.
+    Index(usize),
+    /// A named argument.
+    Name(String),
+}
+
+impl Display for ManifestArg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ManifestArg::Index(index) => write!(f, "{}", index),
+            ManifestArg::Name(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+/// A placeholder description in a [`Manifest`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ManifestPlaceholder {
+    arg: ManifestArg,
+    spec: Spec,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    width: Option<ManifestArg>,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    precision: Option<ManifestArg>,
+}
+
+impl ManifestPlaceholder {
+    /// The argument the placeholder refers to.
+    pub fn arg(&self) -> &ManifestArg { &self.arg }
+
+    /// The placeholder format spec.
+    pub fn spec(&self) -> Spec { self.spec }
+
+    /// The argument giving the width, e.g. `w` in `{:{w}}`.
+    pub fn width(&self) -> Option<&ManifestArg> { self.width.as_ref() }
+
+    /// The argument giving the precision, e.g. `1` in `{:.{1}}`.
+    pub fn precision(&self) -> Option<&ManifestArg> { self.precision.as_ref() }
+}
+
+/// A machine-readable description of a template, returned by [`Format::manifest`].
+///
+/// Serializable with the `serde` feature enabled.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Manifest {
+    indices: Vec<usize>,
+    names: Vec<String>,
+    placeholders: Vec<ManifestPlaceholder>,
+    example: String,
+}
+
+impl Manifest {
+    /// Indices of the positional arguments used by the template, including those giving widths and precisions,
+    /// in ascending order.
+    pub fn indices(&self) -> &[usize] { &self.indices }
+
+    /// Names of the named arguments used by the template, including those giving widths and precisions,
+    /// in order of their first appearance.
+    pub fn names(&self) -> &[String] { &self.names }
+
+    /// All placeholders of the template, in order of appearance.
+    pub fn placeholders(&self) -> &[ManifestPlaceholder] { &self.placeholders }
+
+    /// The template rendered with every placeholder replaced by the argument index or name in square brackets,
+    /// formatted according to the placeholder spec, e.g. `Hello, [user]!`.
+    /// Widths and precisions given by arguments are not applied.
+    pub fn example(&self) -> &str { &self.example }
+}
+
+struct Label<'a>(&'a ManifestArg);
+
+impl<'a> Display for Label<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(&format!("[{}]", self.0))
+    }
+}
+
+impl Format {
+    /// Describes the template placeholders, e.g. to export them to translators,
+    /// or to generate an input form for the template.
+    /// # Examples:
+    /// ```rust
+    /// use dyn_fmt::{Format, ManifestArg};
+    /// let manifest = Format::parse("{user} has {} new {1}").unwrap().manifest();
+    /// assert_eq!(manifest.indices(), &[0, 1]);
+    /// assert_eq!(manifest.names(), &["user"]);
+    /// assert_eq!(manifest.placeholders()[0].arg(), &ManifestArg::Name("user".into()));
+    /// assert_eq!(manifest.example(), "[user] has [0] new [1]");
+    /// ```
+    pub fn manifest(&self) -> Manifest {
+        let mut manifest = Manifest { indices: Vec::new(), names: Vec::new(), placeholders: Vec::new(), example: String::new() };
+        for segment in self.segments() {
+            let (arg, spec, counts) = match segment {
+                Segment::Literal(s) => {
+                    manifest.example.push_str(s);
+                    continue;
+                },
+                Segment::Arg(arg, spec, counts) => (arg, spec, counts),
+            };
+            let mut add = |arg: ArgRef| match arg {
+                ArgRef::Name(name) => {
+                    if !manifest.names.iter().any(|x| x == name) { manifest.names.push(name.to_string()); }
+                    ManifestArg::Name(name.to_string())
+                },
+                ArgRef::Index(index) => {
+                    if !manifest.indices.contains(&index) { manifest.indices.push(index); }
+                    ManifestArg::Index(index)
+                },
+                ArgRef::Next => unreachable!(),
+            };
+            let arg = add(arg);
+            let width = counts.width().map(&mut add);
+            let precision = counts.precision().map(&mut add);
+            write!(manifest.example, "{}", WithSpec { arg: &Label(&arg), spec: &spec }).unwrap();
+            manifest.placeholders.push(ManifestPlaceholder { arg, spec, width, precision });
+        }
+        manifest.indices.sort_unstable();
+        manifest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Format, ManifestArg};
+
+    #[test]
+    fn manifest() {
+        let manifest = Format::parse("{x:>5}|{2}{}{x}{}{:*<6}").unwrap().manifest();
+        assert_eq!(manifest.indices(), &[0, 1, 2]);
+        assert_eq!(manifest.names(), &["x"]);
+        let args: Vec<_> = manifest.placeholders().iter().map(|x| x.arg().to_string()).collect();
+        assert_eq!(args, ["x", "2", "0", "x", "1", "2"]);
+        assert_eq!(manifest.placeholders()[5].arg(), &ManifestArg::Index(2));
+        assert_eq!(manifest.placeholders()[5].spec().to_string(), "*<6");
+        assert_eq!(manifest.example(), "  [x]|[2][0][x][1][2]***");
+    }
+
+    #[test]
+    fn count_args() {
+        let manifest = Format::parse("{:{w}.{1}} {:>{width}.{w}}").unwrap().manifest();
+        assert_eq!(manifest.indices(), &[0, 1]);
+        assert_eq!(manifest.names(), &["w", "width"]);
+        let placeholders = manifest.placeholders();
+        assert_eq!(placeholders[0].width(), Some(&ManifestArg::Name("w".into())));
+        assert_eq!(placeholders[0].precision(), Some(&ManifestArg::Index(1)));
+        assert_eq!(placeholders[1].arg(), &ManifestArg::Index(1));
+        assert_eq!(placeholders[1].width(), Some(&ManifestArg::Name("width".into())));
+        assert_eq!(manifest.example(), "[0] [1]");
+        assert!(Format::parse("{}").unwrap().manifest().placeholders()[0].width().is_none());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialize() {
+        let manifest = Format::parse("{} {name:>4}").unwrap().manifest();
+        let json = serde_json::to_string(&manifest).unwrap();
+        assert_eq!(json, concat!(
+            r#"{"indices":[0],"names":["name"],"placeholders":[{"arg":{"index":0},"spec":""},"#,
+            r#"{"arg":{"name":"name"},"spec":">4"}],"example":"[0] [name]"}"#
+        ));
+        assert_eq!(serde_json::from_str::<crate::Manifest>(&json).unwrap(), manifest);
+    }
+}
@@ -0,0 +1,90 @@
+use core::fmt::{self, Display, Write};
+use std::sync::OnceLock;
+
+/// A [`Display`]able value rendered once, on first display, with the output cached and reused afterwards.
+///
+/// Useful when the same [`Arguments`](crate::Arguments) or another expensive value
+/// is formatted into multiple sinks. The cached output is written as is, ignoring the spec
+/// it is formatted with. If rendering the value fails, the error is returned and nothing is cached.
+///
+/// Unavailable in `no_std` environment.
+/// # Examples:
+/// ```rust
+/// use dyn_fmt::{Arguments, Memoized};
+/// let message = Memoized::new(Arguments::new("{} of {}", &[3, 5]));
+/// assert!(!message.is_rendered());
+/// assert_eq!(message.to_string(), "3 of 5");
+/// assert!(message.is_rendered());
+/// assert_eq!(format!("[{}]", message), "[3 of 5]");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Memoized<T: Display> {
+    value: T,
+    output: OnceLock<String>,
+}
+
+impl<T: Display> Memoized<T> {
+    /// Wraps the value, deferring its rendering until the first display.
+    pub fn new(value: T) -> Self { Memoized { value, output: OnceLock::new() } }
+
+    /// Returns the wrapped value.
+    pub fn value(&self) -> &T { &self.value }
+
+    /// Unwraps the value, dropping the cached output.
+    pub fn into_inner(self) -> T { self.value }
+
+    /// Returns `true` if the output is already rendered and cached.
+    pub fn is_rendered(&self) -> bool { self.output.get().is_some() }
+
+    /// Returns the output, rendering it if it is not cached yet.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the [`Display`] implementation of the value returns an error.
+    pub fn as_str(&self) -> &str {
+        self.rendered().expect("a Display implementation returned an error unexpectedly")
+    }
+
+    fn rendered(&self) -> Result<&str, fmt::Error> {
+        if let Some(output) = self.output.get() { return Ok(output); }
+        let mut output = String::new();
+        write!(output, "{}", self.value)?;
+        Ok(self.output.get_or_init(|| output))
+    }
+}
+
+impl<T: Display> Display for Memoized<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { f.write_str(self.rendered()?) }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Arguments, Memoized};
+    use core::cell::Cell;
+    use core::fmt::{self, Display, Write};
+
+    struct Counted<'a>(&'a Cell<usize>);
+
+    impl<'a> Display for Counted<'a> {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            self.0.set(self.0.get() + 1);
+            if self.0.get() == 1 { return Err(fmt::Error); }
+            write!(f, "#{}", self.0.get())
+        }
+    }
+
+    #[test]
+    fn memoized() {
+        let count = Cell::new(0);
+        let args = [Counted(&count)];
+        let message = Memoized::new(Arguments::new("{}!", &args));
+        assert_eq!(count.get(), 0);
+        assert!(write!(String::new(), "{}", message).is_err());
+        assert!(!message.is_rendered());
+        assert_eq!(format!("{:>9}|{}", message, message), "#2!|#2!");
+        assert_eq!(message.as_str(), "#2!");
+        assert_eq!(count.get(), 2);
+        assert_eq!(message.value().formatted_len(), 3);
+        assert_eq!(message.into_inner().to_string(), "#4!");
+    }
+}
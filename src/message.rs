@@ -0,0 +1,86 @@
+use crate::Arguments;
+use core::fmt::{self, Display};
+use std::borrow::Cow;
+
+/// A format string combined with owned arguments, rendered only when displayed.
+///
+/// Useful as an error message or an error context, e.g. with `anyhow::Context`,
+/// which is expensive to render and usually is not displayed. It can be also used as an error itself.
+/// See also the [`dyn_context!`](crate::dyn_context) macro.
+///
+/// Unavailable in `no_std` environment.
+/// # Examples:
+/// ```rust
+/// use dyn_fmt::LazyMessage;
+/// let message = LazyMessage::new("cannot open {} at line {}").arg("config.toml").arg(12);
+/// assert_eq!(message.to_string(), "cannot open config.toml at line 12");
+/// ```
+pub struct LazyMessage {
+    fmt: Cow<'static, str>,
+    args: Vec<Box<dyn Display + Send + Sync>>,
+}
+
+impl LazyMessage {
+    /// Creates a message with the format string and no arguments.
+    pub fn new(fmt: impl Into<Cow<'static, str>>) -> Self { LazyMessage { fmt: fmt.into(), args: Vec::new() } }
+
+    /// Appends the next positional argument.
+    pub fn arg(mut self, arg: impl Display + Send + Sync + 'static) -> Self {
+        self.args.push(Box::new(arg));
+        self
+    }
+
+    /// Returns the format string.
+    pub fn as_str(&self) -> &str { &self.fmt }
+}
+
+impl Display for LazyMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Arguments::new(&*self.fmt, self.args.iter().map(|x| &**x)).fmt(f)
+    }
+}
+
+impl fmt::Debug for LazyMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LazyMessage")
+            .field("fmt", &self.fmt)
+            .field("args", &self.args.iter().map(|x| x.to_string()).collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl std::error::Error for LazyMessage { }
+
+#[cfg(test)]
+mod tests {
+    use crate::{LazyMessage, dyn_context};
+    use anyhow::Context;
+    use core::fmt::{self, Display};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static RENDERED: AtomicUsize = AtomicUsize::new(0);
+
+    struct Counted(u32);
+
+    impl Display for Counted {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            RENDERED.fetch_add(1, Ordering::Relaxed);
+            Display::fmt(&self.0, f)
+        }
+    }
+
+    #[test]
+    fn lazy_message() {
+        let ok: Result<u32, fmt::Error> = Ok(1);
+        assert_eq!(ok.context(dyn_context!("step {:>3}", Counted(1))).unwrap(), 1);
+        let e = Err::<(), _>(fmt::Error).context(dyn_context!(String::from("step {}/{}"), Counted(2), "x")).unwrap_err();
+        assert_eq!(RENDERED.load(Ordering::Relaxed), 0);
+        assert_eq!(e.to_string(), "step 2/x");
+        assert_eq!(RENDERED.load(Ordering::Relaxed), 1);
+        let message = LazyMessage::new("{} {}").arg(1);
+        assert_eq!(message.as_str(), "{} {}");
+        assert_eq!(format!("{:?}", message), r#"LazyMessage { fmt: "{} {}", args: ["1"] }"#);
+        let e: Box<dyn std::error::Error + Send + Sync> = Box::new(message);
+        assert_eq!(e.to_string(), "1 ");
+    }
+}
@@ -0,0 +1,81 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+static PARSES: AtomicUsize = AtomicUsize::new(0);
+static CACHE_HITS: AtomicUsize = AtomicUsize::new(0);
+static CACHE_MISSES: AtomicUsize = AtomicUsize::new(0);
+static BYTES_FORMATTED: AtomicUsize = AtomicUsize::new(0);
+
+/// A snapshot of the process-wide counters of the library activity.
+///
+/// The counters are updated with relaxed atomic operations, so they are cheap to maintain,
+/// but a snapshot taken while other threads are formatting is not necessarily consistent.
+/// They wrap around on overflow.
+///
+/// Available with the `"metrics"` feature.
+/// # Examples:
+/// ```rust
+/// use dyn_fmt::{Catalog, Format, Metrics};
+/// let before = Metrics::get();
+/// let mut catalog = Catalog::new();
+/// catalog.insert("greeting", Format::parse("Hello, {}!").unwrap());
+/// assert_eq!(catalog.format("greeting", &["world"]).unwrap(), "Hello, world!");
+/// assert!(catalog.format("farewell", &["world"]).is_none());
+/// let delta = Metrics::get().since(&before);
+/// assert_eq!((delta.parses(), delta.cache_hits(), delta.cache_misses(), delta.bytes_formatted()), (1, 1, 1, 13));
+/// ```
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub struct Metrics {
+    parses: usize,
+    cache_hits: usize,
+    cache_misses: usize,
+    bytes_formatted: usize,
+}
+
+impl Metrics {
+    /// Reads the current values of the counters.
+    pub fn get() -> Metrics {
+        Metrics {
+            parses: PARSES.load(Ordering::Relaxed),
+            cache_hits: CACHE_HITS.load(Ordering::Relaxed),
+            cache_misses: CACHE_MISSES.load(Ordering::Relaxed),
+            bytes_formatted: BYTES_FORMATTED.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Returns the activity happened between `earlier` and `self`.
+    pub fn since(&self, earlier: &Metrics) -> Metrics {
+        Metrics {
+            parses: self.parses.wrapping_sub(earlier.parses),
+            cache_hits: self.cache_hits.wrapping_sub(earlier.cache_hits),
+            cache_misses: self.cache_misses.wrapping_sub(earlier.cache_misses),
+            bytes_formatted: self.bytes_formatted.wrapping_sub(earlier.bytes_formatted),
+        }
+    }
+
+    /// The number of templates parsed by [`Format::parse`](crate::Format::parse) and similar functions,
+    /// whether successfully or not.
+    pub fn parses(&self) -> usize { self.parses }
+
+    /// The number of messages found in a [`Catalog`](crate::Catalog).
+    pub fn cache_hits(&self) -> usize { self.cache_hits }
+
+    /// The number of messages looked up in a [`Catalog`](crate::Catalog) but not found.
+    pub fn cache_misses(&self) -> usize { self.cache_misses }
+
+    /// The number of bytes in strings produced by [`format`](crate::AsStrFormatExt::format),
+    /// [`Format::render`](crate::Format::render), [`Catalog::format`](crate::Catalog::format), and similar functions.
+    /// Output written with [`write!`] is not counted.
+    pub fn bytes_formatted(&self) -> usize { self.bytes_formatted }
+}
+
+pub(crate) fn record_parse() {
+    PARSES.fetch_add(1, Ordering::Relaxed);
+}
+
+pub(crate) fn record_lookup(hit: bool) {
+    if hit { &CACHE_HITS } else { &CACHE_MISSES }.fetch_add(1, Ordering::Relaxed);
+}
+
+pub(crate) fn record_bytes(len: usize) {
+    BYTES_FORMATTED.fetch_add(len, Ordering::Relaxed);
+}
@@ -0,0 +1,387 @@
+use crate::{ArgRef, ParseErrorKind, Spec};
+#[cfg(feature = "std")]
+use crate::Align;
+use crate::spec::fmt_arg;
+#[cfg(feature = "std")]
+use crate::spec::WithSpec;
+#[cfg(feature = "std")]
+use crate::{Scratch, formatted_len, to_scratch};
+use core::fmt::{self, Display, Write};
+use core::hash::{Hash, Hasher};
+use core::ops::Range;
+#[cfg(feature = "std")]
+use std::cell::Cell;
+#[cfg(feature = "std")]
+use std::sync::RwLock;
+#[cfg(feature = "std")]
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[cfg(feature = "std")]
+static PROCESS_DEFAULT: RwLock<Option<FormatOptions>> = RwLock::new(None);
+
+#[cfg(feature = "std")]
+static PROCESS_DEFAULT_SET: AtomicBool = AtomicBool::new(false);
+
+#[cfg(feature = "std")]
+thread_local! {
+    static THREAD_DEFAULT: Cell<Option<FormatOptions>> = const { Cell::new(None) };
+}
+
+/// Options controlling how arguments are substituted into a format string.
+///
+/// Use [`Arguments::with_options`](crate::Arguments::with_options)
+/// or `FormatArguments::with_options` to apply them.
+/// # Examples:
+/// ```rust
+/// use dyn_fmt::{Arguments, FormatOptions};
+/// let options = FormatOptions::new().isolate_bidi(true);
+/// let s = Arguments::new("{} wrote", &["שלום"]).with_options(options).to_string();
+/// assert_eq!(s, "\u{2068}שלום\u{2069} wrote");
+/// ```
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub struct FormatOptions {
+    isolate_bidi: bool,
+    invalid_placeholders: InvalidPlaceholders,
+    on_event: Option<Callback>,
+    #[cfg(feature = "std")]
+    truncation: Option<(&'static str, bool)>,
+    #[cfg(feature = "std")]
+    width_metric: WidthMetric,
+}
+
+/// The [`FormatOptions::on_event`] callback, compared by its address.
+#[derive(Debug, Clone, Copy)]
+struct Callback(fn(FmtEvent));
+
+impl PartialEq for Callback {
+    fn eq(&self, other: &Self) -> bool { self.0 as usize == other.0 as usize }
+}
+
+impl Eq for Callback { }
+
+impl Hash for Callback {
+    fn hash<H: Hasher>(&self, state: &mut H) { (self.0 as usize).hash(state) }
+}
+
+/// A recoverable problem found while rendering, reported to the [`FormatOptions::on_event`] callback.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum FmtEvent<'a> {
+    /// The placeholder refers to an argument which is not provided. It is replaced by empty string.
+    /// The argument giving a width or a precision is reported the same way.
+    MissingArgument(ArgRef<'a>),
+    /// The fragment of the format string at the byte range is malformed
+    /// and is rendered according to the [`InvalidPlaceholders`] policy.
+    Malformed { kind: ParseErrorKind, span: Range<usize> },
+}
+
+/// How [`Arguments`](crate::Arguments) renders an invalid placeholder,
+/// e.g. `{:q}`, or a placeholder referring to an argument explicitly, e.g. `{abc}`,
+/// which is supported by `Format` only. Invalid placeholders do not consume arguments.
+/// # Examples:
+/// ```rust
+/// use dyn_fmt::{Arguments, FormatOptions, InvalidPlaceholders};
+/// let s = |policy| Arguments::new("{abc} {:q} {}", &[1]).with_options(FormatOptions::new().invalid_placeholders(policy)).to_string();
+/// assert_eq!(s(InvalidPlaceholders::Lenient), "abc :q 1");
+/// assert_eq!(s(InvalidPlaceholders::Keep), "{abc} {:q} 1");
+/// assert_eq!(s(InvalidPlaceholders::Marker("<invalid>")), "<invalid> <invalid> 1");
+/// ```
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum InvalidPlaceholders {
+    /// Drops the braces and renders the rest as literal text, e.g. `abc`. The default.
+    #[default]
+    Lenient,
+    /// Renders the placeholder as is, e.g. `{abc}`.
+    Keep,
+    /// Renders the marker instead of the placeholder.
+    Marker(&'static str),
+    /// Fails formatting with [`fmt::Error`].
+    /// Note that [`to_string`](std::string::ToString::to_string) panics in that case,
+    /// so the arguments should be written with [`write!`] instead.
+    Error,
+}
+
+/// The unit the width and the precision of a string argument are measured in, see [`FormatOptions::width_metric`].
+///
+/// Unavailable in `no_std` environment.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum WidthMetric {
+    /// Unicode scalar values, the same as in the [`format!`](std::format) macro. The default.
+    #[default]
+    Chars,
+    /// UTF-8 bytes, e.g. for fixed-size fields of a protocol. A string is never cut inside a character.
+    Bytes,
+    /// Terminal display cells, where wide characters take two cells, and combining characters take none.
+    ///
+    /// Available with the `"unicode-width"` feature.
+    #[cfg(feature = "unicode-width")]
+    Cells,
+}
+
+#[cfg(feature = "std")]
+impl WidthMetric {
+    /// Returns the length of the string in units of the metric.
+    pub fn len(self, s: &str) -> usize {
+        match self {
+            WidthMetric::Chars => s.chars().count(),
+            WidthMetric::Bytes => s.len(),
+            #[cfg(feature = "unicode-width")]
+            WidthMetric::Cells => unicode_width::UnicodeWidthStr::width(s),
+        }
+    }
+
+    /// Returns the longest prefix of the string not longer than `n` units of the metric.
+    pub fn prefix(self, s: &str, n: usize) -> &str {
+        let mut len = 0;
+        for (i, c) in s.char_indices() {
+            len += match self {
+                WidthMetric::Chars => 1,
+                WidthMetric::Bytes => c.len_utf8(),
+                #[cfg(feature = "unicode-width")]
+                WidthMetric::Cells => unicode_width::UnicodeWidthChar::width(c).unwrap_or(0),
+            };
+            if len > n { return &s[.. i]; }
+        }
+        s
+    }
+}
+
+impl InvalidPlaceholders {
+    pub(crate) fn write(&self, placeholder: &str, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InvalidPlaceholders::Lenient => Ok(()),
+            InvalidPlaceholders::Keep => f.write_str(placeholder),
+            InvalidPlaceholders::Marker(marker) => f.write_str(marker),
+            InvalidPlaceholders::Error => Err(fmt::Error),
+        }
+    }
+}
+
+impl FormatOptions {
+    /// Creates options substituting arguments as is.
+    pub fn new() -> Self { Self::default() }
+
+    /// Wraps every substituted argument, including its padding, in the Unicode
+    /// FIRST STRONG ISOLATE (U+2068) and POP DIRECTIONAL ISOLATE (U+2069) characters.
+    ///
+    /// It prevents right-to-left text in an argument from visually reordering the surrounding text,
+    /// which can be used to make a message look different from what it is.
+    pub fn isolate_bidi(mut self, isolate: bool) -> Self {
+        self.isolate_bidi = isolate;
+        self
+    }
+
+    /// Whether substituted arguments are wrapped in bidi isolate characters.
+    pub fn isolates_bidi(&self) -> bool { self.isolate_bidi }
+
+    /// Sets how [`Arguments`](crate::Arguments) renders invalid placeholders,
+    /// [`InvalidPlaceholders::Lenient`] by default.
+    pub fn invalid_placeholders(mut self, policy: InvalidPlaceholders) -> Self {
+        self.invalid_placeholders = policy;
+        self
+    }
+
+    /// How invalid placeholders are rendered.
+    pub fn invalid_placeholders_policy(&self) -> InvalidPlaceholders { self.invalid_placeholders }
+
+    /// Sets the function called for every [`FmtEvent`] while rendering,
+    /// which allows counting or logging template problems without failing.
+    /// # Examples:
+    /// ```rust
+    /// use dyn_fmt::{Arguments, FmtEvent, FormatOptions};
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// static MISSING: AtomicUsize = AtomicUsize::new(0);
+    /// let options = FormatOptions::new().on_event(|event| if let FmtEvent::MissingArgument(_) = event {
+    ///     MISSING.fetch_add(1, Ordering::Relaxed);
+    /// });
+    /// assert_eq!(Arguments::new("{} of {}", &[1]).with_options(options).to_string(), "1 of ");
+    /// assert_eq!(MISSING.load(Ordering::Relaxed), 1);
+    /// ```
+    pub fn on_event(mut self, callback: fn(FmtEvent)) -> Self {
+        self.on_event = Some(Callback(callback));
+        self
+    }
+
+    /// Sets the options used by [`Arguments::new`](crate::Arguments::new),
+    /// and so by [`format`](crate::AsStrFormatExt::format) and [`dyn_write!`](crate::dyn_write),
+    /// in all threads which have no [thread default](FormatOptions::set_thread_default).
+    /// `None` restores [`FormatOptions::new`].
+    ///
+    /// Unavailable in `no_std` environment.
+    /// # Examples:
+    /// ```rust
+    /// use dyn_fmt::{AsStrFormatExt, FormatOptions, InvalidPlaceholders};
+    /// let strict = FormatOptions::new().invalid_placeholders(InvalidPlaceholders::Keep);
+    /// FormatOptions::set_process_default(Some(strict));
+    /// assert_eq!("{name}: {}".format(&[1]), "{name}: 1");
+    /// std::thread::spawn(|| assert_eq!("{name}".format(&[1]), "{name}")).join().unwrap();
+    /// FormatOptions::set_process_default(None);
+    /// assert_eq!("{name}: {}".format(&[1]), "name: 1");
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn set_process_default(options: Option<FormatOptions>) {
+        *PROCESS_DEFAULT.write().unwrap_or_else(|e| e.into_inner()) = options;
+        PROCESS_DEFAULT_SET.store(options.is_some(), Ordering::Release);
+    }
+
+    /// Sets the options used by [`Arguments::new`](crate::Arguments::new) in the current thread,
+    /// overriding the [process default](FormatOptions::set_process_default).
+    /// `None` restores the process default.
+    ///
+    /// Unavailable in `no_std` environment.
+    #[cfg(feature = "std")]
+    pub fn set_thread_default(options: Option<FormatOptions>) {
+        THREAD_DEFAULT.with(|x| x.set(options));
+    }
+
+    /// The options used by [`Arguments::new`](crate::Arguments::new) in the current thread:
+    /// the thread default, the process default, or [`FormatOptions::new`], whichever is set first.
+    pub fn current_default() -> FormatOptions {
+        #[cfg(feature = "std")]
+        {
+            if let Some(options) = THREAD_DEFAULT.with(|x| x.get()) { return options; }
+            if PROCESS_DEFAULT_SET.load(Ordering::Acquire) {
+                if let Some(options) = *PROCESS_DEFAULT.read().unwrap_or_else(|e| e.into_inner()) { return options; }
+            }
+        }
+        FormatOptions::new()
+    }
+
+    /// Sets the indicator, e.g. `…` or `...`, appended to a string argument truncated by the precision.
+    /// By default nothing marks the truncation.
+    ///
+    /// If `counts_toward_precision` is `true`, the value is cut shorter to fit the indicator
+    /// into the precision, otherwise the indicator follows the full precision of the value.
+    /// In both cases the width applies to the value with the indicator.
+    ///
+    /// Unavailable in `no_std` environment.
+    /// # Examples:
+    /// ```rust
+    /// use dyn_fmt::{Arguments, FormatOptions};
+    /// let s = |options| Arguments::new("[{:<8.6}] [{:.6}]", &["truncated", "short"]).with_options(options).to_string();
+    /// assert_eq!(s(FormatOptions::new()), "[trunca  ] [short]");
+    /// assert_eq!(s(FormatOptions::new().truncation_indicator("…", true)), "[trunc…  ] [short]");
+    /// assert_eq!(s(FormatOptions::new().truncation_indicator("...", false)), "[trunca...] [short]");
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn truncation_indicator(mut self, indicator: &'static str, counts_toward_precision: bool) -> Self {
+        self.truncation = if indicator.is_empty() { None } else { Some((indicator, counts_toward_precision)) };
+        self
+    }
+
+    /// The truncation indicator and whether it counts toward the precision, if set.
+    ///
+    /// Unavailable in `no_std` environment.
+    #[cfg(feature = "std")]
+    pub fn truncation(&self) -> Option<(&'static str, bool)> { self.truncation }
+
+    /// Sets the unit the width and the precision of string arguments are measured in,
+    /// [`WidthMetric::Chars`] by default.
+    ///
+    /// With a metric other than [`Chars`](WidthMetric::Chars), the argument is rendered first and then padded
+    /// or cut by the rendered text, so the width applies even to arguments ignoring it in their [`Display`].
+    /// The precision cuts an argument if it is a string, i.e. if it is rendered as empty string with zero precision.
+    /// Zero-padded arguments, the decimal alignment, and presentations are not affected.
+    ///
+    /// Unavailable in `no_std` environment.
+    /// # Examples:
+    /// ```rust
+    /// use dyn_fmt::{Arguments, FormatOptions, WidthMetric};
+    /// let s = |metric| Arguments::new("[{:>6}] [{:.3}] [{:<6.1}]", &[&"äb" as &dyn std::fmt::Display, &"äbc", &1.25])
+    ///     .with_options(FormatOptions::new().width_metric(metric)).to_string();
+    /// assert_eq!(s(WidthMetric::Chars), "[    äb] [äbc] [1.2   ]");
+    /// assert_eq!(s(WidthMetric::Bytes), "[   äb] [äb] [1.2   ]");
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn width_metric(mut self, metric: WidthMetric) -> Self {
+        self.width_metric = metric;
+        self
+    }
+
+    /// The unit the width and the precision of string arguments are measured in.
+    ///
+    /// Unavailable in `no_std` environment.
+    #[cfg(feature = "std")]
+    pub fn metric(&self) -> WidthMetric { self.width_metric }
+
+    pub(crate) fn report(&self, event: FmtEvent) {
+        if let Some(Callback(on_event)) = self.on_event { on_event(event); }
+    }
+
+    pub(crate) fn fmt_arg<T: Display + ?Sized>(&self, arg: &T, spec: &Spec, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.isolate_bidi { return self.fmt_truncated(arg, spec, f); }
+        f.write_char('\u{2068}')?;
+        self.fmt_truncated(arg, spec, f)?;
+        f.write_char('\u{2069}')
+    }
+
+    /// Formats the argument, marking its truncation by the precision with the indicator, if any.
+    ///
+    /// The argument is considered truncated if the precision cut its text to exactly
+    /// `precision` characters, which is never the case for numbers.
+    fn fmt_truncated<T: Display + ?Sized>(&self, arg: &T, spec: &Spec, f: &mut fmt::Formatter) -> fmt::Result {
+        #[cfg(feature = "std")]
+        if
+            self.width_metric != WidthMetric::Chars && (spec.width().is_some() || spec.precision().is_some())
+            && spec.presentation().is_none() && spec.align() != Some(Align::Decimal) && !spec.zero()
+        {
+            return self.fmt_measured(arg, spec, f);
+        }
+        #[cfg(feature = "std")]
+        if let (Some((indicator, counts)), Some(precision), None) = (self.truncation, spec.precision(), spec.presentation()) {
+            let full = to_scratch(arg);
+            let cut = to_scratch(&format_args!("{:.*}", precision, arg));
+            if cut.len() < full.len() && full.starts_with(cut.as_str()) && cut.chars().count() == precision {
+                let keep = if counts { precision.saturating_sub(indicator.chars().count()) } else { precision };
+                let mut text = Scratch::from(String::with_capacity(cut.len() + indicator.len()));
+                text.extend(cut.chars().take(keep));
+                text.push_str(indicator);
+                let mut plain = *spec;
+                plain.set_precision(None);
+                return fmt_arg(text.as_str(), &plain, f);
+            }
+        }
+        fmt_arg(arg, spec, f)
+    }
+
+    /// Formats the argument measuring its width and precision with the [`WidthMetric`].
+    #[cfg(feature = "std")]
+    fn fmt_measured<T: Display + ?Sized>(&self, arg: &T, spec: &Spec, f: &mut fmt::Formatter) -> fmt::Result {
+        let metric = self.width_metric;
+        let mut plain = *spec;
+        plain.set_width(None);
+        plain.set_precision(None);
+        let full = to_scratch(&WithSpec { arg, spec: &plain });
+        let is_text = full.is_empty() || formatted_len(&format_args!("{:.0}", arg)) == 0;
+        let text = match spec.precision() {
+            None => full,
+            Some(precision) if is_text => {
+                let cut = metric.prefix(&full, precision);
+                match self.truncation {
+                    Some((indicator, counts)) if cut.len() < full.len() => {
+                        let keep = if counts { precision.saturating_sub(metric.len(indicator)) } else { precision };
+                        to_scratch(&format_args!("{}{}", metric.prefix(&full, keep), indicator))
+                    },
+                    _ => to_scratch(cut),
+                }
+            },
+            Some(precision) => {
+                plain.set_precision(Some(precision));
+                to_scratch(&WithSpec { arg, spec: &plain })
+            },
+        };
+        let padding = spec.width().unwrap_or(0).saturating_sub(metric.len(&text));
+        let (left, right) = match spec.align().unwrap_or(if is_text { Align::Left } else { Align::Right }) {
+            Align::Left => (0, padding),
+            Align::Center => (padding / 2, (padding + 1) / 2),
+            Align::Right | Align::Decimal => (padding, 0),
+        };
+        for _ in 0 .. left { f.write_char(spec.fill())?; }
+        f.write_str(&text)?;
+        for _ in 0 .. right { f.write_char(spec.fill())?; }
+        Ok(())
+    }
+}
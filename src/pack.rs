@@ -0,0 +1,155 @@
+use crate::{Catalog, Format, LoadError, ParseError};
+use crate::store::read_templates;
+use core::fmt::Write;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Templates embedded into the binary, validated at build time with
+/// [`compile_templates`] or [`template_pack_source`], and included with [`include_templates!`](crate::include_templates).
+///
+/// Every entry is a locale, `None` for messages which do not belong to any locale, a message key, and a template.
+/// The templates are parsed into a [`Catalog`] once, on the first [`catalog`](TemplatePack::catalog) call.
+///
+/// Unavailable in `no_std` environment.
+/// # Examples:
+/// ```rust
+/// use dyn_fmt::TemplatePack;
+/// static TEMPLATES: TemplatePack = TemplatePack::new(&[
+///     (None, "greeting", "Hello, {}!"),
+///     (Some("de"), "greeting", "Hallo, {}!"),
+/// ]);
+/// assert_eq!(TEMPLATES.catalog().format_localized("de", "greeting", &["Anna"]).unwrap(), "Hallo, Anna!");
+/// ```
+#[derive(Debug)]
+pub struct TemplatePack {
+    entries: &'static [(Option<&'static str>, &'static str, &'static str)],
+    catalog: OnceLock<Catalog>,
+}
+
+impl TemplatePack {
+    /// Creates a pack of the templates, which should be valid.
+    pub const fn new(entries: &'static [(Option<&'static str>, &'static str, &'static str)]) -> Self {
+        TemplatePack { entries, catalog: OnceLock::new() }
+    }
+
+    /// Returns the locales, message keys, and templates of the pack.
+    pub fn entries(&self) -> &'static [(Option<&'static str>, &'static str, &'static str)] { self.entries }
+
+    /// Returns the catalog of the templates, parsing them on the first call.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a template is malformed, which is impossible for a pack generated by
+    /// [`compile_templates`] or [`template_pack_source`].
+    pub fn catalog(&self) -> &Catalog {
+        self.catalog.get_or_init(|| {
+            let mut catalog = Catalog::new();
+            for &(locale, key, template) in self.entries {
+                let format = Format::parse(template)
+                    .unwrap_or_else(|e| panic!("invalid template '{}' in a template pack: {}", key, e));
+                match locale {
+                    None => catalog.insert(key, format),
+                    Some(locale) => catalog.insert_localized(locale, key, format),
+                };
+            }
+            catalog
+        })
+    }
+}
+
+/// Validates the templates and returns the Rust expression creating a [`TemplatePack`] of them,
+/// to be written into a file by a build script and included with [`include_templates!`](crate::include_templates).
+///
+/// Every template is a locale, `None` for messages which do not belong to any locale, a message key, and a template.
+/// Returns the key and the error of the first malformed template.
+///
+/// Unavailable in `no_std` environment.
+/// # Examples:
+/// ```rust
+/// use dyn_fmt::template_pack_source;
+/// let source = template_pack_source([(None, "greeting", "Hello, {}!")]).unwrap();
+/// assert_eq!(source, "::dyn_fmt::TemplatePack::new(&[\n    (None, \"greeting\", \"Hello, {}!\"),\n])\n");
+/// assert_eq!(template_pack_source([(Some("de"), "bad", "{:q}")]).unwrap_err().0, "bad");
+/// ```
+pub fn template_pack_source<'a>(
+    templates: impl IntoIterator<Item=(Option<&'a str>, &'a str, &'a str)>
+) -> Result<String, (String, ParseError)> {
+    let mut source = String::from("::dyn_fmt::TemplatePack::new(&[\n");
+    for (locale, key, template) in templates {
+        Format::parse(template).map_err(|e| (key.to_owned(), e))?;
+        writeln!(source, "    ({:?}, {:?}, {:?}),", locale, key, template).unwrap();
+    }
+    source.push_str("])\n");
+    Ok(source)
+}
+
+/// Reads and validates all templates from the directory laid out as for [`TemplateStore`](crate::TemplateStore),
+/// and writes the Rust expression creating a [`TemplatePack`] of them into the `out` file.
+/// Intended for build scripts, the file is usually written into `OUT_DIR`
+/// and included with [`include_templates!`](crate::include_templates).
+///
+/// Returns the errors of all files which cannot be read or parsed, in which case nothing is written.
+/// The entries are sorted by locale and key, so the output does not depend on the file system order.
+///
+/// Unavailable in `no_std` environment.
+/// # Examples:
+/// ```rust,no_run
+/// // build.rs
+/// let out = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("messages.rs");
+/// dyn_fmt::compile_templates("templates", out).unwrap();
+/// println!("cargo:rerun-if-changed=templates");
+/// ```
+pub fn compile_templates(dir: impl AsRef<Path>, out: impl AsRef<Path>) -> Result<(), Vec<LoadError>> {
+    let dir = dir.as_ref();
+    let mut files = read_templates(dir).map_err(|e| vec![LoadError::Io(dir.to_owned(), e)])?;
+    files.sort_by(|a, b| (&a.0, &a.1).cmp(&(&b.0, &b.1)));
+    let mut templates = Vec::new();
+    let mut errors = Vec::new();
+    for (locale, key, path, text) in files {
+        match text.map_err(|e| LoadError::Io(path.clone(), e)).and_then(|s| match Format::parse(s.as_str()) {
+            Ok(_) => Ok(s),
+            Err(e) => Err(LoadError::Parse(path, e)),
+        }) {
+            Ok(s) => templates.push((locale, key, s)),
+            Err(e) => errors.push(e),
+        }
+    }
+    if !errors.is_empty() { return Err(errors); }
+    let source = template_pack_source(templates.iter().map(|(l, k, s)| (l.as_deref(), k.as_str(), s.as_str())))
+        .expect("templates are validated");
+    let out = out.as_ref();
+    fs::write(out, source).map_err(|e| vec![LoadError::Io(out.to_owned(), e)])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{LoadError, TemplatePack, compile_templates};
+    use std::fs;
+
+    #[test]
+    fn compile_dir() {
+        let dir = std::env::temp_dir().join(format!("dyn-fmt-test-pack-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("de")).unwrap();
+        fs::write(dir.join("b.txt"), "b \"{}\"\n").unwrap();
+        fs::write(dir.join("a.txt"), "a{}").unwrap();
+        fs::write(dir.join("de").join("a.txt"), "de-a{}").unwrap();
+        let out = std::env::temp_dir().join(format!("dyn-fmt-test-pack-{}.rs", std::process::id()));
+        compile_templates(&dir, &out).unwrap();
+        assert_eq!(fs::read_to_string(&out).unwrap(), "::dyn_fmt::TemplatePack::new(&[\n    \
+            (None, \"a\", \"a{}\"),\n    \
+            (None, \"b\", \"b \\\"{}\\\"\\n\"),\n    \
+            (Some(\"de\"), \"a\", \"de-a{}\"),\n\
+        ])\n");
+        fs::write(dir.join("c.txt"), "{:q}").unwrap();
+        fs::remove_file(&out).unwrap();
+        let errors = compile_templates(&dir, &out).unwrap_err();
+        assert!(matches!(&errors[..], [LoadError::Parse(path, _)] if path.ends_with("c.txt")));
+        assert!(!out.exists());
+        fs::remove_dir_all(&dir).unwrap();
+        static PACK: TemplatePack = TemplatePack::new(&[(None, "a", "a{}"), (Some("de"), "a", "de-a{}")]);
+        assert_eq!(PACK.entries().len(), 2);
+        assert_eq!(PACK.catalog().format_localized("de", "a", &[1]).unwrap(), "de-a1");
+    }
+}
@@ -0,0 +1,930 @@
+use crate::spec::{Count, Spec};
+use core::fmt::{self, Display};
+use core::ops::Range;
+
+/// The kind of a [`ParseError`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum ParseErrorKind {
+    /// A `{#` comment is not closed with `#}`.
+    UnterminatedComment,
+    /// A `{% raw %}` block is not closed with `{% endraw %}`.
+    UnterminatedRaw,
+    /// A placeholder is not closed with `}`.
+    UnterminatedPlaceholder,
+    /// A placeholder format spec is not valid.
+    InvalidSpec,
+    /// A width or a precision exceeds 65535, the largest value supported by the standard formatting machinery,
+    /// e.g. `{:70000}`.
+    CountOverflow,
+    /// A placeholder argument name is not a valid identifier, e.g. `{1st}` or `{first-name}`.
+    InvalidName,
+    /// A placeholder argument index does not fit into `usize`.
+    InvalidIndex,
+    /// A placeholder starts with a character reserved for future syntax, see [`RESERVED_SIGILS`](crate::RESERVED_SIGILS).
+    ReservedSyntax,
+    /// A backslash does not start a valid escape sequence, see [`ParseOptions::allow_escapes`].
+    InvalidEscape,
+    /// A template uses both `{}` and `{0}` placeholders, see [`ParseOptions::forbid_mixed_numbering`].
+    MixedNumbering,
+    /// A placeholder refers to an argument explicitly, e.g. `{0}` or `{name}`,
+    /// which is supported by [`Format`](crate::Format) only.
+    /// Reported if [`FormatOptions::invalid_placeholders`](crate::FormatOptions::invalid_placeholders) is set.
+    ExplicitReference,
+    /// A brace neither starts a placeholder, a comment, or a raw block, nor is doubled, e.g. `}` in `a}b`,
+    /// see [`ParseOptions::forbid_unbalanced_braces`].
+    UnbalancedBrace,
+    /// The syntax is disabled with crate features, e.g. a comment without the `"syntax-comments"` feature.
+    DisabledSyntax(SyntaxFeature),
+}
+
+impl Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseErrorKind::UnterminatedComment => write!(f, "unterminated comment"),
+            ParseErrorKind::UnterminatedRaw => write!(f, "unterminated raw block"),
+            ParseErrorKind::UnterminatedPlaceholder => write!(f, "unterminated placeholder"),
+            ParseErrorKind::InvalidSpec => write!(f, "invalid format spec"),
+            ParseErrorKind::CountOverflow => write!(f, "width or precision is too large"),
+            ParseErrorKind::InvalidName => write!(f, "invalid argument name"),
+            ParseErrorKind::InvalidIndex => write!(f, "argument index is too large"),
+            ParseErrorKind::ReservedSyntax => write!(f, "reserved syntax"),
+            ParseErrorKind::InvalidEscape => write!(f, "invalid escape sequence"),
+            ParseErrorKind::MixedNumbering => write!(f, "automatic and manual argument numbering are mixed"),
+            ParseErrorKind::ExplicitReference => write!(f, "explicit argument reference is not supported"),
+            ParseErrorKind::UnbalancedBrace => write!(f, "unbalanced brace, use `{{{{` or `}}}}` to output a brace"),
+            ParseErrorKind::DisabledSyntax(feature) => write!(f, "{} syntax is disabled", feature.name()),
+        }
+    }
+}
+
+impl ParseErrorKind {
+    /// Whether the error is found in a placeholder rather than in a comment, a raw block, or an escape sequence.
+    pub(crate) fn in_placeholder(&self) -> bool {
+        !matches!(self,
+            ParseErrorKind::UnterminatedComment | ParseErrorKind::UnterminatedRaw
+                | ParseErrorKind::InvalidEscape | ParseErrorKind::UnbalancedBrace
+                | ParseErrorKind::DisabledSyntax(
+                    SyntaxFeature::Comment | SyntaxFeature::RawBlock | SyntaxFeature::EscapeSequence
+                )
+        )
+    }
+}
+
+/// An optional syntax element of a template, reported by [`Format::required_features`](crate::Format::required_features).
+///
+/// Literal text, `{{` and `}}`, and `{}` placeholders with a spec are the basic syntax,
+/// and are not reported.
+/// Comments, raw blocks, escape sequences, named arguments, and the width and the precision given by arguments
+/// can be disabled in `no_std` environment to reduce the code size, see the `"syntax"` crate feature,
+/// in which case they are rejected with [`ParseErrorKind::DisabledSyntax`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[non_exhaustive]
+pub enum SyntaxFeature {
+    /// A placeholder referring to an argument by index, e.g. `{0}`.
+    ExplicitIndex,
+    /// A placeholder referring to an argument by name, e.g. `{name}`.
+    Named,
+    /// The width or the precision given by an argument, e.g. `{:>{width}}` or `{:.{1}}`.
+    CountArgs,
+    /// A presentation type, e.g. `{:e}` or `{:base64}`.
+    Presentation,
+    /// The decimal alignment, e.g. `{:d8.2}`.
+    DecimalAlign,
+    /// A `{# ... #}` comment.
+    Comment,
+    /// A `{% raw %} ... {% endraw %}` block.
+    RawBlock,
+    /// An escape sequence, e.g. `\n`, see [`ParseOptions::allow_escapes`].
+    EscapeSequence,
+    /// Whitespace inside a placeholder, see [`ParseOptions::allow_whitespace`].
+    Whitespace,
+    /// Characters of a spec skipped because of [`ParseOptions::ignore_unknown_flags`].
+    UnknownFlags,
+    /// A single brace taken literally, see [`ParseOptions::forbid_unbalanced_braces`].
+    StrayBrace,
+}
+
+impl SyntaxFeature {
+    fn name(&self) -> &'static str {
+        match self {
+            SyntaxFeature::ExplicitIndex => "argument index",
+            SyntaxFeature::Named => "argument name",
+            SyntaxFeature::CountArgs => "width or precision argument",
+            SyntaxFeature::Presentation => "presentation type",
+            SyntaxFeature::DecimalAlign => "decimal alignment",
+            SyntaxFeature::Comment => "comment",
+            SyntaxFeature::RawBlock => "raw block",
+            SyntaxFeature::EscapeSequence => "escape sequence",
+            SyntaxFeature::Whitespace => "placeholder whitespace",
+            SyntaxFeature::UnknownFlags => "unknown flag",
+            SyntaxFeature::StrayBrace => "stray brace",
+        }
+    }
+}
+
+/// An error found while parsing a format string.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ParseError {
+    kind: ParseErrorKind,
+    span: Range<usize>,
+}
+
+impl ParseError {
+    pub(crate) fn new(kind: ParseErrorKind, span: Range<usize>) -> Self { ParseError { kind, span } }
+
+    /// The kind of the error.
+    pub fn kind(&self) -> ParseErrorKind { self.kind }
+
+    /// The byte range of the offending fragment within the format string.
+    pub fn span(&self) -> Range<usize> { self.span.clone() }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at {}..{}", self.kind, self.span.start, self.span.end)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseError { }
+
+#[cfg(feature = "std")]
+impl From<ParseError> for std::io::Error {
+    fn from(e: ParseError) -> Self { std::io::Error::new(std::io::ErrorKind::InvalidData, e) }
+}
+
+/// Options controlling how strictly a format string is parsed, see [`Format::parse_with`](crate::Format::parse_with).
+///
+/// The default options are strict: they are intended for new templates,
+/// while [`lenient`](ParseOptions::lenient) ones help with migrating legacy templates.
+/// # Examples:
+/// ```rust
+/// use dyn_fmt::ParseOptions;
+/// let options = ParseOptions::new().allow_whitespace(true);
+/// assert!(options.allows_whitespace());
+/// assert!(!options.ignores_unknown_flags());
+/// ```
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub struct ParseOptions {
+    whitespace: bool,
+    unknown_flags: bool,
+    escapes: bool,
+    mixed_numbering: bool,
+    unbalanced_braces: bool,
+}
+
+impl ParseOptions {
+    /// Creates strict options.
+    pub fn new() -> Self { Self::default() }
+
+    /// Creates options accepting everything the individual options allow to accept.
+    /// Escape sequences are not enabled, because they change the meaning of literal text.
+    pub fn lenient() -> Self {
+        ParseOptions { whitespace: true, unknown_flags: true, escapes: false, mixed_numbering: false, unbalanced_braces: false }
+    }
+
+    /// Allows whitespace around the argument reference and the spec, e.g. `{ 1 : >4 }`.
+    pub fn allow_whitespace(mut self, allow: bool) -> Self {
+        self.whitespace = allow;
+        self
+    }
+
+    /// Makes characters which are not a part of the spec syntax, e.g. `x` in `{:x>5}`,
+    /// to be ignored instead of making the spec invalid.
+    pub fn ignore_unknown_flags(mut self, ignore: bool) -> Self {
+        self.unknown_flags = ignore;
+        self
+    }
+
+    /// Enables C-style escape sequences in literal text: `\n`, `\r`, `\t`, `\0`, `\\`, `\'`, `\"`,
+    /// and `\u{...}` with up to six hex digits of a Unicode scalar value.
+    /// Any other backslash is an error. Raw blocks are not affected.
+    pub fn allow_escapes(mut self, allow: bool) -> Self {
+        self.escapes = allow;
+        self
+    }
+
+    /// Rejects templates which use both automatic (`{}`, `{:>5}`) and manual (`{0}`, `{1:>5}`) numbering,
+    /// as Python's `str.format` does. Named placeholders can be combined with either.
+    pub fn forbid_mixed_numbering(mut self, forbid: bool) -> Self {
+        self.mixed_numbering = forbid;
+        self
+    }
+
+    /// Rejects braces which neither start a placeholder, a comment, or a raw block, nor are doubled,
+    /// e.g. a trailing `{` or `}` in `a}b`, with [`ParseErrorKind::UnbalancedBrace`].
+    /// By default such a brace is dropped, and the character following it is taken literally.
+    /// # Examples:
+    /// ```rust
+    /// use dyn_fmt::{ParseErrorKind, ParseOptions, validate};
+    /// assert!(validate("a}{}", ParseOptions::new()).is_ok());
+    /// let err = validate("a}{}", ParseOptions::new().forbid_unbalanced_braces(true)).unwrap_err();
+    /// assert_eq!((err.kind(), err.span()), (ParseErrorKind::UnbalancedBrace, 1 .. 2));
+    /// ```
+    pub fn forbid_unbalanced_braces(mut self, forbid: bool) -> Self {
+        self.unbalanced_braces = forbid;
+        self
+    }
+
+    /// Whether whitespace around the argument reference and the spec is allowed.
+    pub fn allows_whitespace(&self) -> bool { self.whitespace }
+
+    /// Whether characters which are not a part of the spec syntax are ignored.
+    pub fn ignores_unknown_flags(&self) -> bool { self.unknown_flags }
+
+    /// Whether escape sequences in literal text are enabled.
+    pub fn allows_escapes(&self) -> bool { self.escapes }
+
+    /// Whether templates mixing automatic and manual numbering are rejected.
+    pub fn forbids_mixed_numbering(&self) -> bool { self.mixed_numbering }
+
+    /// Whether unbalanced braces are rejected.
+    pub fn forbids_unbalanced_braces(&self) -> bool { self.unbalanced_braces }
+}
+
+/// Tracks whether a template uses automatic or manual argument numbering.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Numbering {
+    automatic: Option<bool>,
+}
+
+impl Numbering {
+    /// Checks the placeholder argument, if the options forbid mixed numbering.
+    pub(crate) fn check(&mut self, arg: &ArgRef, options: &ParseOptions) -> Result<(), ParseErrorKind> {
+        if !options.mixed_numbering { return Ok(()); }
+        let automatic = match arg {
+            ArgRef::Next => true,
+            ArgRef::Index(_) => false,
+            ArgRef::Name(_) => return Ok(()),
+        };
+        if *self.automatic.get_or_insert(automatic) != automatic { return Err(ParseErrorKind::MixedNumbering); }
+        Ok(())
+    }
+}
+
+/// Checks a format string the same way [`Format::parse_with`](crate::Format::parse_with) does,
+/// returning the first error, if any.
+///
+/// Validation neither allocates nor needs a scratch buffer: templates of any length
+/// and specs of any length are checked in a fixed amount of stack space,
+/// so it is usable in `no_std` environments without an allocator.
+/// Note that in `no_std` environment [`Presentation`](crate::Presentation) types are not available,
+/// so specs like `{:e}` are invalid.
+/// # Examples:
+/// ```rust
+/// # #[cfg(feature = "syntax-named")] {
+/// use dyn_fmt::{ParseErrorKind, ParseOptions, validate};
+/// assert!(validate("{0} of {name:>8}", ParseOptions::new()).is_ok());
+/// let err = validate("{0} of {:q}", ParseOptions::new()).unwrap_err();
+/// assert_eq!((err.kind(), err.span()), (ParseErrorKind::InvalidSpec, 7 .. 11));
+/// # }
+/// ```
+pub fn validate(fmt: &str, options: ParseOptions) -> Result<(), ParseError> {
+    let mut numbering = Numbering::default();
+    for (span, token) in Tokens::explicit(fmt, options) {
+        match token {
+            Token::Arg(arg, _, counts) => {
+                for arg in [Some(arg), counts.width, counts.precision].iter().flatten() {
+                    numbering.check(arg, &options).map_err(|kind| ParseError::new(kind, span.clone()))?;
+                }
+            },
+            Token::Error(kind) => return Err(ParseError::new(kind, span)),
+            Token::Literal | Token::Escaped | Token::Comment | Token::StrayBrace => { },
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) enum EscapeError {
+    /// The input ends before the sequence is complete.
+    Incomplete,
+    /// The sequence of the specified length is not valid.
+    Invalid(usize),
+}
+
+/// Parses an escape sequence at the start of `s`, which starts with a backslash.
+/// Returns the escaped character and the sequence length.
+pub(crate) fn parse_escape(s: &str) -> Result<(char, usize), EscapeError> {
+    let bytes = s.as_bytes();
+    let c = match bytes.get(1) {
+        None => return Err(EscapeError::Incomplete),
+        Some(b'n') => '\n',
+        Some(b'r') => '\r',
+        Some(b't') => '\t',
+        Some(b'0') => '\0',
+        Some(b'\\') => '\\',
+        Some(b'\'') => '\'',
+        Some(b'"') => '"',
+        Some(b'u') => {
+            match bytes.get(2) {
+                None => return Err(EscapeError::Incomplete),
+                Some(b'{') => { },
+                Some(_) => return Err(EscapeError::Invalid(2)),
+            }
+            let digits = bytes[3 ..].iter().take(7).take_while(|b| b.is_ascii_hexdigit()).count();
+            let Some(&close) = bytes.get(3 + digits) else {
+                return Err(if digits <= 6 { EscapeError::Incomplete } else { EscapeError::Invalid(3 + digits) });
+            };
+            if close != b'}' || digits == 0 || digits > 6 { return Err(EscapeError::Invalid(3 + digits)); }
+            let c = u32::from_str_radix(&s[3 .. 3 + digits], 16).ok().and_then(char::from_u32);
+            return c.map(|c| (c, 4 + digits)).ok_or(EscapeError::Invalid(4 + digits));
+        },
+        Some(_) => return Err(EscapeError::Invalid(s[1 ..].chars().next().map_or(1, |c| 1 + c.len_utf8()))),
+    };
+    Ok((c, 2))
+}
+
+/// Replaces valid escape sequences in `s` with the characters they represent,
+/// leaving other backslashes as is.
+#[cfg(feature = "std")]
+pub(crate) fn unescape(s: &str, res: &mut String) {
+    let mut s = s;
+    while let Some(backslash) = s.find('\\') {
+        res.push_str(&s[.. backslash]);
+        s = &s[backslash ..];
+        match parse_escape(s) {
+            Ok((c, len)) => {
+                res.push(c);
+                s = &s[len ..];
+            },
+            Err(_) => {
+                res.push('\\');
+                s = &s[1 ..];
+            },
+        }
+    }
+    res.push_str(s);
+}
+
+/// Characters which cannot start a placeholder argument, reserved for future syntax.
+///
+/// A placeholder starting with one of them, e.g. `{@user}`, is an error,
+/// so templates written today do not change their meaning when the syntax is extended.
+pub const RESERVED_SIGILS: &[char] = &['!', '$', '@', '&', '?', '*', '=', '~'];
+
+/// The argument a placeholder refers to.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum ArgRef<'a> {
+    /// `{}`, the argument following the one used by the previous `{}` placeholder.
+    /// Placeholders referring to arguments explicitly do not affect it.
+    Next,
+    /// `{0}`, the argument with the specified index.
+    Index(usize),
+    /// `{name}`, the argument with the specified name.
+    ///
+    /// A valid name consists of letters, digits, and underscores, and does not start with a digit.
+    /// A single underscore is not a valid name.
+    Name(&'a str),
+}
+
+pub(crate) fn is_name(s: &str) -> bool {
+    let mut chars = s.chars();
+    let Some(first) = chars.next() else { return false; };
+    (first.is_alphabetic() || first == '_') && s != "_" && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+fn parse_arg_ref(s: &str) -> Result<ArgRef<'_>, ParseErrorKind> {
+    let Some(first) = s.chars().next() else { return Ok(ArgRef::Next); };
+    if RESERVED_SIGILS.contains(&first) { return Err(ParseErrorKind::ReservedSyntax); }
+    if first.is_ascii_digit() {
+        if !s.bytes().all(|b| b.is_ascii_digit()) { return Err(ParseErrorKind::InvalidName); }
+        return s.parse().map(ArgRef::Index).map_err(|_| ParseErrorKind::InvalidIndex);
+    }
+    if !is_name(s) { return Err(ParseErrorKind::InvalidName); }
+    if !cfg!(feature = "syntax-named") { return Err(ParseErrorKind::DisabledSyntax(SyntaxFeature::Named)); }
+    Ok(ArgRef::Name(s))
+}
+
+/// The width and the precision of a placeholder given by arguments, e.g. `{:{width}.{1}}`.
+/// They refer to arguments explicitly, never with [`ArgRef::Next`].
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub struct Counts<'a> {
+    pub(crate) width: Option<ArgRef<'a>>,
+    pub(crate) precision: Option<ArgRef<'a>>,
+}
+
+impl<'a> Counts<'a> {
+    /// The argument giving the width, if any.
+    pub fn width(&self) -> Option<ArgRef<'a>> { self.width }
+
+    /// The argument giving the precision, if any.
+    pub fn precision(&self) -> Option<ArgRef<'a>> { self.precision }
+
+    #[cfg_attr(not(feature = "std"), allow(dead_code))]
+    pub(crate) fn is_empty(&self) -> bool { self.width.is_none() && self.precision.is_none() }
+}
+
+fn parse_count_ref(count: Option<Count<'_>>) -> Result<Option<ArgRef<'_>>, ParseErrorKind> {
+    let Some(Count::Ref(s)) = count else { return Ok(None); };
+    if !cfg!(feature = "syntax-counts") { return Err(ParseErrorKind::DisabledSyntax(SyntaxFeature::CountArgs)); }
+    match parse_arg_ref(s)? {
+        ArgRef::Next => Err(ParseErrorKind::InvalidSpec),
+        arg => Ok(Some(arg)),
+    }
+}
+
+/// Parses the content of a placeholder between the braces,
+/// rejecting the width and the precision given by arguments.
+#[cfg(test)]
+fn parse_placeholder<'a>(s: &'a str, options: &ParseOptions) -> Result<(ArgRef<'a>, Spec), ParseErrorKind> {
+    let (arg, spec, counts) = parse_placeholder_counts(s, options)?;
+    if !counts.is_empty() { return Err(ParseErrorKind::InvalidSpec); }
+    Ok((arg, spec))
+}
+
+/// Parses the content of a placeholder between the braces.
+pub(crate) fn parse_placeholder_counts<'a>(
+    s: &'a str,
+    options: &ParseOptions
+) -> Result<(ArgRef<'a>, Spec, Counts<'a>), ParseErrorKind> {
+    let (arg, spec) = match s.find(':') {
+        Some(colon) => (&s[.. colon], Some(&s[colon + 1 ..])),
+        None => (s, None),
+    };
+    let arg = parse_arg_ref(if options.whitespace { arg.trim() } else { arg })?;
+    let (spec, width, precision) = match spec {
+        Some(spec) if options.whitespace => {
+            let spec = spec.trim_end();
+            Spec::parse_counts(spec, options.unknown_flags)
+                .or_else(|_| Spec::parse_counts(spec.trim_start(), options.unknown_flags))?
+        },
+        Some(spec) => Spec::parse_counts(spec, options.unknown_flags)?,
+        None => (Spec::default(), None, None),
+    };
+    Ok((arg, spec, Counts { width: parse_count_ref(width)?, precision: parse_count_ref(precision)? }))
+}
+
+/// Finds the brace closing a placeholder, skipping the braces around argument references in the spec.
+fn find_close(bytes: &[u8], from: usize) -> Option<usize> {
+    let close = find_byte(bytes, from, b'}')?;
+    let Some(colon) = bytes[from .. close].iter().position(|&b| b == b':') else { return Some(close); };
+    let spec = from + colon + 1;
+    let mut pos = spec;
+    loop {
+        match find_brace(bytes, pos)? {
+            close if bytes[close] == b'}' => return Some(close),
+            fill if fill == spec && matches!(bytes.get(fill + 1), Some(b'<' | b'^' | b'>')) => pos = fill + 1,
+            open => pos = find_byte(bytes, open + 1, b'}')? + 1,
+        }
+    }
+}
+
+/// The length in bytes of the first character of a non-empty string.
+pub(crate) fn char_len(s: &str) -> usize { s.chars().next().map_or(1, char::len_utf8) }
+
+/// Checks whether `s`, following an opening brace, starts a placeholder other than `{}`.
+/// Without explicit argument references (`options` is `None`) only `{:spec}` is recognized.
+pub(crate) fn starts_placeholder(s: &str, options: Option<&ParseOptions>) -> bool {
+    let Some(c) = s.chars().next() else { return false; };
+    if c == ':' { return true; }
+    let Some(options) = options else { return false; };
+    c.is_alphanumeric() || c == '_' || RESERVED_SIGILS.contains(&c) || (options.whitespace && c.is_whitespace())
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) enum Token<'a> {
+    Literal,
+    /// Literal text containing escape sequences.
+    Escaped,
+    Arg(ArgRef<'a>, Spec, Counts<'a>),
+    Comment,
+    StrayBrace,
+    Error(ParseErrorKind),
+}
+
+/// Splits a format string into tokens, each paired with its byte range.
+///
+/// The content of a `{% raw %}` block is returned as a single literal.
+/// A brace which does not start a placeholder, a comment, or a raw block is dropped,
+/// and the character following it is taken literally, so `{{` and `}}` produce single braces.
+/// Dropped braces other than such escapes are reported with [`Token::StrayBrace`],
+/// or with [`ParseErrorKind::UnbalancedBrace`] if [`ParseOptions`] forbid them.
+/// An erroneous fragment is reported with [`Token::Error`] and then treated the same lenient way.
+///
+/// Placeholders with explicit argument references (`{0}`, `{name}`) are recognized
+/// only by the tokenizer created with [`Tokens::explicit`], otherwise their braces are stray.
+/// Such tokenizer also respects [`ParseOptions`].
+pub(crate) struct Tokens<'a> {
+    fmt: &'a str,
+    pos: usize,
+    verbatim: bool,
+    explicit: Option<ParseOptions>,
+    whole_placeholders: bool,
+}
+
+impl<'a> Tokens<'a> {
+    pub(crate) fn new(fmt: &'a str) -> Self {
+        Tokens { fmt, pos: 0, verbatim: false, explicit: None, whole_placeholders: false }
+    }
+
+    pub(crate) fn explicit(fmt: &'a str, options: ParseOptions) -> Self {
+        Tokens { fmt, pos: 0, verbatim: false, explicit: Some(options), whole_placeholders: false }
+    }
+
+    /// Makes an erroneous placeholder a single [`Token::Error`] followed by the text after it,
+    /// instead of treating its braces leniently.
+    /// Placeholders with explicit argument references are reported
+    /// with [`ParseErrorKind::ExplicitReference`] by the tokenizer created with [`Tokens::new`].
+    pub(crate) fn whole_placeholders(mut self) -> Self {
+        self.whole_placeholders = true;
+        self
+    }
+
+    /// Makes the first character taken literally, as if it followed a dropped brace.
+    #[cfg_attr(not(feature = "std"), allow(dead_code))]
+    pub(crate) fn verbatim(mut self, verbatim: bool) -> Self {
+        self.verbatim = verbatim;
+        self
+    }
+
+    /// The position of the next token and whether its first character is taken literally,
+    /// which allows to resume tokenizing from there with [`verbatim`](Tokens::verbatim).
+    #[cfg_attr(not(feature = "std"), allow(dead_code))]
+    pub(crate) fn state(&self) -> (usize, bool) { (self.pos, self.verbatim) }
+}
+
+#[cfg(not(feature = "memchr"))]
+mod scan {
+    use core::mem::size_of;
+
+    const WORD: usize = size_of::<usize>();
+    const LO: usize = usize::MAX / 0xFF;
+    const HI: usize = LO << 7;
+
+    fn has_byte(word: usize, b: u8) -> bool {
+        let x = word ^ (LO * b as usize);
+        x.wrapping_sub(LO) & !x & HI != 0
+    }
+
+    fn find_by(bytes: &[u8], from: usize, word_pat: impl Fn(usize) -> bool, pat: impl Fn(u8) -> bool) -> Option<usize> {
+        let mut chunks = bytes[from ..].chunks_exact(WORD);
+        let mut pos = from;
+        for chunk in chunks.by_ref() {
+            if word_pat(usize::from_ne_bytes(chunk.try_into().unwrap())) { break; }
+            pos += WORD;
+        }
+        bytes[pos ..].iter().position(|&b| pat(b)).map(|n| pos + n)
+    }
+
+    pub(crate) fn find_byte(bytes: &[u8], from: usize, b: u8) -> Option<usize> {
+        find_by(bytes, from, |w| has_byte(w, b), |x| x == b)
+    }
+
+    pub(crate) fn find_brace(bytes: &[u8], from: usize) -> Option<usize> {
+        find_by(bytes, from, |w| has_byte(w, b'{') || has_byte(w, b'}'), |x| x == b'{' || x == b'}')
+    }
+
+    pub(crate) fn find_brace_or(bytes: &[u8], from: usize, b: u8) -> Option<usize> {
+        find_by(
+            bytes,
+            from,
+            |w| has_byte(w, b'{') || has_byte(w, b'}') || has_byte(w, b),
+            |x| x == b'{' || x == b'}' || x == b
+        )
+    }
+}
+
+#[cfg(feature = "memchr")]
+mod scan {
+    pub(crate) fn find_byte(bytes: &[u8], from: usize, b: u8) -> Option<usize> {
+        memchr::memchr(b, &bytes[from ..]).map(|n| from + n)
+    }
+
+    pub(crate) fn find_brace(bytes: &[u8], from: usize) -> Option<usize> {
+        memchr::memchr2(b'{', b'}', &bytes[from ..]).map(|n| from + n)
+    }
+
+    pub(crate) fn find_brace_or(bytes: &[u8], from: usize, b: u8) -> Option<usize> {
+        memchr::memchr3(b'{', b'}', b, &bytes[from ..]).map(|n| from + n)
+    }
+}
+
+pub(crate) use scan::{find_brace, find_brace_or, find_byte};
+
+/// Finds the first position starting with `first` and matching `pat`.
+pub(crate) fn find(bytes: &[u8], from: usize, first: u8, pat: impl Fn(&[u8]) -> bool) -> Option<usize> {
+    let mut from = from;
+    loop {
+        let pos = find_byte(bytes, from, first)?;
+        if pat(&bytes[pos ..]) { return Some(pos); }
+        from = pos + 1;
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) enum TagMatch {
+    Matched(usize),
+    Incomplete,
+    Mismatched,
+}
+
+/// Matches a `{% name %}` tag at the start of `bytes`.
+/// Returns its length, or [`TagMatch::Incomplete`] if `bytes` is a proper prefix of such a tag.
+pub(crate) fn match_tag(bytes: &[u8], name: &str) -> TagMatch {
+    fn skip_spaces(bytes: &[u8], pos: usize) -> Result<usize, TagMatch> {
+        bytes[pos ..].iter().position(|b| !b.is_ascii_whitespace()).map(|n| pos + n).ok_or(TagMatch::Incomplete)
+    }
+    fn expect(bytes: &[u8], pos: usize, s: &[u8]) -> Result<usize, TagMatch> {
+        let n = s.len().min(bytes.len() - pos);
+        if bytes[pos .. pos + n] != s[.. n] { return Err(TagMatch::Mismatched); }
+        if n < s.len() { return Err(TagMatch::Incomplete); }
+        Ok(pos + n)
+    }
+    let res = expect(bytes, 0, b"{%")
+        .and_then(|pos| skip_spaces(bytes, pos))
+        .and_then(|pos| expect(bytes, pos, name.as_bytes()))
+        .and_then(|pos| skip_spaces(bytes, pos))
+        .and_then(|pos| expect(bytes, pos, b"%}"));
+    match res {
+        Ok(len) => TagMatch::Matched(len),
+        Err(m) => m,
+    }
+}
+
+fn tag(bytes: &[u8], name: &str) -> Option<usize> {
+    match match_tag(bytes, name) {
+        TagMatch::Matched(len) => Some(len),
+        _ => None,
+    }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = (Range<usize>, Token<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (span, token) = self.next_token()?;
+        if token == Token::StrayBrace && self.explicit.is_some_and(|x| x.unbalanced_braces) {
+            return Some((span, Token::Error(ParseErrorKind::UnbalancedBrace)));
+        }
+        Some((span, token))
+    }
+}
+
+impl<'a> Tokens<'a> {
+    fn next_token(&mut self) -> Option<(Range<usize>, Token<'a>)> {
+        let bytes = self.fmt.as_bytes();
+        let default = ParseOptions::new();
+        let legacy = self.whole_placeholders.then_some(&default);
+        loop {
+            let start = self.pos;
+            if start == bytes.len() { return None; }
+            let escapes = self.explicit.is_some_and(|x| x.escapes);
+            if self.verbatim && escapes && bytes[start] == b'\\' {
+                self.verbatim = false;
+                self.pos = start + 1;
+                return Some((start .. self.pos, Token::Literal));
+            }
+            let mut end = if self.verbatim { start + char_len(&self.fmt[start ..]) } else { start };
+            self.verbatim = false;
+            let mut escaped = false;
+            loop {
+                let special = if escapes { find_brace_or(bytes, end, b'\\') } else { find_brace(bytes, end) };
+                end = special.unwrap_or(bytes.len());
+                if end == bytes.len() || bytes[end] != b'\\' { break; }
+                if !cfg!(feature = "syntax-escapes") {
+                    if end != start { break; }
+                    self.verbatim = true;
+                    return Some((start .. start + 1, Token::Error(ParseErrorKind::DisabledSyntax(SyntaxFeature::EscapeSequence))));
+                }
+                match parse_escape(&self.fmt[end ..]) {
+                    Ok((_, len)) => {
+                        escaped = true;
+                        end += len;
+                    },
+                    Err(_) if end != start => break,
+                    Err(e) => {
+                        let end = if let EscapeError::Invalid(len) = e { start + len } else { bytes.len() };
+                        self.verbatim = true;
+                        return Some((start .. end, Token::Error(ParseErrorKind::InvalidEscape)));
+                    },
+                }
+            }
+            if end != start {
+                self.pos = end;
+                return Some((start .. end, if escaped { Token::Escaped } else { Token::Literal }));
+            }
+            self.pos = start + 1;
+            self.verbatim = true;
+            if bytes[start] == b'}' {
+                if bytes.get(start + 1) == Some(&b'}') { continue; }
+                return Some((start .. self.pos, Token::StrayBrace));
+            }
+            match bytes.get(start + 1) {
+                None => return Some((start .. self.pos, Token::StrayBrace)),
+                Some(b'{') => { },
+                Some(b'}') => {
+                    self.verbatim = false;
+                    self.pos = start + 2;
+                    return Some((start .. self.pos, Token::Arg(ArgRef::Next, Spec::default(), Counts::default())));
+                },
+                Some(b'#') => {
+                    if !cfg!(feature = "syntax-comments") {
+                        return Some((start .. start + 2, Token::Error(ParseErrorKind::DisabledSyntax(SyntaxFeature::Comment))));
+                    }
+                    if let Some(close) = find(bytes, start + 2, b'#', |b| b.starts_with(b"#}")) {
+                        self.verbatim = false;
+                        self.pos = close + 2;
+                        return Some((start .. self.pos, Token::Comment));
+                    }
+                    return Some((start .. bytes.len(), Token::Error(ParseErrorKind::UnterminatedComment)));
+                },
+                Some(b'%') => {
+                    let Some(open) = tag(&bytes[start ..], "raw") else {
+                        return Some((start .. self.pos, Token::StrayBrace));
+                    };
+                    if !cfg!(feature = "syntax-raw") {
+                        return Some((start .. start + open, Token::Error(ParseErrorKind::DisabledSyntax(SyntaxFeature::RawBlock))));
+                    }
+                    let content = start + open;
+                    let Some(close) = find(bytes, content, b'{', |b| tag(b, "endraw").is_some()) else {
+                        return Some((start .. bytes.len(), Token::Error(ParseErrorKind::UnterminatedRaw)));
+                    };
+                    self.verbatim = false;
+                    self.pos = close + tag(&bytes[close ..], "endraw").unwrap();
+                    if close != content {
+                        return Some((content .. close, Token::Literal));
+                    }
+                },
+                Some(_) if starts_placeholder(&self.fmt[start + 1 ..], self.explicit.as_ref().or(legacy)) => {
+                    let close = if self.explicit.is_some() { find_close(bytes, start + 1) } else { find_byte(bytes, start + 1, b'}') };
+                    let Some(close) = close else {
+                        if self.whole_placeholders { self.pos = bytes.len(); }
+                        return Some((start .. bytes.len(), Token::Error(ParseErrorKind::UnterminatedPlaceholder)));
+                    };
+                    let options = self.explicit.unwrap_or_default();
+                    let placeholder = match parse_placeholder_counts(&self.fmt[start + 1 .. close], &options) {
+                        Ok((arg, ..)) if self.explicit.is_none() && arg != ArgRef::Next => Err(ParseErrorKind::ExplicitReference),
+                        placeholder => placeholder,
+                    };
+                    let (arg, spec, counts) = match placeholder {
+                        Ok(placeholder) => placeholder,
+                        Err(kind) => {
+                            if self.whole_placeholders {
+                                self.verbatim = false;
+                                self.pos = close + 1;
+                            }
+                            return Some((start .. close + 1, Token::Error(kind)));
+                        },
+                    };
+                    self.verbatim = false;
+                    self.pos = close + 1;
+                    return Some((start .. self.pos, Token::Arg(arg, spec, counts)));
+                },
+                Some(_) => return Some((start .. self.pos, Token::StrayBrace)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ArgRef, EscapeError, ParseErrorKind, ParseOptions, find, find_brace, find_byte, parse_escape};
+    use crate::Spec;
+
+    fn parse_placeholder(s: &str) -> Result<(ArgRef<'_>, Spec), ParseErrorKind> {
+        super::parse_placeholder(s, &ParseOptions::new())
+    }
+
+    #[test]
+    fn leniency() {
+        let whitespace = ParseOptions::new().allow_whitespace(true);
+        let spec = |s| Spec::parse(s).unwrap();
+        assert_eq!(super::parse_placeholder(" 1 : 4 ", &whitespace), Ok((ArgRef::Index(1), spec("4"))));
+        #[cfg(feature = "syntax-named")]
+        assert_eq!(super::parse_placeholder("x: >5", &whitespace), Ok((ArgRef::Name("x"), spec(" >5"))));
+        assert_eq!(super::parse_placeholder(" : +", &whitespace), Ok((ArgRef::Next, spec("+"))));
+        assert_eq!(super::parse_placeholder("1 ", &ParseOptions::new()), Err(ParseErrorKind::InvalidName));
+        let unknown = ParseOptions::new().ignore_unknown_flags(true);
+        assert_eq!(super::parse_placeholder(":>5x", &unknown), Ok((ArgRef::Next, spec(">5"))));
+        assert_eq!(super::parse_placeholder(":q+w#e0r5t.y2u", &unknown), Ok((ArgRef::Next, spec("+#05.2"))));
+        assert_eq!(super::parse_placeholder(":5+.", &unknown), Ok((ArgRef::Next, spec("5"))));
+        assert_eq!(super::parse_placeholder(":99999999999999999999999", &unknown), Err(ParseErrorKind::CountOverflow));
+        assert_eq!(super::parse_placeholder(":.99999999999999999999999", &ParseOptions::new()), Err(ParseErrorKind::CountOverflow));
+        #[cfg(feature = "syntax-named")]
+        assert_eq!(super::parse_placeholder(" a : >5 z ", &ParseOptions::lenient()), Ok((ArgRef::Name("a"), spec(" >5"))));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn non_ascii() {
+        use super::{Token, Tokens};
+        fn spans<'a>(fmt: &'a str, tokens: Tokens<'a>) -> Vec<(&'a str, Token<'a>)> {
+            tokens.map(|(span, token)| (&fmt[span], token)).collect()
+        }
+        let fmt = "я{ж}€{:😀^5}ы}}ё{имя:я>3}}😀{";
+        for (span, _) in spans(fmt, Tokens::new(fmt)).into_iter().chain(spans(fmt, Tokens::explicit(fmt, ParseOptions::lenient()))) {
+            assert!(!span.is_empty());
+        }
+        let tokens = spans(fmt, Tokens::explicit(fmt, ParseOptions::new()));
+        assert_eq!(tokens[1], ("{ж}", Token::Arg(ArgRef::Name("ж"), Spec::default(), Default::default())));
+        assert_eq!(tokens[3], ("{:😀^5}", Token::Arg(ArgRef::Next, Spec::parse("😀^5").unwrap(), Default::default())));
+        assert_eq!(tokens[6], ("{имя:я>3}", Token::Arg(ArgRef::Name("имя"), Spec::parse("я>3").unwrap(), Default::default())));
+        let literals = spans(fmt, Tokens::new(fmt)).into_iter().filter(|(_, t)| *t == Token::Literal).map(|(s, _)| s).collect::<Vec<_>>();
+        assert_eq!(literals, ["я", "ж", "€", "ы", "}ё", "имя:я>3", "}😀"]);
+        assert_eq!(parse_placeholder("я:>3"), Ok((ArgRef::Name("я"), Spec::parse(">3").unwrap())));
+        assert_eq!(parse_placeholder(":>я"), Err(ParseErrorKind::InvalidSpec));
+        assert_eq!(parse_placeholder("😀"), Err(ParseErrorKind::InvalidName));
+    }
+
+    #[test]
+    fn validate() {
+        let strict = ParseOptions::new();
+        assert_eq!(super::validate("", strict), Ok(()));
+        #[cfg(feature = "syntax")]
+        assert_eq!(super::validate("{{{}}} {#c#} {% raw %}{{% endraw %} {a:*^+#08.3}", strict), Ok(()));
+        #[cfg(feature = "syntax")]
+        assert_eq!(super::validate("{:{w}.{1}} {}", strict), Ok(()));
+        let err = |fmt, options| super::validate(fmt, options).map_err(|e| (e.kind(), e.span()));
+        assert_eq!(err("ab {x", strict), Err((ParseErrorKind::UnterminatedPlaceholder, 3 .. 5)));
+        assert_eq!(err("{:99999999999999999999}", strict), Err((ParseErrorKind::CountOverflow, 0 .. 23)));
+        assert_eq!(err("{:65536}", strict), Err((ParseErrorKind::CountOverflow, 0 .. 8)));
+        assert_eq!(err("a{:.65536}", strict), Err((ParseErrorKind::CountOverflow, 1 .. 10)));
+        assert_eq!(err("{:65535.65535}", strict), Ok(()));
+        assert_eq!(err("{} {0}", strict.forbid_mixed_numbering(true)), Err((ParseErrorKind::MixedNumbering, 3 .. 6)));
+        #[cfg(feature = "syntax-escapes")]
+        assert_eq!(err(r"\q {}", strict.allow_escapes(true)), Err((ParseErrorKind::InvalidEscape, 0 .. 2)));
+        assert_eq!(err("{ 0 }", ParseOptions::lenient()), Ok(()));
+    }
+
+    #[test]
+    fn escape_sequences() {
+        assert_eq!(parse_escape(r"\n"), Ok(('\n', 2)));
+        assert_eq!(parse_escape(r"\\n"), Ok(('\\', 2)));
+        assert_eq!(parse_escape(r"\u{41}x"), Ok(('A', 6)));
+        assert_eq!(parse_escape(r"\u{10FFFF}"), Ok(('\u{10FFFF}', 10)));
+        assert_eq!(parse_escape(r"\"), Err(EscapeError::Incomplete));
+        assert_eq!(parse_escape(r"\u{12"), Err(EscapeError::Incomplete));
+        assert_eq!(parse_escape(r"\x41"), Err(EscapeError::Invalid(2)));
+        assert_eq!(parse_escape(r"\u41"), Err(EscapeError::Invalid(2)));
+        assert_eq!(parse_escape(r"\u{110000}"), Err(EscapeError::Invalid(10)));
+        assert_eq!(parse_escape(r"\u{12x}"), Err(EscapeError::Invalid(5)));
+    }
+
+    #[test]
+    fn placeholders() {
+        assert_eq!(parse_placeholder(""), Ok((ArgRef::Next, Spec::default())));
+        assert_eq!(parse_placeholder(":"), Ok((ArgRef::Next, Spec::default())));
+        assert_eq!(parse_placeholder("12"), Ok((ArgRef::Index(12), Spec::default())));
+        assert_eq!(parse_placeholder("007:>3"), Ok((ArgRef::Index(7), Spec::parse(">3").unwrap())));
+        #[cfg(feature = "syntax-named")]
+        assert_eq!(parse_placeholder("имя_1"), Ok((ArgRef::Name("имя_1"), Spec::default())));
+        #[cfg(feature = "syntax-named")]
+        assert_eq!(parse_placeholder("_x::^5"), Ok((ArgRef::Name("_x"), Spec::parse(":^5").unwrap())));
+        assert_eq!(parse_placeholder("1st"), Err(ParseErrorKind::InvalidName));
+        assert_eq!(parse_placeholder("first-name"), Err(ParseErrorKind::InvalidName));
+        assert_eq!(parse_placeholder("_"), Err(ParseErrorKind::InvalidName));
+        assert_eq!(parse_placeholder(" x"), Err(ParseErrorKind::InvalidName));
+        assert_eq!(parse_placeholder("99999999999999999999999"), Err(ParseErrorKind::InvalidIndex));
+        assert_eq!(parse_placeholder("@user"), Err(ParseErrorKind::ReservedSyntax));
+        #[cfg(feature = "syntax-named")]
+        assert_eq!(parse_placeholder("x:q"), Err(ParseErrorKind::InvalidSpec));
+    }
+
+    #[cfg(not(feature = "syntax"))]
+    #[test]
+    fn disabled_syntax() {
+        use crate::SyntaxFeature;
+        let err = |fmt, options| super::validate(fmt, options).map_err(|e| (e.kind(), e.span()));
+        let strict = ParseOptions::new();
+        assert_eq!(err("{0:>5.2} {{}}", strict), Ok(()));
+        #[cfg(not(feature = "syntax-named"))]
+        assert_eq!(err("{} {name}", strict), Err((ParseErrorKind::DisabledSyntax(SyntaxFeature::Named), 3 .. 9)));
+        #[cfg(not(feature = "syntax-counts"))]
+        assert_eq!(err("{:.{1}}", strict), Err((ParseErrorKind::DisabledSyntax(SyntaxFeature::CountArgs), 0 .. 7)));
+        #[cfg(not(feature = "syntax-comments"))]
+        assert_eq!(err("a{# c #}", strict), Err((ParseErrorKind::DisabledSyntax(SyntaxFeature::Comment), 1 .. 3)));
+        #[cfg(not(feature = "syntax-raw"))]
+        assert_eq!(err("{% raw %}{{% endraw %}", strict), Err((ParseErrorKind::DisabledSyntax(SyntaxFeature::RawBlock), 0 .. 9)));
+        #[cfg(not(feature = "syntax-escapes"))]
+        assert_eq!(err(r"a\n", strict.allow_escapes(true)), Err((ParseErrorKind::DisabledSyntax(SyntaxFeature::EscapeSequence), 1 .. 2)));
+        #[cfg(not(feature = "syntax-comments"))]
+        {
+            use crate::SliceWriter;
+            use core::fmt::Write;
+            let mut buf = [0u8; 16];
+            let mut writer = SliceWriter::new(&mut buf);
+            write!(writer, "{}", crate::Arguments::new("{# c #} {}", &[1])).unwrap();
+            assert_eq!(writer.as_str(), "# c # 1");
+        }
+    }
+
+    #[test]
+    fn scan() {
+        let mut bytes = [b'a'; 40];
+        for i in 0 .. bytes.len() {
+            bytes[i] = b'}';
+            for from in 0 ..= bytes.len() {
+                let expected = if from <= i { Some(i) } else { None };
+                assert_eq!(find_brace(&bytes, from), expected);
+                assert_eq!(find_byte(&bytes, from, b'}'), expected);
+                assert_eq!(find_byte(&bytes, from, b'{'), None);
+            }
+            bytes[i] = b'a';
+        }
+        assert_eq!(find_brace("яяяяяяяяя{".as_bytes(), 0), Some(18));
+        assert_eq!(find(b"#a##}#}", 0, b'#', |b| b.starts_with(b"#}")), Some(3));
+        assert_eq!(find(b"#a##", 0, b'#', |b| b.starts_with(b"#}")), None);
+    }
+}
@@ -0,0 +1,482 @@
+use crate::{Csv, Encoding, Separator, Spec, SqlQuote};
+use crate::quote::quote;
+use crate::spec::{MAX_COUNT, fmt_arg};
+#[cfg(feature = "locale")]
+use crate::Currency;
+use core::fmt::{self, Display};
+
+/// The presentation type of a [`Spec`], the part after the precision, e.g. `E` in `{:.2E}`.
+///
+/// Arguments are only required to implement [`Display`], so a presentation is applied
+/// to the text the argument displays. If the text is not suitable for the presentation,
+/// e.g. `NaN` for the exponent notation, the argument is formatted as if there were no presentation.
+///
+/// Unavailable in `no_std` environment.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum Presentation {
+    /// `e`, the scientific notation with a lowercase exponent, e.g. `1.5e3`,
+    /// the same as the [`LowerExp`](fmt::LowerExp) formatting trait.
+    LowerExp,
+    /// `E`, the scientific notation with an uppercase exponent, e.g. `1.5E3`,
+    /// the same as the [`UpperExp`](fmt::UpperExp) formatting trait.
+    UpperExp,
+    /// `fixed(n)`, an integer scaled down by `10ⁿ`, e.g. `1234` with `fixed(2)` is `12.34`.
+    /// The decimal point is placed exactly, without converting the number to a float,
+    /// which suits amounts stored in minor units, such as cents.
+    /// The precision, if any, sets the number of fraction digits, rounding half to even.
+    Fixed(u8),
+    /// `ordinal`, an integer with the English ordinal suffix, e.g. `1st`, `2nd`, `3rd`, `11th`, or `22nd`.
+    Ordinal,
+    /// `join("sep")`, the elements of a [`Seq`](crate::Seq) argument separated by `sep`, e.g. `{:join(" | ")}`.
+    /// The spec is applied to the whole list.
+    Join(Separator),
+    /// `map` or `map("kv", "sep")`, the entries of a [`Pairs`](crate::Pairs) argument
+    /// as keys and values separated by `kv` (`=` by default), separated by `sep` (`, ` by default),
+    /// e.g. `{:map(": ", "; ")}`. The spec is applied to the whole list.
+    Map(Separator, Separator),
+    /// `hex`, `HEX`, `base64`, `base64(nopad)`, `base64(url)`, `base64(url,nopad)`, or `hexdump`,
+    /// a [`Bytes`](crate::Bytes) argument in the [`Encoding`], e.g. `{:base64}`.
+    /// The spec is applied to the encoded text.
+    Encode(Encoding),
+    /// `url`, the text percent-encoded as a URL component, e.g. a query parameter value:
+    /// all bytes except ASCII letters, digits, and `-._~` are encoded, e.g. `a b&c` is `a%20b%26c`.
+    UrlComponent,
+    /// `url(path)`, the text percent-encoded as a URL path, which additionally keeps `/`
+    /// and the other characters allowed in path segments, `!$&'()*+,;=:@`, e.g. `a b/c?` is `a%20b/c%3F`.
+    UrlPath,
+    /// `csv`, `tsv`, `csv(safe)`, or `tsv(safe)`, the text quoted as a CSV or TSV field
+    /// if it contains special characters, e.g. `a,b` with `csv` is `"a,b"`, see [`Csv`].
+    Csv(Csv),
+    /// `sql_literal`, `sql_ident`, or `sql_backtick`, the text quoted as an SQL string literal
+    /// or identifier, e.g. `O'Brien` with `sql_literal` is `'O''Brien'`, see [`SqlQuote`].
+    SqlQuote(SqlQuote),
+    /// `p`, accepted for compatibility with [`format!`](std::format) templates.
+    /// It does not change the formatting, a [`Ptr`](crate::Ptr) argument is displayed as an address
+    /// with or without it.
+    Pointer,
+    /// `cur(CODE)` or `cur(CODE,locale)`, a currency amount, e.g. `1234.5` with `cur(USD)` is `$1,234.50`.
+    /// The precision is ignored, the number of fraction digits is defined by the currency.
+    ///
+    /// Available with the `"locale"` feature.
+    #[cfg(feature = "locale")]
+    Currency(Currency),
+    /// `words`, an integer spelled out in English, e.g. `-42` is `minus forty-two`.
+    /// Integers up to 39 digits are supported.
+    ///
+    /// The spelling is always English, it does not depend on the `"locale"` feature.
+    Words,
+}
+
+impl Presentation {
+    pub(crate) fn parse(s: &str) -> Option<Presentation> {
+        match s {
+            "e" => Some(Presentation::LowerExp),
+            "E" => Some(Presentation::UpperExp),
+            "ordinal" => Some(Presentation::Ordinal),
+            "hex" | "HEX" | "base64" => Encoding::parse(s).map(Presentation::Encode),
+            _ if s.starts_with("base64(") || s.starts_with("hexdump") => Encoding::parse(s).map(Presentation::Encode),
+            "p" => Some(Presentation::Pointer),
+            _ if s.starts_with("csv") || s.starts_with("tsv") => Csv::parse(s).map(Presentation::Csv),
+            _ if s.starts_with("sql_") => SqlQuote::parse(s).map(Presentation::SqlQuote),
+            "url" => Some(Presentation::UrlComponent),
+            "url(path)" => Some(Presentation::UrlPath),
+            "words" => Some(Presentation::Words),
+            #[cfg(feature = "locale")]
+            _ if s.starts_with("cur(") => Currency::parse(s).map(Presentation::Currency),
+            "map" => Some(Presentation::Map(Separator::EQUALS, Separator::COMMA)),
+            _ if s.starts_with("map(") => Separator::parse_pair(s.strip_prefix("map(")?.strip_suffix(')')?)
+                .map(|(key, entry)| Presentation::Map(key, entry)),
+            _ if s.starts_with("join(") => Separator::parse(s.strip_prefix("join(")?.strip_suffix(')')?).map(Presentation::Join),
+            _ => {
+                let scale = s.strip_prefix("fixed(")?.strip_suffix(')')?;
+                if !scale.bytes().all(|b| b.is_ascii_digit()) { return None; }
+                scale.parse().ok().map(Presentation::Fixed)
+            },
+        }
+    }
+
+    /// Formats the argument text according to the presentation,
+    /// returning `None` if the text is not suitable for it.
+    fn apply<T: Display + ?Sized>(&self, arg: &T, spec: &Spec) -> Option<Presented> {
+        match self {
+            Presentation::LowerExp => exp(arg, spec.precision(), 'e').map(Presented::Number),
+            Presentation::UpperExp => exp(arg, spec.precision(), 'E').map(Presented::Number),
+            &Presentation::Fixed(scale) => fixed(arg, scale.into(), spec.precision()).map(Presented::Number),
+            Presentation::Ordinal => ordinal(arg).map(Presented::Number),
+            Presentation::Join(separator) => separator.join(arg).map(Presented::Text),
+            &Presentation::Map(key, entry) => Separator::map(key, entry, arg).map(Presented::Text),
+            Presentation::Encode(encoding) => encoding.encode(arg).map(Presented::Text),
+            Presentation::Csv(csv) => Some(Presented::Text(quote(csv, arg))),
+            Presentation::SqlQuote(sql) => Some(Presented::Text(quote(sql, arg))),
+            Presentation::Pointer => None,
+            Presentation::UrlComponent => Some(Presented::Text(percent_encode(arg, false))),
+            Presentation::UrlPath => Some(Presented::Text(percent_encode(arg, true))),
+            #[cfg(feature = "locale")]
+            Presentation::Currency(currency) => currency_amount(arg, currency).map(Presented::Number),
+            Presentation::Words => words(arg).map(Presented::Text),
+        }
+    }
+
+    pub(crate) fn fmt_arg<T: Display + ?Sized>(&self, arg: &T, spec: &Spec, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut plain = *spec;
+        plain.set_presentation(None);
+        match self.apply(arg, spec) {
+            Some(presented) => {
+                plain.set_precision(None);
+                fmt_arg(&presented, &plain, f)
+            },
+            None => fmt_arg(arg, &plain, f),
+        }
+    }
+}
+
+/// Chooses a presentation with typical parameters, covering every kind.
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Presentation {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        const SPECS: &[&str] = &[
+            "e", "E", "ordinal", "p", "hex", "HEX", "base64", "base64(nopad)", "base64(url,nopad)", "hexdump", "hexdump(4)",
+            "csv", "tsv", "csv(safe)", "sql_literal", "sql_ident", "sql_backtick", "url", "url(path)",
+            "map", "map(\": \", \"; \")", "join(\" | \")",
+            "words",
+            #[cfg(feature = "locale")]
+            "cur(EUR,de)",
+        ];
+        if u.ratio(1, 8)? { return Ok(Presentation::Fixed(u.arbitrary()?)); }
+        Ok(Presentation::parse(u.choose(SPECS)?).expect("valid presentation"))
+    }
+}
+
+impl Display for Presentation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Presentation::LowerExp => write!(f, "e"),
+            Presentation::UpperExp => write!(f, "E"),
+            Presentation::Fixed(scale) => write!(f, "fixed({})", scale),
+            Presentation::Ordinal => write!(f, "ordinal"),
+            Presentation::Join(separator) => write!(f, "join({})", separator),
+            &Presentation::Map(Separator::EQUALS, Separator::COMMA) => write!(f, "map"),
+            Presentation::Map(key, entry) => write!(f, "map({}, {})", key, entry),
+            Presentation::Encode(encoding) => encoding.fmt(f),
+            Presentation::Csv(csv) => csv.fmt(f),
+            Presentation::SqlQuote(sql) => sql.fmt(f),
+            Presentation::Pointer => write!(f, "p"),
+            Presentation::UrlComponent => write!(f, "url"),
+            Presentation::UrlPath => write!(f, "url(path)"),
+            #[cfg(feature = "locale")]
+            Presentation::Currency(currency) => currency.fmt(f),
+            Presentation::Words => write!(f, "words"),
+        }
+    }
+}
+
+/// The result of a presentation.
+enum Presented {
+    Number(Number),
+    /// A text, padded as a string.
+    Text(String),
+}
+
+impl Display for Presented {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Presented::Number(number) => number.fmt(f),
+            Presented::Text(text) => f.pad(text),
+        }
+    }
+}
+
+/// A presented number, padded as an integer: the sign is controlled by the spec flags,
+/// and the `0` flag pads it with zeros after the sign.
+struct Number {
+    non_negative: bool,
+    digits: String,
+}
+
+impl Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad_integral(self.non_negative, "", &self.digits)
+    }
+}
+
+/// Splits a decimal number, e.g. `-12.50`, into the sign, the integer, and the fraction digits.
+fn decimal(s: &str) -> Option<(bool, &str, &str)> {
+    let (non_negative, s) = match s.strip_prefix('-') {
+        Some(s) => (false, s),
+        None => (true, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let (int, frac) = s.split_once('.').unwrap_or((s, ""));
+    let digits = |x: &str| x.bytes().all(|b| b.is_ascii_digit());
+    if int.is_empty() || !digits(int) || !digits(frac) { return None; }
+    Some((non_negative, int, frac))
+}
+
+/// Rounds the digits to `len` digits half to even, returning `true` if the result overflows to `10...0`.
+fn round(digits: &mut Vec<u8>, len: usize) -> bool {
+    if digits.len() <= len {
+        digits.resize(len, b'0');
+        return false;
+    }
+    let next = digits[len];
+    let sticky = digits[len + 1 ..].iter().any(|&b| b != b'0');
+    digits.truncate(len);
+    let odd = digits.last().is_some_and(|&b| (b - b'0') % 2 == 1);
+    if next < b'5' || (next == b'5' && !sticky && !odd) { return false; }
+    for b in digits.iter_mut().rev() {
+        if *b == b'9' {
+            *b = b'0';
+        } else {
+            *b += 1;
+            return false;
+        }
+    }
+    if let Some(first) = digits.first_mut() { *first = b'1'; }
+    true
+}
+
+/// The number of fraction digits of the smallest positive `f64`, the subnormal `2⁻¹⁰⁷⁴`,
+/// which is enough to print any `f64` exactly.
+const EXACT_FRACTION_DIGITS: usize = 1074;
+
+fn exp<T: Display + ?Sized>(arg: &T, precision: Option<usize>, e: char) -> Option<Number> {
+    // The shortest representation is used if there is no precision, the exact one otherwise,
+    // as the `LowerExp` and `UpperExp` implementations of floats do.
+    let text = match precision {
+        None => arg.to_string(),
+        Some(_) => format!("{:.*}", EXACT_FRACTION_DIGITS, arg),
+    };
+    let (non_negative, int, frac) = decimal(&text)?;
+    let all = || int.bytes().chain(frac.bytes());
+    let (mut digits, mut exp) = match all().position(|b| b != b'0') {
+        None => (vec![b'0'], 0),
+        Some(first) => (all().skip(first).collect(), int.len() as isize - 1 - first as isize),
+    };
+    match precision {
+        None => {
+            let zeros = digits.iter().rev().take_while(|&&b| b == b'0').count();
+            digits.truncate((digits.len() - zeros).max(1));
+        },
+        Some(precision) => if round(&mut digits, precision.min(MAX_COUNT).saturating_add(1)) { exp += 1; },
+    }
+    let mut res = String::with_capacity(digits.len() + 8);
+    res.push(char::from(digits[0]));
+    if digits.len() > 1 {
+        res.push('.');
+        res.extend(digits[1 ..].iter().map(|&b| char::from(b)));
+    }
+    res.push(e);
+    res.push_str(&exp.to_string());
+    Some(Number { non_negative, digits: res })
+}
+
+fn fixed<T: Display + ?Sized>(arg: &T, scale: usize, precision: Option<usize>) -> Option<Number> {
+    let text = arg.to_string();
+    let (non_negative, int, "") = decimal(&text)? else { return None; };
+    let mut digits = Vec::with_capacity(int.len().max(scale + 1) + 1);
+    digits.resize((scale + 1).saturating_sub(int.len()), b'0');
+    digits.extend(int.bytes());
+    let mut int_len = digits.len() - scale;
+    if let Some(precision) = precision {
+        if round(&mut digits, int_len.saturating_add(precision.min(MAX_COUNT))) {
+            digits.push(b'0');
+            int_len += 1;
+        }
+    }
+    let mut res = String::with_capacity(digits.len() + 1);
+    res.extend(digits[.. int_len].iter().map(|&b| char::from(b)));
+    if digits.len() > int_len {
+        res.push('.');
+        res.extend(digits[int_len ..].iter().map(|&b| char::from(b)));
+    }
+    Some(Number { non_negative, digits: res })
+}
+
+fn ordinal<T: Display + ?Sized>(arg: &T) -> Option<Number> {
+    let text = arg.to_string();
+    let (non_negative, int, "") = decimal(&text)? else { return None; };
+    let tens = int.len().checked_sub(2).map_or(b'0', |i| int.as_bytes()[i]);
+    let suffix = match (tens, int.as_bytes()[int.len() - 1]) {
+        (b'1', _) => "th",
+        (_, b'1') => "st",
+        (_, b'2') => "nd",
+        (_, b'3') => "rd",
+        _ => "th",
+    };
+    Some(Number { non_negative, digits: format!("{}{}", int, suffix) })
+}
+
+fn percent_encode<T: Display + ?Sized>(arg: &T, path: bool) -> String {
+    let text = arg.to_string();
+    let mut res = String::with_capacity(text.len());
+    for b in text.bytes() {
+        let keep = b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~')
+            || path && matches!(b, b'/' | b'!' | b'$' | b'&' | b'\'' | b'(' | b')' | b'*' | b'+' | b',' | b';' | b'=' | b':' | b'@');
+        if keep {
+            res.push(char::from(b));
+        } else {
+            res.push('%');
+            res.push(char::from(b"0123456789ABCDEF"[usize::from(b >> 4)]));
+            res.push(char::from(b"0123456789ABCDEF"[usize::from(b & 0xF)]));
+        }
+    }
+    res
+}
+
+#[cfg(feature = "locale")]
+fn currency_amount<T: Display + ?Sized>(arg: &T, currency: &Currency) -> Option<Number> {
+    let text = arg.to_string();
+    let (non_negative, int, frac) = decimal(&text)?;
+    let mut digits: Vec<u8> = int.bytes().chain(frac.bytes()).collect();
+    let mut int_len = int.len();
+    if round(&mut digits, int_len + currency.minor_digits()) {
+        digits.push(b'0');
+        int_len += 1;
+    }
+    let leading = digits[.. int_len - 1].iter().take_while(|&&b| b == b'0').count();
+    let (int, frac) = digits[leading ..].split_at(int_len - leading);
+    Some(Number { non_negative, digits: currency.present(int, frac) })
+}
+
+fn words<T: Display + ?Sized>(arg: &T) -> Option<String> {
+    const ONES: [&str; 20] = [
+        "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten",
+        "eleven", "twelve", "thirteen", "fourteen", "fifteen", "sixteen", "seventeen", "eighteen", "nineteen",
+    ];
+    const TENS: [&str; 10] = ["", "", "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety"];
+    const SCALES: [&str; 13] = [
+        "", "thousand", "million", "billion", "trillion", "quadrillion", "quintillion", "sextillion",
+        "septillion", "octillion", "nonillion", "decillion", "undecillion",
+    ];
+    let text = arg.to_string();
+    let (non_negative, int, "") = decimal(&text)? else { return None; };
+    let int = int.trim_start_matches('0');
+    if int.len() > SCALES.len() * 3 { return None; }
+    let mut res = String::new();
+    if !non_negative && !int.is_empty() { res.push_str("minus "); }
+    if int.is_empty() {
+        res.push_str(ONES[0]);
+        return Some(res);
+    }
+    let mut push = |word: &str| {
+        if !res.is_empty() && !res.ends_with(' ') { res.push(' '); }
+        res.push_str(word);
+    };
+    let groups = (int.len() + 2) / 3;
+    let head = int.len() - (groups - 1) * 3;
+    for group in 0 .. groups {
+        let digits = &int[if group == 0 { 0 } else { head + (group - 1) * 3 } .. head + group * 3];
+        let n: usize = digits.parse().unwrap();
+        if n == 0 { continue; }
+        if n >= 100 {
+            push(ONES[n / 100]);
+            push("hundred");
+        }
+        match n % 100 {
+            0 => { },
+            r @ 1 ..= 19 => push(ONES[r]),
+            r if r % 10 == 0 => push(TENS[r / 10]),
+            r => push(&format!("{}-{}", TENS[r / 10], ONES[r % 10])),
+        }
+        let scale = SCALES[groups - 1 - group];
+        if !scale.is_empty() { push(scale); }
+    }
+    Some(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Arguments, Spec};
+
+    #[test]
+    fn exp() {
+        macro_rules! check {
+            ($value:expr, [$($spec:literal),*]) => {
+                $(
+                    assert_eq!(
+                        Arguments::new(concat!("{:", $spec, "}"), &[$value]).to_string(),
+                        format!(concat!("{:", $spec, "}"), $value),
+                        "{{:{}}} with {}", $spec, stringify!($value)
+                    );
+                )*
+            };
+        }
+
+        macro_rules! conformance {
+            ([$($value:expr),*], $specs:tt) => {
+                $(check!($value, $specs);)*
+            };
+        }
+
+        conformance!([
+            0, 15, 25, 35, -1500, u64::MAX, 1.5f64, -0.0f64, 0.125f64, 2.675f64, 9.5f64, 0.95f64, 1e-7f64,
+            1e300f64, 5e-324f64, f64::MAX, 0.1f32, f64::NAN, f64::INFINITY
+        ], ["e", "E", ".0e", ".1E", ".2E", ".3e", "+010.2E", "*^12e", "<8E", "010E", ".20e"]);
+        assert_eq!(Arguments::new("{:E}|{:.1e}", &["abc"; 2]).to_string(), "abc|a");
+        assert_eq!(Arguments::new("{:.18446744073709551615e}", &[1.5]).to_string(), ":.18446744073709551615e");
+        let s = crate::Format::parse("{1:.{0}e}").unwrap().render_from(["18446744073709551615", "1.5"]);
+        assert_eq!((&s[.. 4], &s[s.len() - 3 ..], s.len()), ("1.50", "0e0", 2 + 65535 + 2));
+    }
+
+    #[test]
+    fn fixed() {
+        let s = Arguments::new("{:fixed(2)}|{:fixed(2)}|{:fixed(3)}|{:fixed(0)}|{:+fixed(1)}", &[1234, -5, 7, 42, 0]).to_string();
+        assert_eq!(s, "12.34|-0.05|0.007|42|+0.0");
+        let s = Arguments::new("{:>8fixed(2)}|{:08fixed(2)}|{:*<7fixed(2)}", &[-1234; 3]).to_string();
+        assert_eq!(s, "  -12.34|-0012.34|-12.34*");
+        let s = Arguments::new("{:.1fixed(2)}|{:.1fixed(2)}|{:.0fixed(2)}|{:.4fixed(2)}|{:.0fixed(3)}", &[1225, 1235, 9999, 5, 1500]).to_string();
+        assert_eq!(s, "12.2|12.4|100|0.0500|2");
+        assert_eq!(Arguments::new("{:fixed(2)}", &[u128::MAX]).to_string(), "3402823669209384634633746074317682114.55");
+        assert_eq!(Arguments::new("{:fixed(2)}|{:>5fixed(2)}", &[1.5, f64::NAN]).to_string(), "1.5|  NaN");
+        assert_eq!(Arguments::new("{:fixed(256)}", &[1]).to_string(), ":fixed(256)");
+        assert_eq!(Arguments::new("{:.18446744073709551615fixed(2)}", &[1]).to_string(), ":.18446744073709551615fixed(2)");
+        let s = crate::Format::parse("{1:.{0}fixed(2)}").unwrap().render_from(["18446744073709551615", "125"]);
+        assert_eq!((&s[.. 5], s.len()), ("1.250", 2 + 65535));
+    }
+
+    #[test]
+    fn ordinal() {
+        let s = Arguments::new("{:ordinal} {:ordinal} {:ordinal} {:ordinal} {:ordinal} {:ordinal} {:ordinal}", &[0, 1, 2, 3, 4, 11, 12]).to_string();
+        assert_eq!(s, "0th 1st 2nd 3rd 4th 11th 12th");
+        let s = Arguments::new("{:ordinal} {:ordinal} {:ordinal} {:ordinal} {:ordinal}", &[13, 21, 102, 111, -3]).to_string();
+        assert_eq!(s, "13th 21st 102nd 111th -3rd");
+        assert_eq!(Arguments::new("{:>6ordinal}|{:<5ordinal}|{:ordinal}", &[&22 as &dyn core::fmt::Display, &1.5, &"x"]).to_string(), "  22nd|1.5  |x");
+    }
+
+    #[test]
+    fn url() {
+        let s = Arguments::new("https://api/{:url(path)}/items?q={:url}&n={:url}", &["acme corp/eu", "a&b=c d?#ж", "1"]).to_string();
+        assert_eq!(s, "https://api/acme%20corp/eu/items?q=a%26b%3Dc%20d%3F%23%D0%B6&n=1");
+        assert_eq!(Arguments::new("{:url}|{:url(path)}", &["-._~!*'();:@/", "-._~!*'();:@/?%"]).to_string(), "-._~%21%2A%27%28%29%3B%3A%40%2F|-._~!*'();:@/%3F%25");
+        assert_eq!(Arguments::new("{:>5url}|{:.1url}", &[" ", "ab"]).to_string(), "  %20|ab");
+        assert_eq!(Spec::parse("url(path)").unwrap().to_string(), "url(path)");
+        assert!(Spec::parse("url(query)").is_none());
+    }
+
+    #[test]
+    fn words() {
+        let s = Arguments::new("{:words}|{:words}|{:words}|{:words}|{:words}", &[0, 7, 42, -115, 1_000_010]).to_string();
+        assert_eq!(s, "zero|seven|forty-two|minus one hundred fifteen|one million ten");
+        let s = Arguments::new("{:words}", &[1_234_567_890_u64]).to_string();
+        assert_eq!(s, "one billion two hundred thirty-four million five hundred sixty-seven thousand eight hundred ninety");
+        assert!(Arguments::new("{:words}", &[u128::MAX]).to_string().starts_with("three hundred forty undecillion "));
+        let s = Arguments::new("{:>12words}|{:*<8words}|{:08words}|{:words}", &[&20 as &dyn core::fmt::Display, &30, &90, &"x"]).to_string();
+        assert_eq!(s, "      twenty|thirty**|ninety  |x");
+        assert_eq!(Arguments::new("{:words}", &[1.5]).to_string(), "1.5");
+    }
+
+    #[cfg(feature = "locale")]
+    #[test]
+    fn currency() {
+        let s = Arguments::new("{:cur(USD)}|{:cur(USD)}|{:cur(USD)}|{:cur(JPY)}|{:cur(KWD)}", &[0.125, 999.995, -1234567.0, 2.5, 1.0]).to_string();
+        assert_eq!(s, "$0.12|$1,000.00|-$1,234,567.00|¥2|KWD\u{A0}1.000");
+        let s = Arguments::new("{:cur(EUR,de)}|{:cur(EUR,fr)}|{:cur(RUB,ru)}|{:cur(CHF,de)}", &[1234.5; 4]).to_string();
+        assert_eq!(s, "1.234,50\u{A0}€|1\u{202F}234,50\u{A0}€|1\u{A0}234,50\u{A0}₽|1.234,50\u{A0}CHF");
+        let s = Arguments::new("{:>10cur(USD)}|{:+cur(USD)}|{:.3cur(USD)}|{:cur(USD)}", &[5; 4]).to_string();
+        assert_eq!(s, "     $5.00|+$5.00|$5.00|$5.00");
+        assert_eq!(Arguments::new("{:cur(USD)}|{:>4cur(USD)}", &["n/a"; 2]).to_string(), "n/a| n/a");
+        assert_eq!(Arguments::new("{:cur(usd)}|{:cur(EUR,xx)}", &[1; 2]).to_string(), ":cur(usd)|:cur(EUR,xx)");
+        assert_eq!(Spec::parse(">9cur(EUR,de)").unwrap().to_string(), ">9cur(EUR,de)");
+        assert_eq!(Spec::parse("cur(USD,en)").unwrap().to_string(), "cur(USD)");
+    }
+}
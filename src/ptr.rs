@@ -0,0 +1,42 @@
+use core::fmt::{self, Display, Pointer};
+
+/// An argument implementing [`Pointer`], e.g. a reference or a raw pointer, displayed as its address,
+/// the same as with `{:p}` in the [`format!`](std::format) macro, including the spec flags.
+///
+/// The `p` type of the spec, e.g. `{:#p}`, is accepted for compatibility with `format!` templates,
+/// but does not change the formatting: arguments are displayed with [`Display`],
+/// so an address is shown only if the argument is wrapped in `Ptr`.
+/// # Examples:
+/// ```rust
+/// use dyn_fmt::{Arguments, Ptr};
+/// let x = 1;
+/// let s = Arguments::new("{} {:>20}", &[Ptr(&x); 2]).to_string();
+/// assert_eq!(s, format!("{:p} {:>20p}", &x, &x));
+/// ```
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Ptr<T>(pub T);
+
+impl<T: Pointer> Display for Ptr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { self.0.fmt(f) }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "std")]
+    #[test]
+    fn pointer() {
+        use crate::{Arguments, Format, Ptr, Spec};
+        use std::collections::HashMap;
+
+
+        let x = [1u8, 2];
+        let p = Ptr(x.as_ptr());
+        let s = Arguments::new("{:p}|{:#p}|{:<20p}|{:020p}|{}", &[p; 5]).to_string();
+        assert_eq!(s, format!("{:p}|{:#p}|{:<20p}|{:020p}|{:p}", p.0, p.0, p.0, p.0, p.0));
+        assert_eq!(Arguments::new("{:p}", &["text"]).to_string(), "text");
+        let format = Format::parse("at {addr:p}").unwrap();
+        assert_eq!(format.render_from(HashMap::from([("addr", Ptr(&x))])), format!("at {:p}", &x));
+        assert_eq!(Spec::parse("#018p").unwrap().to_string(), "#018p");
+        assert!(Spec::parse("p8").is_none());
+    }
+}
@@ -0,0 +1,219 @@
+use core::fmt::{self, Display, Write};
+
+/// A quoting rule making text safe to embed into a particular syntax, e.g. an SQL literal or a CSV field.
+///
+/// The shipped rules are [`SqlQuote`] and [`Csv`], selectable per placeholder by the presentation,
+/// e.g. `{:sql_literal}` or `{:csv}`. Any rule can be applied to an argument by wrapping it in [`Quoted`].
+///
+/// Unavailable in `no_std` environment.
+pub trait Quote {
+    /// Appends the quoted text to `out`.
+    fn quote(&self, text: &str, out: &mut String);
+}
+
+/// Encloses the text in `quote` characters, doubling those inside.
+fn enclose(text: &str, quote: char, out: &mut String) {
+    out.reserve(text.len() + 2);
+    out.push(quote);
+    for c in text.chars() {
+        if c == quote { out.push(quote); }
+        out.push(c);
+    }
+    out.push(quote);
+}
+
+/// A field quoting style of the [`Csv`](crate::Presentation::Csv) presentation:
+/// `csv`, `tsv`, `csv(safe)`, or `tsv(safe)`.
+///
+/// A value containing the delimiter (a comma or a tab), a double quote, or a line break
+/// is enclosed in double quotes, with embedded double quotes doubled, as RFC 4180 specifies.
+/// Other values are substituted as is.
+///
+/// The `safe` variants also neutralize values which spreadsheet applications would evaluate
+/// as formulas, i.e. starting with `=`, `+`, `-`, `@`, a tab, or a carriage return:
+/// such a value is prefixed with `'` and quoted. Note that it affects negative numbers as well.
+/// # Examples:
+/// ```rust
+/// use dyn_fmt::Arguments;
+/// let row = Arguments::new("{:csv},{:csv},{:csv(safe)}", &["plain", "say \"hi\", bye", "=1+2"]).to_string();
+/// assert_eq!(row, "plain,\"say \"\"hi\"\", bye\",\"'=1+2\"");
+/// ```
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Csv {
+    delimiter: char,
+    safe: bool,
+}
+
+impl Csv {
+    pub(crate) fn parse(s: &str) -> Option<Csv> {
+        let (delimiter, safe) = match s {
+            "csv" => (',', false),
+            "tsv" => ('\t', false),
+            "csv(safe)" => (',', true),
+            "tsv(safe)" => ('\t', true),
+            _ => return None,
+        };
+        Some(Csv { delimiter, safe })
+    }
+
+    /// The field delimiter, `,` or `\t`.
+    pub fn delimiter(&self) -> char { self.delimiter }
+
+    /// Whether values which could be evaluated as formulas are neutralized.
+    pub fn neutralizes_formulas(&self) -> bool { self.safe }
+}
+
+impl Quote for Csv {
+    fn quote(&self, text: &str, out: &mut String) {
+        if self.safe && text.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+            return enclose(&format!("'{}", text), '"', out);
+        }
+        if text.contains([self.delimiter, '"', '\r', '\n']) { return enclose(text, '"', out); }
+        out.push_str(text);
+    }
+}
+
+impl Display for Csv {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(if self.delimiter == ',' { "csv" } else { "tsv" })?;
+        if self.safe { f.write_str("(safe)")?; }
+        Ok(())
+    }
+}
+
+/// An SQL quoting rule of the [`SqlQuote`](crate::Presentation::SqlQuote) presentation:
+/// `sql_literal`, `sql_ident`, or `sql_backtick`.
+///
+/// It is intended for generating SQL, e.g. DDL, from vetted templates,
+/// where a value becomes a part of the statement text. It is not parameter binding:
+/// values in queries should be passed to the database driver as bound parameters instead.
+/// The quoting follows the SQL standard, so it is not suitable for modes where a backslash
+/// is an escape character inside string literals, e.g. MySQL without `NO_BACKSLASH_ESCAPES`.
+/// # Examples:
+/// ```rust
+/// use dyn_fmt::Arguments;
+/// let args = ["order", "my table", "O'Brien"];
+/// let sql = Arguments::new("SELECT {:sql_ident} FROM {:sql_backtick} WHERE name = {:sql_literal}", &args).to_string();
+/// assert_eq!(sql, "SELECT \"order\" FROM `my table` WHERE name = 'O''Brien'");
+/// ```
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum SqlQuote {
+    /// `sql_literal`, a string literal in single quotes, e.g. `'O''Brien'`.
+    Literal,
+    /// `sql_ident`, an identifier in double quotes, e.g. `"my ""table"""`.
+    Identifier,
+    /// `sql_backtick`, an identifier in backticks, as used by MySQL, e.g. `` `my table` ``.
+    BacktickIdentifier,
+}
+
+impl SqlQuote {
+    pub(crate) fn parse(s: &str) -> Option<SqlQuote> {
+        match s {
+            "sql_literal" => Some(SqlQuote::Literal),
+            "sql_ident" => Some(SqlQuote::Identifier),
+            "sql_backtick" => Some(SqlQuote::BacktickIdentifier),
+            _ => None,
+        }
+    }
+}
+
+impl Quote for SqlQuote {
+    fn quote(&self, text: &str, out: &mut String) {
+        let quote = match self {
+            SqlQuote::Literal => '\'',
+            SqlQuote::Identifier => '"',
+            SqlQuote::BacktickIdentifier => '`',
+        };
+        enclose(text, quote, out);
+    }
+}
+
+impl Display for SqlQuote {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            SqlQuote::Literal => "sql_literal",
+            SqlQuote::Identifier => "sql_ident",
+            SqlQuote::BacktickIdentifier => "sql_backtick",
+        })
+    }
+}
+
+/// An argument quoted with a [`Quote`] rule, e.g. a custom one.
+///
+/// The spec is applied to the quoted text.
+///
+/// Unavailable in `no_std` environment.
+/// # Examples:
+/// ```rust
+/// use dyn_fmt::{Arguments, Quote, Quoted};
+///
+/// struct Brackets;
+///
+/// impl Quote for Brackets {
+///     fn quote(&self, text: &str, out: &mut String) {
+///         out.push('[');
+///         out.push_str(&text.replace(']', "]]"));
+///         out.push(']');
+///     }
+/// }
+///
+/// let s = Arguments::new("SELECT * FROM {}", &[Quoted(Brackets, "a]b")]).to_string();
+/// assert_eq!(s, "SELECT * FROM [a]]b]");
+/// ```
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
+pub struct Quoted<Q, T>(pub Q, pub T);
+
+impl<Q: Quote, T: Display> Display for Quoted<Q, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut text = String::new();
+        write!(text, "{}", self.1)?;
+        let mut res = String::with_capacity(text.len() + 2);
+        self.0.quote(&text, &mut res);
+        f.pad(&res)
+    }
+}
+
+/// Quotes the argument text with the rule.
+pub(crate) fn quote<Q: Quote, T: Display + ?Sized>(rule: &Q, arg: &T) -> String {
+    let mut res = String::new();
+    rule.quote(&arg.to_string(), &mut res);
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Arguments, Format, Quoted, Spec, SqlQuote};
+    use std::collections::HashMap;
+
+    #[test]
+    fn csv() {
+        let values = ["a", "a,b", "a\tb", "\"", "line\r\nbreak", "-5", "@x", " =x", ""];
+        let row = |spec: &str| values.iter().map(|x| Arguments::new(spec, &[x]).to_string()).collect::<Vec<_>>();
+        assert_eq!(row("{:csv}"), ["a", "\"a,b\"", "a\tb", "\"\"\"\"", "\"line\r\nbreak\"", "-5", "@x", " =x", ""]);
+        assert_eq!(row("{:tsv}"), ["a", "a,b", "\"a\tb\"", "\"\"\"\"", "\"line\r\nbreak\"", "-5", "@x", " =x", ""]);
+        assert_eq!(row("{:csv(safe)}"), ["a", "\"a,b\"", "a\tb", "\"\"\"\"", "\"line\r\nbreak\"", "\"'-5\"", "\"'@x\"", " =x", ""]);
+        assert_eq!(row("{:tsv(safe)}")[2], "\"a\tb\"");
+        let format = Format::parse("{id},{name:csv}\n").unwrap();
+        let args = HashMap::from([("id", "7"), ("name", "Smith, \"Jr\"")]);
+        assert_eq!(format.render_from(args), "7,\"Smith, \"\"Jr\"\"\"\n");
+        for s in ["csv", "tsv", "csv(safe)", "tsv(safe)"] {
+            assert_eq!(Spec::parse(s).unwrap().to_string(), s);
+        }
+        assert!(Spec::parse("csv(tab)").is_none());
+    }
+
+    #[test]
+    fn sql() {
+        let s = Arguments::new("{:sql_literal} {:sql_ident} {:sql_backtick} {:sql_literal}", &["it's", "a\"b", "c`d", ""]).to_string();
+        assert_eq!(s, "'it''s' \"a\"\"b\" `c``d` ''");
+        assert_eq!(Arguments::new("{:sql_literal}|{:>6sql_ident}", &[&-1.5 as &dyn core::fmt::Display, &"x"]).to_string(), "'-1.5'|   \"x\"");
+        let format = Format::parse("CREATE TABLE {table:sql_ident} (id INT DEFAULT {default:sql_literal})").unwrap();
+        let args = HashMap::from([("table", "user data"), ("default", "0'; DROP TABLE x; --")]);
+        assert_eq!(format.render_from(args), "CREATE TABLE \"user data\" (id INT DEFAULT '0''; DROP TABLE x; --')");
+        assert_eq!(Arguments::new("{:>8}", &[Quoted(SqlQuote::Literal, 42)]).to_string(), "    '42'");
+        for s in ["sql_literal", "sql_ident", "sql_backtick"] {
+            assert_eq!(Spec::parse(s).unwrap().to_string(), s);
+        }
+    }
+}
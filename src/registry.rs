@@ -0,0 +1,149 @@
+use crate::spec::fmt_arg;
+use crate::{InvalidPlaceholders, to_string_measured, write_args};
+use core::any::{Any, TypeId};
+use core::fmt::{self, Debug, Display};
+use std::collections::HashMap;
+
+/// A [`Display`]able value which type can be recognized by [`FormatterRegistry`].
+///
+/// Implemented for all `'static` types implementing [`Display`].
+/// Use `&dyn AnyDisplay` to pass arguments of different types.
+pub trait AnyDisplay: Display {
+    /// Returns the value as [`Any`].
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: Display + Any> AnyDisplay for T {
+    fn as_any(&self) -> &dyn Any { self }
+}
+
+type FmtFn = dyn Fn(&dyn Any, &mut fmt::Formatter) -> fmt::Result + Send + Sync;
+
+/// A set of custom formatters for specific types, consulted before falling back to [`Display`].
+///
+/// Allows to change the presentation of foreign types, which [`Display`] implementation cannot be changed.
+/// Unavailable in `no_std` environment.
+/// # Examples:
+/// ```rust
+/// use dyn_fmt::{AnyDisplay, FormatterRegistry};
+/// use std::fmt::Write;
+///
+/// struct Cents(u64);
+///
+/// impl std::fmt::Display for Cents {
+///     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result { write!(f, "{} cents", self.0) }
+/// }
+///
+/// let mut registry = FormatterRegistry::new();
+/// registry.register(|x: &Cents, f| write!(f, "${}.{:02}", x.0 / 100, x.0 % 100));
+/// let args: [&dyn AnyDisplay; 2] = [&"Total", &Cents(1050)];
+/// assert_eq!(registry.format("{}: {}", args), "Total: $10.50");
+/// ```
+#[derive(Default)]
+pub struct FormatterRegistry {
+    formatters: HashMap<TypeId, Box<FmtFn>>,
+}
+
+impl Debug for FormatterRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FormatterRegistry").field("formatters", &self.formatters.len()).finish()
+    }
+}
+
+impl FormatterRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self { Self::default() }
+
+    /// Sets a formatter for values of type `T`, replacing the previously registered one, if any.
+    pub fn register<T: Any>(
+        &mut self,
+        formatter: impl Fn(&T, &mut fmt::Formatter) -> fmt::Result + Send + Sync + 'static
+    ) -> &mut Self {
+        let formatter = move |value: &dyn Any, f: &mut fmt::Formatter| formatter(value.downcast_ref().unwrap(), f);
+        self.formatters.insert(TypeId::of::<T>(), Box::new(formatter));
+        self
+    }
+
+    /// Removes the formatter for values of type `T`. Returns `true` if there was one.
+    pub fn unregister<T: Any>(&mut self) -> bool {
+        self.formatters.remove(&TypeId::of::<T>()).is_some()
+    }
+
+    /// Formats a single value using its registered formatter or its [`Display`] implementation.
+    pub fn fmt_value<T: AnyDisplay + ?Sized>(&self, value: &T, f: &mut fmt::Formatter) -> fmt::Result {
+        let any = value.as_any();
+        match self.formatters.get(&any.type_id()) {
+            Some(formatter) => formatter(any, f),
+            None => value.fmt(f),
+        }
+    }
+
+    /// Combines a format string with arguments, similar to [`Arguments::new`](crate::Arguments::new),
+    /// but formatting arguments using the registry.
+    pub fn args<'a, F: AsRef<str>, T: AnyDisplay + ?Sized + 'a, I: IntoIterator<Item=&'a T> + Clone>(
+        &self,
+        fmt: F,
+        args: I
+    ) -> RegistryArguments<'_, 'a, F, T, I> {
+        RegistryArguments { registry: self, fmt, args }
+    }
+
+    /// Creates a [`String`] similar to [`format`](crate::AsStrFormatExt::format),
+    /// but formatting arguments using the registry.
+    pub fn format<'a, T: AnyDisplay + ?Sized + 'a>(
+        &self,
+        fmt: impl AsRef<str>,
+        args: impl IntoIterator<Item=&'a T> + Clone
+    ) -> String {
+        to_string_measured(&self.args(fmt, args))
+    }
+}
+
+/// A format string combined with its arguments and a [`FormatterRegistry`],
+/// returned by [`FormatterRegistry::args`].
+#[derive(Debug, Clone)]
+pub struct RegistryArguments<'r, 'a, F: AsRef<str>, T: AnyDisplay + ?Sized + 'a, I: IntoIterator<Item=&'a T> + Clone> {
+    registry: &'r FormatterRegistry,
+    fmt: F,
+    args: I,
+}
+
+impl<'r, 'a, F: AsRef<str>, T: AnyDisplay + ?Sized + 'a, I: IntoIterator<Item=&'a T> + Clone> Display
+for RegistryArguments<'r, 'a, F, T, I> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_args(f, self.fmt.as_ref(), self.args.clone(), InvalidPlaceholders::Lenient, |_, _| { }, |arg, spec, f| {
+            if spec.is_default() {
+                self.registry.fmt_value(arg, f)
+            } else {
+                fmt_arg(&Registered { registry: self.registry, value: arg }, spec, f)
+            }
+        })
+    }
+}
+
+struct Registered<'r, 'a, T: AnyDisplay + ?Sized> {
+    registry: &'r FormatterRegistry,
+    value: &'a T,
+}
+
+impl<'r, 'a, T: AnyDisplay + ?Sized> Display for Registered<'r, 'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { self.registry.fmt_value(self.value, f) }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{AnyDisplay, FormatterRegistry};
+
+    #[test]
+    fn overrides_display() {
+        let mut registry = FormatterRegistry::new();
+        registry.register(|x: &bool, f| f.pad(if *x { "yes" } else { "no" }));
+        let args: [&dyn AnyDisplay; 3] = [&true, &1, &false];
+        assert_eq!(registry.format("{} {} {}", args), "yes 1 no");
+        assert_eq!(registry.format("{}", &[true]), "yes");
+        assert_eq!(registry.format("{:>4}|{:.1}", &[true, false]), " yes|n");
+        assert!(registry.unregister::<bool>());
+        assert!(!registry.unregister::<bool>());
+        assert_eq!(registry.format("{}", &[true]), "true");
+    }
+}
@@ -0,0 +1,292 @@
+use crate::{ArgSource, Format};
+use core::fmt::{self, Display};
+use std::collections::HashMap;
+
+/// A script value converted to an argument.
+#[derive(Debug, Clone)]
+pub(crate) enum ScriptValue {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Text(String),
+}
+
+impl Display for ScriptValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScriptValue::Int(x) => x.fmt(f),
+            ScriptValue::Float(x) => x.fmt(f),
+            ScriptValue::Bool(x) => x.fmt(f),
+            ScriptValue::Text(x) => x.fmt(f),
+        }
+    }
+}
+
+/// The arguments passed by a script: a list, a table of named values, or both.
+#[derive(Debug, Default)]
+pub(crate) struct ScriptArgs {
+    pub(crate) positional: Vec<ScriptValue>,
+    pub(crate) named: HashMap<String, ScriptValue>,
+}
+
+impl ArgSource for ScriptArgs {
+    type Arg = ScriptValue;
+
+    fn len(&self) -> usize { self.positional.len() }
+
+    fn get(&self, index: usize) -> Option<&ScriptValue> { self.positional.get(index) }
+
+    fn get_named(&self, name: &str) -> Option<&ScriptValue> { self.named.get(name) }
+
+    fn for_each_name(&self, f: &mut dyn FnMut(&str)) { self.named.keys().for_each(|x| f(x)) }
+}
+
+/// Renders the template parsed with [`Format::parse`], returning the parse error message on failure.
+pub(crate) fn format(template: &str, args: &ScriptArgs) -> Result<String, String> {
+    let format = Format::parse(template).map_err(|e| format!("invalid template: {}", e))?;
+    Ok(format.render_from(args))
+}
+
+/// Registers the `format(template)`, `format(template, array)`, and `format(template, map)` functions
+/// in a [Rhai](https://rhai.rs) engine.
+///
+/// The template has the syntax of [`Format`]: the array provides positional arguments,
+/// the object map provides named ones. Integers, floats, and booleans keep their type,
+/// so the spec applies to them as to numbers, other values are converted to strings.
+/// A malformed template raises a script error.
+///
+/// Available with the `"rhai"` feature.
+/// # Examples:
+/// ```rust
+/// let mut engine = rhai::Engine::new();
+/// dyn_fmt::register_rhai(&mut engine);
+/// let s: String = engine.eval(r#"format("{} has {n:>3} items, {:.1}%", ["cart", 12.345], #{ n: 7 })"#).unwrap();
+/// assert_eq!(s, "cart has   7 items, 12.3%");
+/// ```
+#[cfg(feature = "rhai")]
+pub fn register_rhai(engine: &mut rhai::Engine) {
+    use rhai::{Array, Dynamic, EvalAltResult, Map};
+
+    fn value(x: &Dynamic) -> ScriptValue {
+        if let Ok(x) = x.as_int() { return ScriptValue::Int(x); }
+        if let Ok(x) = x.as_float() { return ScriptValue::Float(x); }
+        if let Ok(x) = x.as_bool() { return ScriptValue::Bool(x); }
+        ScriptValue::Text(x.to_string())
+    }
+
+    fn named(map: Map) -> HashMap<String, ScriptValue> {
+        map.iter().map(|(k, v)| (k.to_string(), value(v))).collect()
+    }
+
+    fn render(template: &str, args: ScriptArgs) -> Result<String, Box<EvalAltResult>> {
+        format(template, &args).map_err(Into::into)
+    }
+
+    engine.register_fn("format", |template: &str| render(template, ScriptArgs::default()));
+    engine.register_fn("format", |template: &str, args: Array| {
+        render(template, ScriptArgs { positional: args.iter().map(value).collect(), named: HashMap::new() })
+    });
+    engine.register_fn("format", |template: &str, args: Map| {
+        render(template, ScriptArgs { positional: Vec::new(), named: named(args) })
+    });
+    engine.register_fn("format", |template: &str, args: Array, map: Map| {
+        render(template, ScriptArgs { positional: args.iter().map(value).collect(), named: named(map) })
+    });
+}
+
+/// Sets the global `format(template, args)` function in a Lua state.
+///
+/// The template has the syntax of [`Format`]. The optional `args` table provides positional arguments
+/// in its sequence part, `args[1]`, `args[2]`, and so on, and named arguments under string keys.
+/// Integers, floats, and booleans keep their type, so the spec applies to them as to numbers,
+/// other values are converted to strings with `tostring`. A malformed template raises a Lua error.
+///
+/// Available with the `"mlua"` feature.
+/// # Examples:
+/// ```rust
+/// let lua = mlua::Lua::new();
+/// dyn_fmt::register_lua(&lua).unwrap();
+/// let s: String = lua.load(r#"format("{} has {n:>3} items, {:.1}%", { "cart", 12.345, n = 7 })"#).eval().unwrap();
+/// assert_eq!(s, "cart has   7 items, 12.3%");
+/// ```
+#[cfg(feature = "mlua")]
+pub fn register_lua(lua: &mlua::Lua) -> mlua::Result<()> {
+    use mlua::{Function, Lua, Table, Value};
+
+    fn value(lua: &Lua, x: Value) -> mlua::Result<ScriptValue> {
+        Ok(match x {
+            Value::Integer(x) => ScriptValue::Int(x),
+            Value::Number(x) => ScriptValue::Float(x),
+            Value::Boolean(x) => ScriptValue::Bool(x),
+            Value::String(x) => ScriptValue::Text(x.to_string_lossy().into_owned()),
+            x => ScriptValue::Text(lua.globals().get::<_, Function>("tostring")?.call(x)?),
+        })
+    }
+
+    let format = lua.create_function(|lua, (template, args): (String, Option<Table>)| {
+        let mut res = ScriptArgs::default();
+        if let Some(args) = args {
+            for i in 1 ..= args.raw_len() {
+                res.positional.push(value(lua, args.raw_get(i)?)?);
+            }
+            for pair in args.pairs::<Value, Value>() {
+                if let (Value::String(name), x) = pair? {
+                    res.named.insert(name.to_str()?.into(), value(lua, x)?);
+                }
+            }
+        }
+        format(&template, &res).map_err(mlua::Error::RuntimeError)
+    })?;
+    lua.globals().set("format", format)
+}
+
+/// Adds the `Format` class and the `format(template, args=None)` function to a Python module.
+///
+/// `Format(template)` parses the template with [`Format::parse`], raising `ValueError` if it is malformed,
+/// and `Format.render(args=None)` renders it. The arguments are a list or a tuple of positional values,
+/// or a dict of named ones. Integers, floats, and booleans keep their type,
+/// so the spec applies to them as to numbers, other values are converted to strings with `str`.
+///
+/// Call it from the `#[pymodule]` function of an extension crate.
+///
+/// Available with the `"pyo3"` feature.
+/// # Examples:
+/// ```rust
+/// use pyo3::prelude::*;
+///
+/// #[pymodule]
+/// fn templates(m: &Bound<'_, PyModule>) -> PyResult<()> {
+///     dyn_fmt::register_python(m)
+/// }
+/// ```
+#[cfg(feature = "pyo3")]
+pub fn register_python(module: &pyo3::Bound<'_, pyo3::types::PyModule>) -> pyo3::PyResult<()> {
+    python::register(module)
+}
+
+// The code generated by the pyo3 macros converts errors to the same type.
+#[cfg(feature = "pyo3")]
+#[allow(clippy::useless_conversion)]
+mod python {
+    use super::{ScriptArgs, ScriptValue};
+    use crate::Format;
+    use pyo3::exceptions::{PyTypeError, PyValueError};
+    use pyo3::prelude::*;
+    use pyo3::types::{PyBool, PyDict, PyFloat, PyInt, PyList, PyString, PyTuple};
+
+    fn value(x: &Bound<'_, PyAny>) -> PyResult<ScriptValue> {
+        if let Ok(x) = x.downcast::<PyBool>() { return Ok(ScriptValue::Bool(x.is_true())); }
+        if x.is_instance_of::<PyInt>() {
+            if let Ok(x) = x.extract() { return Ok(ScriptValue::Int(x)); }
+        }
+        if let Ok(x) = x.downcast::<PyFloat>() { return Ok(ScriptValue::Float(x.value())); }
+        if let Ok(x) = x.downcast::<PyString>() { return Ok(ScriptValue::Text(x.to_str()?.into())); }
+        Ok(ScriptValue::Text(x.str()?.to_str()?.into()))
+    }
+
+    fn arguments(x: Option<&Bound<'_, PyAny>>) -> PyResult<ScriptArgs> {
+        let mut res = ScriptArgs::default();
+        let Some(x) = x else { return Ok(res); };
+        if let Ok(x) = x.downcast::<PyDict>() {
+            for (k, v) in x.iter() {
+                res.named.insert(k.str()?.to_str()?.into(), value(&v)?);
+            }
+        } else if x.is_instance_of::<PyList>() || x.is_instance_of::<PyTuple>() {
+            for v in x.iter()? {
+                res.positional.push(value(&v?)?);
+            }
+        } else {
+            return Err(PyTypeError::new_err("format arguments should be a list, a tuple, or a dict"));
+        }
+        Ok(res)
+    }
+
+    /// A parsed template.
+    #[pyclass(name = "Format", module = "dyn_fmt", frozen)]
+    struct PyFormat(Format);
+
+    #[pymethods]
+    impl PyFormat {
+        #[new]
+        fn new(template: &str) -> PyResult<Self> {
+            Format::parse(template).map(PyFormat).map_err(|e| PyValueError::new_err(format!("invalid template: {}", e)))
+        }
+
+        #[getter]
+        fn template(&self) -> &str { self.0.as_str() }
+
+        #[pyo3(signature = (args=None))]
+        fn render(&self, args: Option<&Bound<'_, PyAny>>) -> PyResult<String> {
+            Ok(self.0.render_from(&arguments(args)?))
+        }
+
+        fn __repr__(&self) -> String { format!("Format({:?})", self.0.as_str()) }
+    }
+
+    #[pyfunction]
+    #[pyo3(signature = (template, args=None))]
+    fn format(template: &str, args: Option<&Bound<'_, PyAny>>) -> PyResult<String> {
+        super::format(template, &arguments(args)?).map_err(PyValueError::new_err)
+    }
+
+    pub(super) fn register(module: &Bound<'_, PyModule>) -> PyResult<()> {
+        module.add_class::<PyFormat>()?;
+        module.add_function(wrap_pyfunction!(format, module)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "rhai")]
+    #[test]
+    fn rhai() {
+        let mut engine = rhai::Engine::new();
+        crate::register_rhai(&mut engine);
+        let eval = |script: &str| engine.eval::<String>(script);
+        assert_eq!(eval(r#"format("plain")"#).unwrap(), "plain");
+        assert_eq!(eval(r#"format("{:+} {:05.1} {} {:>4}", [1, 2.25, true, "ab"])"#).unwrap(), "+1 002.2 true   ab");
+        assert_eq!(eval(r#"format("{a}-{b}-{c}", #{ a: (), b: [1, 2], c: 'x' })"#).unwrap(), "-[1, 2]-x");
+        assert_eq!(eval(r#"format("{} {}", ["only"])"#).unwrap(), "only ");
+        let err = eval(r#"format("{:q}", [1])"#).unwrap_err();
+        assert!(err.to_string().contains("invalid template: invalid format spec at 0..4"), "{}", err);
+    }
+
+    #[cfg(feature = "mlua")]
+    #[test]
+    fn lua() {
+        let lua = mlua::Lua::new();
+        crate::register_lua(&lua).unwrap();
+        let eval = |script: &str| lua.load(script).eval::<String>();
+        assert_eq!(eval(r#"return format("plain")"#).unwrap(), "plain");
+        assert_eq!(eval(r#"return format("{:+} {:05.1} {} {:>4}", { 1, 2.25, true, "ab" })"#).unwrap(), "+1 002.2 true   ab");
+        let s = eval(r#"return format("{a}-{b}-{}", { "x", a = setmetatable({}, { __tostring = function() return "obj" end }) })"#);
+        assert_eq!(s.unwrap(), "obj--x");
+        let err = eval(r#"return format("{:q}", {})"#).unwrap_err();
+        assert!(err.to_string().contains("invalid template: invalid format spec at 0..4"), "{}", err);
+    }
+
+    #[cfg(feature = "pyo3")]
+    #[test]
+    fn python() {
+        use pyo3::prelude::*;
+        use pyo3::types::{IntoPyDict, PyModule};
+
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let module = PyModule::new_bound(py, "dyn_fmt").unwrap();
+            crate::register_python(&module).unwrap();
+            let globals = [("dyn_fmt", module)].into_py_dict_bound(py);
+            let eval = |script: &str| py.eval_bound(script, Some(&globals), None).and_then(|x| x.extract::<String>());
+            assert_eq!(eval("dyn_fmt.format('plain')").unwrap(), "plain");
+            assert_eq!(eval("dyn_fmt.format('{:+} {:05.1} {} {:>4}', [1, 2.25, True, 'ab'])").unwrap(), "+1 002.2 true   ab");
+            assert_eq!(eval("dyn_fmt.format('{a}-{b}-{c}', {'a': None, 'b': [1, 2], 'c': 2 ** 70})").unwrap(), "None-[1, 2]-1180591620717411303424");
+            assert_eq!(eval("dyn_fmt.Format('{} {:>3}').render(('x', 7))").unwrap(), "x   7");
+            assert_eq!(eval("repr(dyn_fmt.Format('{name}'))").unwrap(), "Format(\"{name}\")");
+            assert_eq!(eval("dyn_fmt.Format('{name}').template").unwrap(), "{name}");
+            let err = eval("dyn_fmt.Format('{:q}')").unwrap_err();
+            assert!(err.is_instance_of::<pyo3::exceptions::PyValueError>(py));
+            assert!(err.to_string().contains("invalid template: invalid format spec at 0..4"), "{}", err);
+            assert!(eval("dyn_fmt.format('{}', 1)").unwrap_err().is_instance_of::<pyo3::exceptions::PyTypeError>(py));
+        });
+    }
+}
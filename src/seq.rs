@@ -0,0 +1,240 @@
+use crate::bytes::ENCODING;
+use core::cell::Cell;
+use core::fmt::{self, Debug, Display, Write};
+use core::str;
+use std::thread::LocalKey;
+
+thread_local! {
+    /// The separator of the `join` presentation being applied, taken by the outermost [`Seq`].
+    static SEPARATOR: Cell<Option<Separator>> = const { Cell::new(None) };
+
+    /// The separators of the `map` presentation being applied, taken by the outermost [`Pairs`].
+    static PAIR_SEPARATORS: Cell<Option<(Separator, Separator)>> = const { Cell::new(None) };
+}
+
+/// Formats the argument with the presentation parameters available to it through the thread local,
+/// returning `None` if the argument did not take them, e.g. it is not a [`Seq`] or [`Pairs`].
+pub(crate) fn present<S: Copy + 'static, T: Display + ?Sized>(key: &'static LocalKey<Cell<Option<S>>>, params: S, arg: &T) -> Option<String> {
+    key.with(|x| x.set(Some(params)));
+    let res = arg.to_string();
+    key.with(|x| x.replace(None)).is_none().then_some(res)
+}
+
+/// Hides the presentation parameters from the items of a [`Seq`] or [`Pairs`] while they are formatted,
+/// so that a presentation is applied to the outermost argument only, or not at all.
+fn shielded<R>(f: impl FnOnce() -> R) -> R {
+    let separator = SEPARATOR.with(|x| x.take());
+    let pair_separators = PAIR_SEPARATORS.with(|x| x.take());
+    let encoding = ENCODING.with(|x| x.take());
+    let res = f();
+    SEPARATOR.with(|x| x.set(separator));
+    PAIR_SEPARATORS.with(|x| x.set(pair_separators));
+    ENCODING.with(|x| x.set(encoding));
+    res
+}
+
+/// The separator of the [`Join`](crate::Presentation::Join) presentation, e.g. `", "` in `{:join(", ")}`.
+///
+/// A separator is up to 15 bytes long and does not contain quotes and braces.
+#[derive(Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Separator {
+    bytes: [u8; 15],
+    len: u8,
+}
+
+impl Separator {
+    /// The default separator of [`Seq`] elements and [`Pairs`] entries, `", "`.
+    pub const COMMA: Separator = Separator::from_static(", ");
+
+    /// The default separator of [`Pairs`] keys and values, `"="`.
+    pub const EQUALS: Separator = Separator::from_static("=");
+
+    const fn from_static(s: &str) -> Separator {
+        let mut bytes = [0; 15];
+        let mut i = 0;
+        while i < s.len() {
+            bytes[i] = s.as_bytes()[i];
+            i += 1;
+        }
+        Separator { bytes, len: s.len() as u8 }
+    }
+
+    pub(crate) fn parse(s: &str) -> Option<Separator> {
+        let s = s.strip_prefix('"')?.strip_suffix('"')?;
+        if s.len() > 15 || s.contains(['"', '{', '}']) { return None; }
+        let mut bytes = [0; 15];
+        bytes[.. s.len()].copy_from_slice(s.as_bytes());
+        Some(Separator { bytes, len: s.len() as u8 })
+    }
+
+    /// The separator text.
+    pub fn as_str(&self) -> &str { str::from_utf8(&self.bytes[.. usize::from(self.len)]).unwrap() }
+
+    /// Parses the `"=", ", "` parameters of the `map` presentation.
+    pub(crate) fn parse_pair(s: &str) -> Option<(Separator, Separator)> {
+        let end = s.strip_prefix('"')?.find('"')? + 2;
+        let (key, entry) = s.split_at(end);
+        let entry = entry.strip_prefix(',')?.trim_start_matches(' ');
+        Some((Separator::parse(key)?, Separator::parse(entry)?))
+    }
+
+    /// Formats the argument, which is expected to be a [`Seq`], with the separator,
+    /// returning `None` if it is not a `Seq`.
+    pub(crate) fn join<T: Display + ?Sized>(&self, arg: &T) -> Option<String> { present(&SEPARATOR, *self, arg) }
+
+    /// Formats the argument, which is expected to be [`Pairs`], with the key and entry separators,
+    /// returning `None` if it is not `Pairs`.
+    pub(crate) fn map<T: Display + ?Sized>(key: Separator, entry: Separator, arg: &T) -> Option<String> {
+        present(&PAIR_SEPARATORS, (key, entry), arg)
+    }
+}
+
+impl Debug for Separator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { Debug::fmt(self.as_str(), f) }
+}
+
+impl Display for Separator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { write!(f, "\"{}\"", self.as_str()) }
+}
+
+/// A sequence argument, displayed as its elements separated by `, `,
+/// or by the separator of the [`Join`](crate::Presentation::Join) presentation, e.g. `{:join(" | ")}`.
+///
+/// The spec, other than the presentation, is applied to the whole list.
+///
+/// Unavailable in `no_std` environment.
+/// # Examples:
+/// ```rust
+/// use dyn_fmt::{Arguments, Seq};
+/// let tags = ["urgent", "billing"];
+/// let s = Arguments::new("{} / {:join(\" | \")} / {:>20}", &[Seq(&tags); 3]).to_string();
+/// assert_eq!(s, "urgent, billing / urgent | billing /      urgent, billing");
+/// ```
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
+pub struct Seq<I>(pub I);
+
+impl<I: IntoIterator + Clone> Display for Seq<I> where I::Item: Display {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let separator = SEPARATOR.with(|x| x.take());
+        let separator = separator.unwrap_or(Separator::COMMA);
+        let separator = separator.as_str();
+        let mut res = String::new();
+        shielded(|| {
+            for (i, item) in self.0.clone().into_iter().enumerate() {
+                if i != 0 { res.push_str(separator); }
+                write!(res, "{}", item)?;
+            }
+            Ok(())
+        })?;
+        f.pad(&res)
+    }
+}
+
+/// A key-value pair of [`Pairs`].
+pub trait Pair {
+    /// The key type.
+    type Key: Display + ?Sized;
+    /// The value type.
+    type Value: Display + ?Sized;
+
+    /// The key.
+    fn key(&self) -> &Self::Key;
+
+    /// The value.
+    fn value(&self) -> &Self::Value;
+}
+
+impl<K: Display, V: Display> Pair for (K, V) {
+    type Key = K;
+    type Value = V;
+
+    fn key(&self) -> &K { &self.0 }
+
+    fn value(&self) -> &V { &self.1 }
+}
+
+impl<P: Pair + ?Sized> Pair for &P {
+    type Key = P::Key;
+    type Value = P::Value;
+
+    fn key(&self) -> &P::Key { (*self).key() }
+
+    fn value(&self) -> &P::Value { (*self).value() }
+}
+
+/// A map-like argument, e.g. `&[(K, V)]` or `&BTreeMap<K, V>`, displayed as `k1=v1, k2=v2`,
+/// or with the separators of the [`Map`](crate::Presentation::Map) presentation,
+/// e.g. `{:map(": ", "; ")}`. Note that a `HashMap` is iterated in an unspecified order.
+///
+/// The spec, other than the presentation, is applied to the whole list.
+///
+/// Unavailable in `no_std` environment.
+/// # Examples:
+/// ```rust
+/// use dyn_fmt::{Arguments, Pairs};
+/// let labels = [("host", "db1"), ("severity", "critical")];
+/// let s = Arguments::new("{} / {:map(\": \", \"; \")}", &[Pairs(&labels); 2]).to_string();
+/// assert_eq!(s, "host=db1, severity=critical / host: db1; severity: critical");
+/// ```
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
+pub struct Pairs<I>(pub I);
+
+impl<I: IntoIterator + Clone> Display for Pairs<I> where I::Item: Pair {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let separators = PAIR_SEPARATORS.with(|x| x.take());
+        let (key, entry) = separators.unwrap_or((Separator::EQUALS, Separator::COMMA));
+        let mut res = String::new();
+        shielded(|| {
+            for (i, item) in self.0.clone().into_iter().enumerate() {
+                if i != 0 { res.push_str(entry.as_str()); }
+                write!(res, "{}{}{}", item.key(), key.as_str(), item.value())?;
+            }
+            Ok(())
+        })?;
+        f.pad(&res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Arguments, Bytes, Format, Pairs, Seq, Spec};
+    use std::collections::{BTreeMap, HashMap};
+
+    #[test]
+    fn join() {
+        let names = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let s = Arguments::new("{:join(\"\")}|{:join(\" and \")}|{:*^11join(\"-\")}|{:join(\"; \")}", &[Seq(&names); 4]).to_string();
+        assert_eq!(s, "abc|a and b and c|***a-b-c***|a; b; c");
+        let empty: [u8; 0] = [];
+        assert_eq!(Arguments::new("[{:join(\"+\")}]|[{}]", &[Seq(&empty); 2]).to_string(), "[]|[]");
+        assert_eq!(Arguments::new("{:join(\"+\")}|{:>3join(\"+\")}", &[1; 2]).to_string(), "1|  1");
+        let nested = [Seq([1, 2]), Seq([3, 4])];
+        assert_eq!(Arguments::new("{:join(\"; \")}", &[Seq(&nested)]).to_string(), "1, 2; 3, 4");
+        let attrs = [("k", Seq([1, 2]))];
+        assert_eq!(Arguments::new("{:join(\"+\")}|{:join(\"+\")}", &[Pairs(&attrs); 2]).to_string(), "k=1, 2|k=1, 2");
+        assert_eq!(Arguments::new("{:map(\": \", \"; \")}", &[Seq(&attrs.map(|x| Pairs([x])))]).to_string(), "k=1, 2");
+        assert_eq!(Arguments::new("{:HEX}", &[Seq([Bytes([0xab]); 2])]).to_string(), "ab, ab");
+        let format = Format::parse("{tags:join(\" | \")}").unwrap();
+        assert_eq!(format.render_from(HashMap::from([("tags", Seq(["x", "y"]))])), "x | y");
+        assert_eq!(Spec::parse("<4join(\", \")").unwrap().to_string(), "<4join(\", \")");
+        assert!(Spec::parse("join(\"0123456789abcdef\")").is_none());
+        assert!(Spec::parse("join(, )").is_none());
+    }
+
+    #[test]
+    fn map() {
+        let labels = BTreeMap::from([("b", 2), ("a", 1)]);
+        let s = Arguments::new("{}|{:map}|{:map(\"=\", \",\")}|{:<12map(\" \",\"; \")}|", &[Pairs(&labels); 4]).to_string();
+        assert_eq!(s, "a=1, b=2|a=1, b=2|a=1,b=2|a 1; b 2    |");
+        let attrs = vec![("k".to_string(), Seq([1, 2]))];
+        assert_eq!(Arguments::new("{:map(\": \", \"\")}", &[Pairs(&attrs)]).to_string(), "k: 1, 2");
+        assert_eq!(Arguments::new("{:map}", &[Seq([1, 2])]).to_string(), "1, 2");
+        let map = HashMap::from([("x", 1.5)]);
+        assert_eq!(Arguments::new("{:map(\" -> \", \", \")}", &[Pairs(&map)]).to_string(), "x -> 1.5");
+        assert_eq!(Spec::parse("map").unwrap().to_string(), "map");
+        assert_eq!(Spec::parse("map(\"=\",\", \")").unwrap().to_string(), "map");
+        assert_eq!(Spec::parse("map(\":\", \",\")").unwrap().to_string(), "map(\":\", \",\")");
+        assert!(Spec::parse("map(\"=\")").is_none());
+        assert!(Spec::parse("map(\"=\" \",\")").is_none());
+    }
+}
@@ -0,0 +1,135 @@
+use crate::{ArgSource, Format, ParseError};
+use crate::format::SourceArguments;
+use core::fmt::{self, Display, Write};
+use std::collections::HashMap;
+
+/// An output buffer accumulating many rendered templates and literals,
+/// e.g. the lines of a report, without allocating a [`String`] per fragment.
+///
+/// Templates pushed with [`push`](FormatSession::push) are parsed once per session
+/// and the parsed [`Format`]s are reused on subsequent pushes of the same template.
+/// [`clear`](FormatSession::clear) keeps both the buffer capacity and the parsed templates,
+/// so a session can be reused to build many outputs.
+///
+/// Unavailable in `no_std` environment.
+/// # Examples:
+/// ```rust
+/// use dyn_fmt::FormatSession;
+/// let mut session = FormatSession::new();
+/// session.push_str("# Report\n");
+/// for (name, count) in [("apples", "3"), ("pears", "12")] {
+///     session.push("{:<8}{:>3}\n", [name, count]).unwrap();
+/// }
+/// assert_eq!(session.templates(), 1);
+/// assert_eq!(session.into_string(), "# Report\napples    3\npears    12\n");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FormatSession {
+    buf: String,
+    formats: HashMap<String, Format>,
+}
+
+impl FormatSession {
+    /// Creates a session with an empty buffer.
+    pub fn new() -> Self { Self::default() }
+
+    /// Creates a session with an empty buffer of the specified capacity in bytes.
+    pub fn with_capacity(capacity: usize) -> Self {
+        FormatSession { buf: String::with_capacity(capacity), formats: HashMap::new() }
+    }
+
+    /// Renders the template with arguments from an [`ArgSource`], appending the output to the buffer.
+    ///
+    /// The template is parsed on its first push only. A malformed template leaves the buffer unchanged.
+    /// The arguments are substituted according to [`FormatOptions::current_default`](crate::FormatOptions::current_default).
+    pub fn push(&mut self, template: &str, args: impl ArgSource) -> Result<&mut Self, ParseError> {
+        if !self.formats.contains_key(template) {
+            let format = Format::parse(template)?;
+            self.formats.insert(template.to_owned(), format);
+        }
+        let format = &self.formats[template];
+        write_display(&mut self.buf, &SourceArguments::new(format, args));
+        Ok(self)
+    }
+
+    /// Renders an already parsed template with arguments from an [`ArgSource`], appending the output to the buffer.
+    pub fn push_format(&mut self, format: &Format, args: impl ArgSource) -> &mut Self {
+        write_display(&mut self.buf, &SourceArguments::new(format, args));
+        self
+    }
+
+    /// Appends a [`Display`]able value, e.g. [`Arguments`](crate::Arguments), to the buffer.
+    pub fn push_display(&mut self, value: &(impl Display + ?Sized)) -> &mut Self {
+        write_display(&mut self.buf, value);
+        self
+    }
+
+    /// Appends literal text to the buffer.
+    pub fn push_str(&mut self, s: &str) -> &mut Self {
+        self.buf.push_str(s);
+        self
+    }
+
+    /// Returns the output accumulated so far.
+    pub fn as_str(&self) -> &str { &self.buf }
+
+    /// The length of the output in bytes.
+    pub fn len(&self) -> usize { self.buf.len() }
+
+    /// Returns `true` if nothing is output yet.
+    pub fn is_empty(&self) -> bool { self.buf.is_empty() }
+
+    /// The number of distinct templates parsed by the session.
+    pub fn templates(&self) -> usize { self.formats.len() }
+
+    /// Discards the output, keeping the buffer capacity and the parsed templates.
+    pub fn clear(&mut self) { self.buf.clear(); }
+
+    /// Returns the output, leaving the buffer empty and keeping the parsed templates.
+    pub fn take(&mut self) -> String { core::mem::take(&mut self.buf) }
+
+    /// Returns the output.
+    pub fn into_string(self) -> String { self.buf }
+}
+
+impl Write for FormatSession {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.buf.push_str(s);
+        Ok(())
+    }
+}
+
+impl Display for FormatSession {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { f.write_str(&self.buf) }
+}
+
+fn write_display(buf: &mut String, value: &(impl Display + ?Sized)) {
+    write!(buf, "{}", value).expect("a Display implementation returned an error unexpectedly");
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Arguments, Format, FormatSession};
+    use core::fmt::Write;
+    use std::collections::HashMap;
+
+    #[test]
+    fn format_session() {
+        let mut session = FormatSession::with_capacity(64);
+        session.push("{name}: {count:>3}\n", HashMap::from([("name", "a"), ("count", "1")])).unwrap()
+            .push("{name}: {count:>3}\n", HashMap::from([("name", "bc"), ("count", "22")])).unwrap()
+            .push_str("--\n");
+        assert!(session.push("{:q}", [1]).is_err());
+        let total = Format::parse("{}{:.1}").unwrap();
+        session.push_format(&total, ["total ", "23"]).push_display(&Arguments::new("{}!", &['x']));
+        write!(session, "{:02}", 7).unwrap();
+        assert_eq!(session.as_str(), "a:   1\nbc:  22\n--\ntotal 2x!07");
+        assert_eq!((session.templates(), session.len()), (1, 29));
+        let capacity = session.take().capacity();
+        assert!(capacity >= 64 && session.is_empty());
+        session.push("{}-{}", [1, 2]).unwrap();
+        assert_eq!(session.to_string(), "1-2");
+        session.clear();
+        assert_eq!((session.templates(), session.into_string()), (2, String::new()));
+    }
+}
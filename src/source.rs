@@ -0,0 +1,209 @@
+use core::fmt::Display;
+use core::iter::FusedIterator;
+#[cfg(feature = "std")]
+use std::borrow::Borrow;
+#[cfg(feature = "std")]
+use std::collections::{BTreeMap, HashMap};
+#[cfg(feature = "std")]
+use std::hash::{BuildHasher, Hash};
+
+/// A random-access collection of arguments.
+///
+/// Implement this trait for a view into a slab, an arena, or component storages
+/// to pass arguments stored there without copying them into a temporary collection.
+/// Use [`iter`](ArgSource::iter) to get an iterator accepted by [`Arguments::new`](crate::Arguments::new)
+/// and other formatting functions.
+/// # Examples:
+/// ```rust
+/// use dyn_fmt::{ArgSource, Arguments};
+///
+/// struct Names<'a> {
+///     storage: &'a [String],
+///     ids: &'a [usize],
+/// }
+///
+/// impl<'a> ArgSource for Names<'a> {
+///     type Arg = str;
+///     fn len(&self) -> usize { self.ids.len() }
+///     fn get(&self, index: usize) -> Option<&str> {
+///         self.storage.get(*self.ids.get(index)?).map(|x| x.as_str())
+///     }
+/// }
+///
+/// let storage = ["Anna".to_string(), "Carl".to_string(), "Bob".to_string()];
+/// let names = Names { storage: &storage, ids: &[2, 0] };
+/// assert_eq!(Arguments::new("{} and {}", names.iter()).to_string(), "Bob and Anna");
+/// ```
+pub trait ArgSource {
+    /// The argument type.
+    type Arg: Display + ?Sized;
+
+    /// The number of arguments.
+    ///
+    /// The arguments are expected to have indices from `0` to `len() - 1`, see [`get`](ArgSource::get).
+    fn len(&self) -> usize;
+
+    /// Returns the argument with the specified index, or `None` if there is no such argument.
+    ///
+    /// Implementations should return `None` for any `index >= len()`.
+    /// An argument beyond the length is still rendered and reported as used,
+    /// but [`iter`](ArgSource::iter) does not reach it, and it is not reported as unused when no placeholder refers to it.
+    fn get(&self, index: usize) -> Option<&Self::Arg>;
+
+    /// Returns the argument with the specified name, or `None` if there is no such argument.
+    /// The default implementation provides no named arguments.
+    fn get_named(&self, name: &str) -> Option<&Self::Arg> {
+        let _ = name;
+        None
+    }
+
+    /// Calls `f` with the name of every named argument, e.g. to report arguments no placeholder refers to.
+    /// The default implementation provides no names.
+    fn for_each_name(&self, f: &mut dyn FnMut(&str)) {
+        let _ = f;
+    }
+
+    /// Returns `true` if there are no arguments.
+    fn is_empty(&self) -> bool { self.len() == 0 }
+
+    /// Returns an iterator over the arguments. The iteration stops at the first missing argument.
+    fn iter(&self) -> ArgSourceIter<'_, Self> { ArgSourceIter { source: self, index: 0 } }
+}
+
+impl<T: Display> ArgSource for [T] {
+    type Arg = T;
+
+    fn len(&self) -> usize { <[T]>::len(self) }
+
+    fn get(&self, index: usize) -> Option<&T> { <[T]>::get(self, index) }
+}
+
+impl<T: Display, const N: usize> ArgSource for [T; N] {
+    type Arg = T;
+
+    fn len(&self) -> usize { N }
+
+    fn get(&self, index: usize) -> Option<&T> { self.as_slice().get(index) }
+}
+
+#[cfg(feature = "std")]
+impl<T: Display> ArgSource for Vec<T> {
+    type Arg = T;
+
+    fn len(&self) -> usize { Vec::len(self) }
+
+    fn get(&self, index: usize) -> Option<&T> { self.as_slice().get(index) }
+}
+
+/// Provides named arguments only.
+#[cfg(feature = "std")]
+impl<K: Borrow<str> + Hash + Eq, T: Display, S: BuildHasher> ArgSource for HashMap<K, T, S> {
+    type Arg = T;
+
+    fn len(&self) -> usize { 0 }
+
+    fn get(&self, _index: usize) -> Option<&T> { None }
+
+    fn get_named(&self, name: &str) -> Option<&T> { HashMap::get(self, name) }
+
+    fn for_each_name(&self, f: &mut dyn FnMut(&str)) { self.keys().for_each(|x| f(x.borrow())) }
+}
+
+/// Provides named arguments only.
+#[cfg(feature = "std")]
+impl<K: Borrow<str> + Ord, T: Display> ArgSource for BTreeMap<K, T> {
+    type Arg = T;
+
+    fn len(&self) -> usize { 0 }
+
+    fn get(&self, _index: usize) -> Option<&T> { None }
+
+    fn get_named(&self, name: &str) -> Option<&T> { BTreeMap::get(self, name) }
+
+    fn for_each_name(&self, f: &mut dyn FnMut(&str)) { self.keys().for_each(|x| f(x.borrow())) }
+}
+
+impl<A: ArgSource + ?Sized> ArgSource for &A {
+    type Arg = A::Arg;
+
+    fn len(&self) -> usize { A::len(self) }
+
+    fn get(&self, index: usize) -> Option<&A::Arg> { A::get(self, index) }
+
+    fn get_named(&self, name: &str) -> Option<&A::Arg> { A::get_named(self, name) }
+
+    fn for_each_name(&self, f: &mut dyn FnMut(&str)) { A::for_each_name(self, f) }
+}
+
+/// An iterator over the arguments of an [`ArgSource`], returned by [`ArgSource::iter`].
+#[derive(Debug)]
+pub struct ArgSourceIter<'a, A: ArgSource + ?Sized> {
+    source: &'a A,
+    index: usize,
+}
+
+impl<'a, A: ArgSource + ?Sized> Clone for ArgSourceIter<'a, A> {
+    fn clone(&self) -> Self { ArgSourceIter { source: self.source, index: self.index } }
+}
+
+impl<'a, A: ArgSource + ?Sized> Iterator for ArgSourceIter<'a, A> {
+    type Item = &'a A::Arg;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.source.len() { return None; }
+        let arg = self.source.get(self.index);
+        self.index = if arg.is_some() { self.index + 1 } else { usize::MAX };
+        arg
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.source.len().saturating_sub(self.index)))
+    }
+}
+
+impl<'a, A: ArgSource + ?Sized> FusedIterator for ArgSourceIter<'a, A> { }
+
+#[cfg(test)]
+mod tests {
+    use crate::ArgSource;
+    #[cfg(feature = "std")]
+    use crate::Arguments;
+
+    struct Sparse<'a> {
+        values: &'a [Option<u32>],
+    }
+
+    impl<'a> ArgSource for Sparse<'a> {
+        type Arg = u32;
+
+        fn len(&self) -> usize { self.values.len() }
+
+        fn get(&self, index: usize) -> Option<&u32> { self.values.get(index)?.as_ref() }
+    }
+
+    #[test]
+    fn iter_stops_at_missing() {
+        let source = Sparse { values: &[Some(1), Some(2), None, Some(4)] };
+        let mut iter = source.iter();
+        assert!(iter.by_ref().copied().eq([1, 2]));
+        assert!(iter.next().is_none());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn format_from_source() {
+        let source = Sparse { values: &[Some(1), Some(2)] };
+        assert_eq!(Arguments::new("{}-{}-{}", source.iter()).to_string(), "1-2-");
+        assert_eq!(Arguments::new("{}{}", vec!["a", "b"].iter()).to_string(), "ab");
+    }
+
+    #[test]
+    fn slices_and_arrays() {
+        let array = [1, 2];
+        let slice: &[i32] = &array;
+        assert_eq!(ArgSource::len(&array), 2);
+        assert_eq!(ArgSource::get(slice, 1), Some(&2));
+        assert_eq!(ArgSource::iter(&slice).count(), 2);
+        assert!(ArgSource::get(&array, 2).is_none());
+    }
+}
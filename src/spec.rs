@@ -0,0 +1,641 @@
+#[cfg(feature = "std")]
+use crate::Presentation;
+use crate::ParseErrorKind;
+use core::fmt::{self, Display, Write};
+
+/// The alignment of a [`Spec`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum Align {
+    /// The `<` alignment.
+    Left,
+    /// The `^` alignment.
+    Center,
+    /// The `>` alignment.
+    Right,
+    /// The `d` alignment, e.g. `{:d12.2}`, lining up the decimal points of numbers in a column.
+    /// A number is right-aligned as if it had `precision` fraction digits,
+    /// so `5`, `1.5`, and `-12.25` occupy the same positions in the column as `5.00`, `1.50`, and `-12.25`.
+    Decimal,
+}
+
+/// The sign flag of a [`Spec`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Sign {
+    /// The `+` flag.
+    Plus,
+    /// The `-` flag.
+    Minus,
+}
+
+/// A format spec, the part of a placeholder after the colon, e.g. `>8.3` in `{:>8.3}`.
+///
+/// Has the same syntax and meaning as in the [`format!`](std::format) macro,
+/// excluding formatting traits other than the exponent notation and `$`/`*` references:
+/// `[[fill]align][sign]['#']['0'][width]['.' precision][type]`, see [`Presentation`](crate::Presentation)
+/// for the supported types.
+/// [`Format`](crate::Format) also accepts the width and the precision given by arguments instead,
+/// e.g. `{value:>{width}.{1}}`, which are resolved when the template is rendered.
+/// The spec is delivered to the argument [`Display`] implementation through the [`Formatter`](fmt::Formatter),
+/// so [`width`](fmt::Formatter::width), [`precision`](fmt::Formatter::precision), and other flags
+/// are available to it.
+/// The only exception is a fill character other than space, which cannot be passed through the `Formatter`.
+/// In that case the argument is formatted several times to detect the padding and replace it with the fill,
+/// so the output is the same as of the `format!` macro, including the interaction of the fill with the `0` flag.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Spec {
+    fill: char,
+    align: Option<Align>,
+    sign: Option<Sign>,
+    alternate: bool,
+    zero: bool,
+    width: Option<usize>,
+    precision: Option<usize>,
+    #[cfg(feature = "std")]
+    presentation: Option<Presentation>,
+}
+
+impl Default for Spec {
+    fn default() -> Self {
+        Spec {
+            fill: ' ',
+            align: None,
+            sign: None,
+            alternate: false,
+            zero: false,
+            width: None,
+            precision: None,
+            #[cfg(feature = "std")]
+            presentation: None,
+        }
+    }
+}
+
+/// The largest width or precision, the limit of the [`format!`](std::format) machinery.
+pub(crate) const MAX_COUNT: usize = u16::MAX as usize;
+
+/// A width or precision given either literally or by an argument, e.g. `{width}`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) enum Count<'a> {
+    Value(usize),
+    Ref(&'a str),
+}
+
+fn parse_count(s: &str) -> Result<(Option<Count<'_>>, &str), ParseErrorKind> {
+    if let Some(rest) = s.strip_prefix('{') {
+        let end = rest.find('}').ok_or(ParseErrorKind::InvalidSpec)?;
+        return Ok((Some(Count::Ref(&rest[.. end])), &rest[end + 1 ..]));
+    }
+    let (n, s) = parse_number(s)?;
+    Ok((n.map(Count::Value), s))
+}
+
+fn parse_number(s: &str) -> Result<(Option<usize>, &str), ParseErrorKind> {
+    let len = s.bytes().position(|b| !b.is_ascii_digit()).unwrap_or(s.len());
+    if len == 0 { return Ok((None, s)); }
+    let mut n: usize = 0;
+    for b in s[.. len].bytes() {
+        n = n * 10 + usize::from(b - b'0');
+        if n > MAX_COUNT { return Err(ParseErrorKind::CountOverflow); }
+    }
+    Ok((Some(n), &s[len ..]))
+}
+
+impl Spec {
+    /// Parses a format spec, e.g. `>8.3`. Returns `None` if the spec is not valid.
+    pub fn parse(s: &str) -> Option<Spec> { Spec::parse_with(s, false) }
+
+    /// Parses a format spec, optionally skipping characters which are not a part of the spec syntax,
+    /// as well as flags in a wrong order.
+    pub(crate) fn parse_with(s: &str, ignore_unknown: bool) -> Option<Spec> {
+        match Spec::parse_counts(s, ignore_unknown).ok()? {
+            (spec, None | Some(Count::Value(_)), None | Some(Count::Value(_))) => Some(spec),
+            _ => None,
+        }
+    }
+
+    /// Same as [`parse_with`](Spec::parse_with), but also accepts the width and the precision
+    /// referring to arguments, e.g. `>{width}.{1}`, which are returned separately.
+    /// Reports a width or a precision above [`MAX_COUNT`] as [`CountOverflow`](ParseErrorKind::CountOverflow).
+    pub(crate) fn parse_counts(
+        s: &str,
+        ignore_unknown: bool
+    ) -> Result<(Spec, Option<Count<'_>>, Option<Count<'_>>), ParseErrorKind> {
+        fn skip(s: &str, ignore_unknown: bool) -> &str {
+            if !ignore_unknown { return s; }
+            s.trim_start_matches(|c: char| !matches!(c, '<' | '^' | '>' | '+' | '-' | '#' | '.' | '{' | '0' ..= '9'))
+        }
+        fn as_align(c: char) -> Option<Align> {
+            match c {
+                '<' => Some(Align::Left),
+                '^' => Some(Align::Center),
+                '>' => Some(Align::Right),
+                'd' => Some(Align::Decimal),
+                _ => None
+            }
+        }
+        let mut spec = Spec::default();
+        let mut chars = s.chars();
+        let mut s = match (chars.next(), chars.next().and_then(as_align)) {
+            (Some(fill), Some(align)) => {
+                spec.fill = fill;
+                spec.align = Some(align);
+                chars.as_str()
+            },
+            (Some(c), _) if as_align(c).is_some() => {
+                spec.align = as_align(c);
+                &s[1 ..]
+            },
+            _ => s,
+        };
+        s = skip(s, ignore_unknown);
+        if let Some(rest) = s.strip_prefix('+') {
+            spec.sign = Some(Sign::Plus);
+            s = rest;
+        } else if let Some(rest) = s.strip_prefix('-') {
+            spec.sign = Some(Sign::Minus);
+            s = rest;
+        }
+        s = skip(s, ignore_unknown);
+        if let Some(rest) = s.strip_prefix('#') {
+            spec.alternate = true;
+            s = rest;
+        }
+        s = skip(s, ignore_unknown);
+        if let Some(rest) = s.strip_prefix('0') {
+            spec.zero = true;
+            s = rest;
+        }
+        let (width, s) = parse_count(skip(s, ignore_unknown))?;
+        if let Some(Count::Value(width)) = width { spec.width = Some(width); }
+        let mut s = skip(s, ignore_unknown);
+        let mut precision = None;
+        if let Some(rest) = s.strip_prefix('.') {
+            let (count, rest) = parse_count(skip(rest, ignore_unknown))?;
+            if let Some(Count::Value(precision)) = count { spec.precision = Some(precision); }
+            if count.is_none() && !ignore_unknown { return Err(ParseErrorKind::InvalidSpec); }
+            precision = count;
+            s = rest;
+        }
+        #[cfg(feature = "std")]
+        if let Some(presentation) = Presentation::parse(s) {
+            spec.presentation = Some(presentation);
+            s = "";
+        }
+        if !s.is_empty() && !ignore_unknown { return Err(ParseErrorKind::InvalidSpec); }
+        Ok((spec, width, precision))
+    }
+
+    /// The fill character, space by default.
+    pub fn fill(&self) -> char { self.fill }
+
+    /// The alignment.
+    pub fn align(&self) -> Option<Align> { self.align }
+
+    /// The sign flag.
+    pub fn sign(&self) -> Option<Sign> { self.sign }
+
+    /// Whether the `#` flag is set.
+    pub fn alternate(&self) -> bool { self.alternate }
+
+    /// Whether the `0` flag is set.
+    pub fn zero(&self) -> bool { self.zero }
+
+    /// The minimal width.
+    pub fn width(&self) -> Option<usize> { self.width }
+
+    /// The precision.
+    ///
+    /// As in the [`format!`](std::format) macro, its meaning depends on the argument:
+    /// numbers use it as the number of fraction digits, while strings are truncated
+    /// to the specified number of chars (before padding is applied).
+    pub fn precision(&self) -> Option<usize> { self.precision }
+
+    /// The presentation type.
+    /// Unavailable in `no_std` environment.
+    #[cfg(feature = "std")]
+    pub fn presentation(&self) -> Option<Presentation> { self.presentation }
+
+    pub(crate) fn is_default(&self) -> bool { *self == Spec::default() }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn set_width(&mut self, width: Option<usize>) { self.width = width; }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn set_precision(&mut self, precision: Option<usize>) { self.precision = precision; }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn set_presentation(&mut self, presentation: Option<Presentation>) { self.presentation = presentation; }
+}
+
+impl Display for Spec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(align) = self.align {
+            if self.fill != ' ' { f.write_char(self.fill)?; }
+            f.write_char(match align { Align::Left => '<', Align::Center => '^', Align::Right => '>', Align::Decimal => 'd' })?;
+        }
+        match self.sign {
+            None => { },
+            Some(Sign::Plus) => f.write_char('+')?,
+            Some(Sign::Minus) => f.write_char('-')?,
+        }
+        if self.alternate { f.write_char('#')?; }
+        if self.zero { f.write_char('0')?; }
+        if let Some(width) = self.width { write!(f, "{}", width)?; }
+        if let Some(precision) = self.precision { write!(f, ".{}", precision)?; }
+        #[cfg(feature = "std")]
+        if let Some(presentation) = self.presentation { write!(f, "{}", presentation)?; }
+        Ok(())
+    }
+}
+
+/// Parses the formatted argument as a width or a precision, clamping it to [`MAX_COUNT`].
+#[cfg(feature = "std")]
+struct CountWriter(Option<usize>);
+
+#[cfg(feature = "std")]
+impl Write for CountWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for b in s.bytes() {
+            if !b.is_ascii_digit() { return Err(fmt::Error); }
+            self.0 = Some((self.0.unwrap_or(0) * 10 + usize::from(b - b'0')).min(MAX_COUNT));
+        }
+        Ok(())
+    }
+}
+
+/// Returns the value of an argument used as a width or a precision,
+/// or `None` if it is not a non-negative integer. Values above [`MAX_COUNT`] are clamped to it,
+/// because the standard formatting machinery does not support them.
+#[cfg(feature = "std")]
+pub(crate) fn count_arg<T: Display + ?Sized>(arg: &T) -> Option<usize> {
+    let mut writer = CountWriter(None);
+    write!(writer, "{}", arg).ok()?;
+    writer.0
+}
+
+/// Serializes the spec as its text, e.g. `">8.3"`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Spec {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Spec {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct SpecVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for SpecVisitor {
+            type Value = Spec;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result { write!(f, "a format spec") }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Spec, E> {
+                Spec::parse(v).ok_or_else(|| E::invalid_value(serde::de::Unexpected::Str(v), &self))
+            }
+        }
+
+        deserializer.deserialize_str(SpecVisitor)
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Align {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        u.choose(&[Align::Left, Align::Center, Align::Right, Align::Decimal]).copied()
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Sign {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(if u.arbitrary()? { Sign::Plus } else { Sign::Minus })
+    }
+}
+
+/// Generates a spec which can be written into a placeholder and parsed back,
+/// i.e. `Spec::parse(&spec.to_string())` returns the same spec.
+/// The width and the precision are less than 256, so that rendering stays cheap.
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Spec {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let align: Option<Align> = u.arbitrary()?;
+        let fill = if align.is_some() && u.ratio(1, 4)? {
+            Some(u.arbitrary::<char>()?).filter(|&c| c != '{' && c != '}').unwrap_or('*')
+        } else {
+            ' '
+        };
+        let zero = u.arbitrary()?;
+        let width = u.arbitrary::<Option<u8>>()?.map(usize::from).filter(|&x| zero || x != 0);
+        Ok(Spec {
+            fill,
+            align,
+            sign: u.arbitrary()?,
+            alternate: u.arbitrary()?,
+            zero,
+            width,
+            precision: u.arbitrary::<Option<u8>>()?.map(usize::from),
+            presentation: u.arbitrary()?,
+        })
+    }
+}
+
+struct CharCounter(usize);
+
+/// Counts the characters of a number and of its fraction part, including the decimal point.
+struct DecimalCounter {
+    len: usize,
+    fraction: Option<usize>,
+}
+
+impl Write for DecimalCounter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            self.len += 1;
+            match &mut self.fraction {
+                Some(fraction) => *fraction += 1,
+                None if c == '.' => self.fraction = Some(1),
+                None => { },
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Write for CharCounter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.chars().count();
+        Ok(())
+    }
+}
+
+macro_rules! write_with_flags {
+    ($f:expr, $arg:expr, $width:expr, $precision:expr, $($flags:literal),*) => {
+        match ($width, $precision) {
+            (None, None) => write!($f, concat!("{:", $($flags,)* "}"), $arg),
+            (Some(w), None) => write!($f, concat!("{:", $($flags,)* "w$}"), $arg, w = w),
+            (None, Some(p)) => write!($f, concat!("{:", $($flags,)* ".p$}"), $arg, p = p),
+            (Some(w), Some(p)) => write!($f, concat!("{:", $($flags,)* "w$.p$}"), $arg, w = w, p = p),
+        }
+    };
+}
+
+macro_rules! write_with_zero {
+    ($f:expr, $arg:expr, $spec:expr, $width:expr, $($flags:literal),*) => {
+        if $spec.zero {
+            write_with_flags!($f, $arg, $width, $spec.precision, $($flags,)* "0")
+        } else {
+            write_with_flags!($f, $arg, $width, $spec.precision, $($flags),*)
+        }
+    };
+}
+
+macro_rules! write_with_alternate {
+    ($f:expr, $arg:expr, $spec:expr, $width:expr, $($flags:literal),*) => {
+        if $spec.alternate {
+            write_with_zero!($f, $arg, $spec, $width, $($flags,)* "#")
+        } else {
+            write_with_zero!($f, $arg, $spec, $width, $($flags),*)
+        }
+    };
+}
+
+macro_rules! write_with_sign {
+    ($f:expr, $arg:expr, $spec:expr, $width:expr, $($flags:literal),*) => {
+        match $spec.sign {
+            None => write_with_alternate!($f, $arg, $spec, $width, $($flags),*),
+            Some(Sign::Plus) => write_with_alternate!($f, $arg, $spec, $width, $($flags,)* "+"),
+            Some(Sign::Minus) => write_with_alternate!($f, $arg, $spec, $width, $($flags,)* "-"),
+        }
+    };
+}
+
+fn write_with_spec<T: Display + ?Sized>(
+    f: &mut dyn Write,
+    arg: &T,
+    spec: &Spec,
+    width: Option<usize>
+) -> fmt::Result {
+    match spec.align {
+        None => write_with_sign!(f, arg, spec, width, ),
+        Some(Align::Left) => write_with_sign!(f, arg, spec, width, "<"),
+        Some(Align::Center) => write_with_sign!(f, arg, spec, width, "^"),
+        Some(Align::Right | Align::Decimal) => write_with_sign!(f, arg, spec, width, ">"),
+    }
+}
+
+/// Checks whether a padded output consists of the unpadded one with `left` and `right` spaces around it.
+struct PaddingChecker {
+    left: usize,
+    len: usize,
+    leading_ok: bool,
+    trailing_spaces: usize,
+}
+
+impl Write for PaddingChecker {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            if self.len < self.left && c != ' ' { self.leading_ok = false; }
+            self.trailing_spaces = if c == ' ' { self.trailing_spaces + 1 } else { 0 };
+            self.len += 1;
+        }
+        Ok(())
+    }
+}
+
+/// Formats an argument according to the spec.
+///
+/// A fill other than space cannot be passed through the `Formatter`, so in that case the argument
+/// is formatted with spaces first. If the result is the unpadded output surrounded by spaces
+/// exactly as the alignment implies, the spaces are replaced with the fill. Otherwise
+/// the argument handles the width on its own (e.g. zero-padded number), and the result is used as is.
+pub(crate) fn fmt_arg<T: Display + ?Sized>(arg: &T, spec: &Spec, f: &mut fmt::Formatter) -> fmt::Result {
+    if spec.is_default() { return arg.fmt(f); }
+    #[cfg(feature = "std")]
+    if let Some(presentation) = spec.presentation { return presentation.fmt_arg(arg, spec, f); }
+    if spec.align == Some(Align::Decimal) { return fmt_decimal(arg, spec, f); }
+    let (Some(width), Some(align)) = (spec.width, spec.align) else {
+        return write_with_spec(f, arg, spec, spec.width);
+    };
+    if spec.fill == ' ' { return write_with_spec(f, arg, spec, spec.width); }
+    let mut counter = CharCounter(0);
+    write_with_spec(&mut counter, arg, spec, None)?;
+    let padding = width.saturating_sub(counter.0);
+    let (left, right) = match align {
+        Align::Left => (0, padding),
+        Align::Center => (padding / 2, (padding + 1) / 2),
+        Align::Right | Align::Decimal => (padding, 0),
+    };
+    let mut checker = PaddingChecker { left, len: 0, leading_ok: true, trailing_spaces: 0 };
+    write_with_spec(&mut checker, arg, spec, spec.width)?;
+    let padded_with_spaces = padding != 0 && checker.len == width && checker.leading_ok && checker.trailing_spaces >= right;
+    if !padded_with_spaces { return write_with_spec(f, arg, spec, spec.width); }
+    for _ in 0 .. left { f.write_char(spec.fill)?; }
+    write_with_spec(f, arg, spec, None)?;
+    for _ in 0 .. right { f.write_char(spec.fill)?; }
+    Ok(())
+}
+
+/// Formats an argument with the [`Decimal`](Align::Decimal) alignment: pads the right side
+/// up to `precision` fraction digits and the decimal point, and the left side up to the width.
+fn fmt_decimal<T: Display + ?Sized>(arg: &T, spec: &Spec, f: &mut fmt::Formatter) -> fmt::Result {
+    let mut plain = *spec;
+    plain.align = None;
+    plain.fill = ' ';
+    plain.width = None;
+    let mut counter = DecimalCounter { len: 0, fraction: None };
+    write_with_spec(&mut counter, arg, &plain, None)?;
+    let column = spec.precision.map_or(0, |p| if p == 0 { 0 } else { p + 1 });
+    let right = column.saturating_sub(counter.fraction.unwrap_or(0));
+    let left = spec.width.unwrap_or(0).saturating_sub(counter.len + right);
+    for _ in 0 .. left { f.write_char(spec.fill)?; }
+    write_with_spec(f, arg, &plain, None)?;
+    for _ in 0 .. right { f.write_char(spec.fill)?; }
+    Ok(())
+}
+
+/// An argument combined with its spec.
+#[cfg(feature = "std")]
+pub(crate) struct WithSpec<'a, T: Display + ?Sized> {
+    pub(crate) arg: &'a T,
+    pub(crate) spec: &'a Spec,
+}
+
+#[cfg(feature = "std")]
+impl<'a, T: Display + ?Sized> Display for WithSpec<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { fmt_arg(self.arg, self.spec, f) }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Align, ParseErrorKind, Sign, Spec};
+    use super::Count;
+
+    #[test]
+    fn parse() {
+        let spec = Spec::parse("*^+#012.3").unwrap();
+        assert_eq!(spec.fill(), '*');
+        assert_eq!(spec.align(), Some(Align::Center));
+        assert_eq!(spec.sign(), Some(Sign::Plus));
+        assert!(spec.alternate());
+        assert!(spec.zero());
+        assert_eq!(spec.width(), Some(12));
+        assert_eq!(spec.precision(), Some(3));
+        assert_eq!(Spec::parse(""), Some(Spec::default()));
+        assert_eq!(Spec::parse("0").unwrap().width(), None);
+        assert_eq!(Spec::parse("00").unwrap().width(), Some(0));
+        assert_eq!(Spec::parse("><").unwrap().fill(), '>');
+        assert_eq!(Spec::parse("{w}"), None);
+        assert_eq!(Spec::parse("{<3").unwrap().fill(), '{');
+        let (spec, width, precision) = Spec::parse_counts("-0{w}.{1}", false).unwrap();
+        assert_eq!((spec.sign(), spec.zero(), width, precision), (Some(Sign::Minus), true, Some(Count::Ref("w")), Some(Count::Ref("1"))));
+        assert_eq!(Spec::parse("я>5").unwrap().fill(), 'я');
+        assert!(Spec::parse(".").is_none());
+        assert!(Spec::parse("5x").is_none());
+        assert!(Spec::parse("99999999999999999999999").is_none());
+        assert_eq!(Spec::parse("65535.0065535").map(|x| (x.width(), x.precision())), Some((Some(65535), Some(65535))));
+        assert_eq!(Spec::parse_counts("65536", false), Err(ParseErrorKind::CountOverflow));
+        assert_eq!(Spec::parse_counts(".65536", false), Err(ParseErrorKind::CountOverflow));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn display() {
+        for spec in ["*^+#012.3", "<5", "-", "#", ".0", "x>3", "+.3e", "08E"] {
+            assert_eq!(format!("{}", Spec::parse(spec).unwrap()), spec);
+        }
+        assert_eq!(format!("{}", Spec::parse(" >3").unwrap()), ">3");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn conformance() {
+        use crate::Arguments;
+        use core::fmt::{self, Display};
+
+        struct Unpadded;
+
+        impl Display for Unpadded {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { f.write_str("raw") }
+        }
+
+        macro_rules! check {
+            ($value:expr, [$($spec:literal),*]) => {
+                $(
+                    assert_eq!(
+                        Arguments::new(concat!("{:", $spec, "}"), &[$value]).to_string(),
+                        format!(concat!("{:", $spec, "}"), $value),
+                        "{{:{}}} with {}", $spec, stringify!($value)
+                    );
+                )*
+            };
+        }
+
+        macro_rules! conformance {
+            ([$($value:expr),*], $specs:tt) => {
+                $(check!($value, $specs);)*
+            };
+        }
+
+        conformance!([5, -5, 0, u64::MAX, 3.25f64, -2.5f64, f64::NAN, -0.0f64, "abc", "", 'я', "абв", true, Unpadded], [
+            "", "5", "<5", "^5", ">5", "*<5", "*^5", "*>5", "*^8", "я>6", "0<2",
+            "05", "+05", "-05", "+", "#", "#08", "+#010", "*>05", "*<05", "*^08", "*^+08", "<05", "^+07",
+            ".0", ".1", ".2", "8.2", "*^8.2", "*>+09.1", "x<3.1", "+.3", "*<+010.3", "00", "*>0"
+        ]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn string_precision() {
+        use crate::Arguments;
+        use std::borrow::Cow;
+
+        assert_eq!(Arguments::new("{:.3}|{:.3}|{:.3}", ["abcdef", "ab", ""]).to_string(), "abc|ab|");
+        assert_eq!(Arguments::new("{:.2}", &[String::from("абвг")]).to_string(), "аб");
+        assert_eq!(Arguments::new("{:.1}", &[Box::<str>::from("xyz")]).to_string(), "x");
+        assert_eq!(Arguments::new("{:.0}", &[Cow::Borrowed("xyz")]).to_string(), "");
+        assert_eq!(Arguments::new("{:>6.2}|{:*^6.2}|{:-<4.3}", ["abcdef"; 3]).to_string(), "    ab|**ab**|abc-");
+        assert_eq!(Arguments::new("{:.1}", &['x']).to_string(), format!("{:.1}", 'x'));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn decimal_align() {
+        use crate::{Arguments, Format};
+        use core::fmt::Display;
+
+        let column: [&dyn Display; 5] = [&5, &1.5, &-12.25, &1234.5678, &"-"];
+        let rows: Vec<String> = column.iter().map(|x| Arguments::new("[{:d9.2}]", &[x]).to_string()).collect();
+        assert_eq!(rows, ["[     5   ]", "[     1.50]", "[   -12.25]", "[  1234.57]", "[     -   ]"]);
+        assert_eq!(Arguments::new("{:*d6.1}|{:d.1}|{:d4}|{:+d8.3}", &[7.0, 2.0, 3.5, 1.0]).to_string(), "***7.0|2.0| 3.5|+++1.000");
+        assert_eq!(Arguments::new("{:*d6.1}", &[7]).to_string(), "***7**");
+        let format = Format::parse("{x:d8.2}|").unwrap();
+        assert_eq!(format.render_from(std::collections::HashMap::from([("x", 42)])), "   42   |");
+        assert_eq!(Spec::parse("xd12.2").unwrap().align(), Some(Align::Decimal));
+        assert_eq!(Spec::parse("d12.2").unwrap().to_string(), "d12.2");
+        assert_eq!(Spec::parse("*d4").unwrap().to_string(), "*d4");
+    }
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn arbitrary_round_trip() {
+        use arbitrary::{Arbitrary, Unstructured};
+
+        let mut seed = 1u64;
+        let data: Vec<u8> = (0 .. 1 << 16).map(|_| {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (seed >> 56) as u8
+        }).collect();
+        let mut u = Unstructured::new(&data);
+        let mut presentations = 0;
+        while !u.is_empty() {
+            let spec = Spec::arbitrary(&mut u).unwrap();
+            let text = spec.to_string();
+            assert_eq!(Spec::parse(&text), Some(spec), "{}", text);
+            if spec.presentation().is_some() { presentations += 1; }
+        }
+        assert!(presentations > 0);
+    }
+}
@@ -0,0 +1,214 @@
+use crate::{Catalog, Format, ParseError};
+use core::fmt::{self, Display};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+/// An error found while loading a single template file in [`TemplateStore::reload`].
+#[derive(Debug)]
+pub enum LoadError {
+    /// The file could not be read.
+    Io(PathBuf, io::Error),
+    /// The file content is not a valid format string.
+    Parse(PathBuf, ParseError),
+}
+
+impl LoadError {
+    /// The path of the file which failed to load.
+    pub fn path(&self) -> &Path {
+        match self {
+            LoadError::Io(path, _) | LoadError::Parse(path, _) => path,
+        }
+    }
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            LoadError::Parse(path, e) => write!(f, "{}: {}", path.display(), e),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadError::Io(_, e) => Some(e),
+            LoadError::Parse(_, e) => Some(e),
+        }
+    }
+}
+
+/// A [`Catalog`] loaded from a directory, which can be reloaded while being used.
+///
+/// Every file directly in the directory is a message which does not belong to any locale,
+/// and every subdirectory is a locale containing its messages. The message key is the file name
+/// without extension, so `greeting.txt` and `de/greeting.txt` both define the `greeting` message.
+///
+/// Unavailable in `no_std` environment.
+/// # Examples:
+/// ```rust
+/// # let dir = std::env::temp_dir().join(format!("dyn-fmt-doctest-store-{}", std::process::id()));
+/// # std::fs::create_dir_all(&dir).unwrap();
+/// use dyn_fmt::TemplateStore;
+/// std::fs::write(dir.join("greeting.txt"), "Hello, {}!").unwrap();
+/// let store = TemplateStore::new(&dir);
+/// assert!(store.reload().unwrap().is_empty());
+/// assert_eq!(store.catalog().format("greeting", &["Anna"]).unwrap(), "Hello, Anna!");
+/// # std::fs::remove_dir_all(&dir).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct TemplateStore {
+    dir: PathBuf,
+    fallbacks: RwLock<Vec<(String, String)>>,
+    catalog: RwLock<Arc<Catalog>>,
+}
+
+impl TemplateStore {
+    /// Creates an empty store for the specified directory. Call [`reload`](TemplateStore::reload) to load it.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        TemplateStore { dir: dir.into(), fallbacks: RwLock::new(Vec::new()), catalog: RwLock::new(Arc::new(Catalog::new())) }
+    }
+
+    /// The directory templates are loaded from.
+    pub fn dir(&self) -> &Path { &self.dir }
+
+    /// Sets a locale fallback, see [`Catalog::set_fallback`]. Takes effect immediately and survives reloads.
+    pub fn set_fallback(&self, locale: impl Into<String>, fallback: impl Into<String>) {
+        let (locale, fallback) = (locale.into(), fallback.into());
+        let mut catalog = self.catalog.write().unwrap();
+        let mut updated = Catalog::clone(&catalog);
+        updated.set_fallback(locale.clone(), fallback.clone());
+        *catalog = Arc::new(updated);
+        self.fallbacks.write().unwrap().push((locale, fallback));
+    }
+
+    /// Returns the currently loaded templates.
+    /// The returned snapshot is not affected by subsequent reloads.
+    pub fn catalog(&self) -> Arc<Catalog> {
+        self.catalog.read().unwrap().clone()
+    }
+
+    /// Reads all templates from the directory and replaces the loaded ones at once.
+    ///
+    /// A file which cannot be read or parsed does not prevent other files from being loaded:
+    /// the previously loaded version of its message, if any, is kept, and the failure is returned in the list.
+    /// An error is returned only if the directory itself cannot be listed, in which case nothing is replaced.
+    pub fn reload(&self) -> io::Result<Vec<LoadError>> {
+        let files = read_templates(&self.dir)?;
+        let mut catalog = self.catalog.write().unwrap();
+        let mut updated = Catalog::new();
+        let mut errors = Vec::new();
+        for (locale, key, path, text) in files {
+            let format = match text {
+                Err(e) => Err(LoadError::Io(path, e)),
+                Ok(s) => Format::parse(s).map_err(|e| LoadError::Parse(path, e)),
+            };
+            let format = match format {
+                Ok(format) => format,
+                Err(e) => {
+                    errors.push(e);
+                    let Some(old) = catalog.get_exact(locale.as_deref(), &key) else { continue; };
+                    old.clone()
+                },
+            };
+            match locale {
+                None => updated.insert(key, format),
+                Some(locale) => updated.insert_localized(locale, key, format),
+            };
+        }
+        for (locale, fallback) in self.fallbacks.read().unwrap().iter() {
+            updated.set_fallback(locale.clone(), fallback.clone());
+        }
+        *catalog = Arc::new(updated);
+        Ok(errors)
+    }
+}
+
+/// The locale, the message key, the path, and the content of a template file.
+pub(crate) type TemplateFile = (Option<String>, String, PathBuf, io::Result<String>);
+
+/// Lists the template files in the directory and its locale subdirectories, and reads them.
+pub(crate) fn read_templates(dir: &Path) -> io::Result<Vec<TemplateFile>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            let Some(locale) = entry.file_name().to_str().map(String::from) else { continue; };
+            for entry in fs::read_dir(entry.path())? {
+                let entry = entry?;
+                if !entry.file_type()?.is_dir() {
+                    files.push((Some(locale.clone()), entry.path()));
+                }
+            }
+        } else {
+            files.push((None, entry.path()));
+        }
+    }
+    Ok(files.into_iter().filter_map(|(locale, path)| {
+        let key = path.file_stem()?.to_str()?.to_owned();
+        let text = fs::read_to_string(&path);
+        Some((locale, key, path, text))
+    }).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{LoadError, TemplateStore};
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("dyn-fmt-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn reload() {
+        let dir = temp_dir("reload");
+        fs::write(dir.join("a.txt"), "a{}").unwrap();
+        fs::write(dir.join("b.txt"), "b{}").unwrap();
+        fs::create_dir(dir.join("de")).unwrap();
+        fs::write(dir.join("de").join("a.txt"), "de-a{}").unwrap();
+        let store = TemplateStore::new(&dir);
+        store.set_fallback("de-AT", "de");
+        assert!(store.reload().unwrap().is_empty());
+        let old = store.catalog();
+        assert_eq!(old.format_localized("de-AT", "a", &[1]).unwrap(), "de-a1");
+        assert_eq!(old.format_localized("de-AT", "b", &[1]).unwrap(), "b1");
+        fs::write(dir.join("a.txt"), "A{}").unwrap();
+        fs::remove_file(dir.join("b.txt")).unwrap();
+        assert!(store.reload().unwrap().is_empty());
+        assert_eq!(store.catalog().format("a", &[1]).unwrap(), "A1");
+        assert!(store.catalog().format("b", &[1]).is_none());
+        assert_eq!(old.format("b", &[1]).unwrap(), "b1");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reload_keeps_failed() {
+        let dir = temp_dir("reload_keeps_failed");
+        fs::write(dir.join("a.txt"), "a{}").unwrap();
+        let store = TemplateStore::new(&dir);
+        assert!(store.reload().unwrap().is_empty());
+        fs::write(dir.join("a.txt"), "a{# oops").unwrap();
+        fs::write(dir.join("b.txt"), "b{# oops").unwrap();
+        let errors = store.reload().unwrap();
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().all(|e| matches!(e, LoadError::Parse(..))));
+        assert_eq!(store.catalog().format("a", &[1]).unwrap(), "a1");
+        assert!(store.catalog().format("b", &[1]).is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn missing_dir() {
+        let dir = temp_dir("missing_dir");
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(TemplateStore::new(&dir).reload().is_err());
+    }
+}
@@ -0,0 +1,529 @@
+use crate::{ArgRef, Counts, ParseOptions};
+use crate::format::Positional;
+use crate::parse::{Numbering, ParseError, ParseErrorKind, TagMatch, Token, Tokens, find, match_tag, unescape};
+use crate::spec::{Spec, WithSpec, count_arg};
+use core::fmt::Display;
+use core::mem::take;
+use core::ops::Range;
+use core::str;
+use std::io::{self, Read, Write};
+
+/// A piece of a format string produced by [`Parser`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Segment<'a> {
+    /// Literal text to be output as is.
+    Literal(&'a str),
+    /// A placeholder to be replaced by the argument formatted according to the spec,
+    /// with the width and the precision given by the arguments referred to by [`Counts`], if any.
+    Arg(ArgRef<'a>, Spec, Counts<'a>),
+}
+
+/// The maximum length in bytes of a placeholder or a tag the parser waits for the end of.
+const MAX_PLACEHOLDER_LEN: usize = 4096;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum State {
+    Text { verbatim: bool },
+    Comment { start: usize },
+    Raw { start: usize },
+}
+
+/// An incremental format string parser, accepting the format string in chunks.
+///
+/// Only the unfinished tail of a chunk (such as a brace at its very end) is kept between calls,
+/// so arbitrarily large format strings can be processed in bounded memory.
+/// Placeholders, comments and raw blocks may span chunk boundaries.
+/// A placeholder not terminated within 4096 bytes is reported as unterminated,
+/// and a `{%` tag not finished within that length is taken as literal text.
+/// The format string is split into the same tokens as by [`Format::parse`](crate::Format::parse),
+/// but the parser does not keep the parsed template,
+/// it passes [`Segment`]s to the provided callback as soon as they are recognized.
+///
+/// Unavailable in `no_std` environment.
+/// # Examples:
+/// ```rust
+/// use dyn_fmt::{Parser, ParseError, Segment};
+/// let mut parser = Parser::new();
+/// let mut res = String::new();
+/// let mut sink = |segment: Segment| {
+///     match segment {
+///         Segment::Literal(s) => res.push_str(s),
+///         Segment::Arg(..) => res.push_str("<arg>"),
+///     }
+///     Ok::<(), ParseError>(())
+/// };
+/// parser.feed("a{", &mut sink).unwrap();
+/// parser.feed("}b{# comm", &mut sink).unwrap();
+/// parser.feed("ent #}c", &mut sink).unwrap();
+/// parser.finish(&mut sink).unwrap();
+/// assert_eq!(res, "a<arg>bc");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Parser {
+    state: State,
+    pending: String,
+    offset: usize,
+    options: ParseOptions,
+    numbering: Numbering,
+}
+
+impl Default for Parser {
+    fn default() -> Self { Self::new() }
+}
+
+impl Parser {
+    /// Creates a parser expecting the first chunk of a format string.
+    pub fn new() -> Self { Parser::with_options(ParseOptions::new()) }
+
+    /// Creates a parser with the specified strictness.
+    pub fn with_options(options: ParseOptions) -> Self {
+        Parser {
+            state: State::Text { verbatim: false },
+            pending: String::new(),
+            offset: 0,
+            options,
+            numbering: Numbering::default(),
+        }
+    }
+
+    /// Parses the next chunk of the format string, reporting an invalid placeholder.
+    /// The parser should not be used after an error.
+    pub fn feed<E: From<ParseError>>(&mut self, chunk: &str, sink: impl FnMut(Segment) -> Result<(), E>) -> Result<(), E> {
+        if self.pending.is_empty() {
+            self.run(chunk, false, sink)
+        } else {
+            let mut input = take(&mut self.pending);
+            input.push_str(chunk);
+            self.run(&input, false, sink)
+        }
+    }
+
+    /// Completes parsing, reporting an unterminated placeholder, comment, or raw block.
+    pub fn finish<E: From<ParseError>>(mut self, sink: impl FnMut(Segment) -> Result<(), E>) -> Result<(), E> {
+        let input = take(&mut self.pending);
+        self.run(&input, true, sink)?;
+        let end = self.offset;
+        match self.state {
+            State::Text { .. } => Ok(()),
+            State::Comment { start } => Err(ParseError::new(ParseErrorKind::UnterminatedComment, start .. end).into()),
+            State::Raw { start } => Err(ParseError::new(ParseErrorKind::UnterminatedRaw, start .. end).into()),
+        }
+    }
+
+    fn run<E: From<ParseError>>(&mut self, input: &str, eof: bool, mut sink: impl FnMut(Segment) -> Result<(), E>) -> Result<(), E> {
+        let bytes = input.as_bytes();
+        let mut pos = 0;
+        'run: while pos < bytes.len() {
+            match self.state {
+                State::Text { verbatim } => {
+                    let base = pos;
+                    let mut tokens = Tokens::explicit(&input[base ..], self.options).verbatim(verbatim);
+                    loop {
+                        let (next, verbatim) = tokens.state();
+                        pos = base + next;
+                        self.state = State::Text { verbatim };
+                        let Some((span, token)) = tokens.next() else {
+                            pos = bytes.len();
+                            break;
+                        };
+                        let span = base + span.start .. base + span.end;
+                        if !eof && incomplete(input, &span, &token) { break 'run; }
+                        match token {
+                            Token::Literal => sink(Segment::Literal(&input[span]))?,
+                            Token::Escaped => {
+                                let mut text = String::new();
+                                unescape(&input[span], &mut text);
+                                sink(Segment::Literal(&text))?;
+                            },
+                            Token::Arg(arg, spec, counts) => {
+                                for arg in [Some(arg), counts.width, counts.precision].iter().flatten() {
+                                    if let Err(kind) = self.numbering.check(arg, &self.options) {
+                                        return Err(ParseError::new(kind, self.offset + span.start .. self.offset + span.end).into());
+                                    }
+                                }
+                                sink(Segment::Arg(arg, spec, counts))?;
+                            },
+                            Token::Comment | Token::StrayBrace => { },
+                            Token::Error(ParseErrorKind::UnterminatedComment) if !eof => {
+                                self.state = State::Comment { start: self.offset + span.start };
+                                pos = span.start + 2;
+                                continue 'run;
+                            },
+                            Token::Error(ParseErrorKind::UnterminatedRaw) if !eof => {
+                                let TagMatch::Matched(len) = match_tag(&bytes[span.start ..], "raw") else { unreachable!() };
+                                self.state = State::Raw { start: self.offset + span.start };
+                                pos = span.start + len;
+                                continue 'run;
+                            },
+                            Token::Error(kind) => {
+                                return Err(ParseError::new(kind, self.offset + span.start .. self.offset + span.end).into());
+                            },
+                        }
+                    }
+                },
+                State::Comment { .. } => {
+                    if let Some(close) = find(bytes, pos, b'#', |b| b.starts_with(b"#}")) {
+                        self.state = State::Text { verbatim: false };
+                        pos = close + 2;
+                    } else if bytes[bytes.len() - 1] == b'#' && !eof {
+                        pos = bytes.len() - 1;
+                        break;
+                    } else {
+                        pos = bytes.len();
+                    }
+                },
+                State::Raw { .. } => {
+                    let close = find(bytes, pos, b'{', |b| match match_tag(b, "endraw") {
+                        TagMatch::Matched(_) => true,
+                        TagMatch::Incomplete => !eof && b.len() <= MAX_PLACEHOLDER_LEN,
+                        TagMatch::Mismatched => false,
+                    });
+                    let end = close.unwrap_or(bytes.len());
+                    if end != pos { sink(Segment::Literal(&input[pos .. end]))?; }
+                    pos = end;
+                    let TagMatch::Matched(len) = match_tag(&bytes[pos ..], "endraw") else { break; };
+                    self.state = State::Text { verbatim: false };
+                    pos += len;
+                },
+            }
+        }
+        self.offset += pos;
+        self.pending.push_str(&input[pos ..]);
+        Ok(())
+    }
+}
+
+/// Checks whether the token ending the available input can change with the next chunk,
+/// e.g. a stray brace can turn out to be the first of two escaped braces.
+/// A token longer than [`MAX_PLACEHOLDER_LEN`] is complete, so the input kept between chunks is bounded.
+fn incomplete(input: &str, span: &Range<usize>, token: &Token) -> bool {
+    if input.len() - span.start > MAX_PLACEHOLDER_LEN { return false; }
+    match token {
+        Token::Literal | Token::Escaped | Token::Arg(..) | Token::Comment => false,
+        Token::Error(ParseErrorKind::UnterminatedComment | ParseErrorKind::UnterminatedRaw) => false,
+        _ => span.end == input.len() || match_tag(&input.as_bytes()[span.start ..], "raw") == TagMatch::Incomplete,
+    }
+}
+
+/// Reads a format string from `input` and writes it to `output`, replacing placeholders
+/// using provided parameters in the order given.
+///
+/// The format string is processed in chunks as it is read, so memory usage does not depend on its size.
+/// Every literal fragment and argument is written to `output` separately,
+/// so it is usually worth wrapping it in a [`BufWriter`](std::io::BufWriter).
+/// An invalid UTF-8 or format string results in an error of [`InvalidData`](io::ErrorKind::InvalidData) kind.
+///
+/// Unavailable in `no_std` environment.
+/// # Examples:
+/// ```rust
+/// let mut output = Vec::new();
+/// dyn_fmt::format_stream("{}a{}b{}c".as_bytes(), &mut output, &[1, 2, 3]).unwrap();
+/// assert_eq!(output, b"1a2b3c");
+/// ```
+pub fn format_stream<'a, T: Display + ?Sized + 'a>(
+    mut input: impl Read,
+    mut output: impl Write,
+    args: impl IntoIterator<Item=&'a T> + Clone
+) -> io::Result<()> {
+    let mut args = Positional::new(args);
+    let mut next = 0;
+    let mut parser = Parser::new();
+    let mut sink = |segment: Segment| {
+        let (arg, mut spec, counts) = match segment {
+            Segment::Literal(s) => return output.write_all(s.as_bytes()),
+            Segment::Arg(arg, spec, counts) => (arg, spec, counts),
+        };
+        let index = match arg {
+            ArgRef::Next => {
+                next += 1;
+                next - 1
+            },
+            ArgRef::Index(index) => index,
+            ArgRef::Name(_) => return Ok(()),
+        };
+        let mut count = |arg: Option<ArgRef>| match arg {
+            Some(ArgRef::Index(index)) => args.get(index).and_then(count_arg),
+            _ => None,
+        };
+        if let Some(width) = count(counts.width()) { spec.set_width(Some(width)); }
+        if let Some(precision) = count(counts.precision()) { spec.set_precision(Some(precision)); }
+        match args.get(index) {
+            Some(arg) => write!(output, "{}", WithSpec { arg, spec: &spec }),
+            None => Ok(()),
+        }
+    };
+    let mut buf = [0; 8192];
+    let mut len = 0;
+    loop {
+        let read = match input.read(&mut buf[len ..]) {
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if read == 0 { break; }
+        len += read;
+        let valid = match str::from_utf8(&buf[.. len]) {
+            Ok(_) => len,
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+        };
+        parser.feed(str::from_utf8(&buf[.. valid]).unwrap(), &mut sink)?;
+        buf.copy_within(valid .. len, 0);
+        len -= valid;
+    }
+    if len != 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "stream did not contain valid UTF-8"));
+    }
+    parser.finish(&mut sink)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ArgRef, Format, ParseError, ParseErrorKind, ParseOptions, Parser, Segment, format_stream};
+    use super::MAX_PLACEHOLDER_LEN;
+    use crate::spec::WithSpec;
+    use core::fmt::Write;
+    use core::mem::take;
+    use std::io::{self, Read};
+
+    fn parse(chunks: &[&str]) -> Result<String, ParseError> {
+        parse_with(chunks, ParseOptions::new())
+    }
+
+    fn parse_with(chunks: &[&str], options: ParseOptions) -> Result<String, ParseError> {
+        let mut res = String::new();
+        let mut sink = |segment: Segment| {
+            match segment {
+                Segment::Literal(s) => res.push_str(s),
+                Segment::Arg(ArgRef::Name(_), ..) => { },
+                Segment::Arg(_, spec, _) => write!(res, "{}", WithSpec { arg: "$", spec: &spec }).unwrap(),
+            }
+            Ok::<(), ParseError>(())
+        };
+        let mut parser = Parser::with_options(options);
+        for chunk in chunks {
+            parser.feed(chunk, &mut sink)?;
+        }
+        parser.finish(&mut sink)?;
+        Ok(res)
+    }
+
+    #[test]
+    fn matches_format_at_any_split() {
+        let templates = [
+            "{}a{}b{}c", "{{}}{}", "{{}}x{{}{}}y{", "{{{}}}x{y}", "{{{}}}x{{}", "abcd{}абвгд{}{}",
+            "{# x #}a{}b{##}{}{#}#}", "{% raw %}{}{# #}{% endraw %}{}", "{%raw%}{%  endraw   %}",
+            "{% endraw %}{% rax %}", "{%", "}", "{", "{% raw %}{% endra{% endraw %}", "{{# #}}",
+            "{:>5}{:*^+#09.3}{:}", "{{:5}}", "{1}{x:>3}{}{0:<2}{имя}", "{ x} {-}", "{:>{1}}{0:.{x}}{:{0}.{1}}",
+        ];
+        for template in templates {
+            let expected = Format::parse(template).unwrap().render(&["$"; 8]);
+            assert_eq!(parse(&[template]).unwrap(), expected, "{:?}", template);
+            for (i, _) in template.char_indices() {
+                let res = parse(&[&template[.. i], &template[i ..]]).unwrap();
+                assert_eq!(res, expected, "{:?} split at {}", template, i);
+            }
+            let chars: Vec<String> = template.chars().map(String::from).collect();
+            let chars: Vec<&str> = chars.iter().map(|x| x.as_str()).collect();
+            assert_eq!(parse(&chars).unwrap(), expected, "{:?} by chars", template);
+        }
+    }
+
+    /// Describes the segments, merging adjacent literals and resolving `{}` to indices as [`Format`] does.
+    struct Described {
+        res: Vec<String>,
+        literal: String,
+        next: usize,
+    }
+
+    impl Described {
+        fn push(&mut self, segment: Segment) {
+            let (arg, spec, counts) = match segment {
+                Segment::Literal(s) => return self.literal.push_str(s),
+                Segment::Arg(arg, spec, counts) => (arg, spec, counts),
+            };
+            self.flush();
+            let arg = if arg == ArgRef::Next {
+                self.next += 1;
+                ArgRef::Index(self.next - 1)
+            } else {
+                arg
+            };
+            self.res.push(format!("{:?}", Segment::Arg(arg, spec, counts)));
+        }
+
+        fn flush(&mut self) {
+            if !self.literal.is_empty() { self.res.push(format!("{:?}", Segment::Literal(&take(&mut self.literal)))); }
+        }
+    }
+
+    #[test]
+    fn same_segments_as_format() {
+        let options = ParseOptions::lenient().allow_escapes(true);
+        let template = r"a{{\t{}{1:>{w}.{0}}}}{% raw %}{x}{% endraw %}{# c #}{ y :<3}{:.{2}}b\u{44f}";
+        let mut expected = Described { res: Vec::new(), literal: String::new(), next: 0 };
+        Format::parse_with(template, options).unwrap().segments().for_each(|x| expected.push(x));
+        expected.flush();
+        for i in (0 ..= template.len()).filter(|&i| template.is_char_boundary(i)) {
+            let mut res = Described { res: Vec::new(), literal: String::new(), next: 0 };
+            let mut parser = Parser::with_options(options);
+            let mut sink = |segment: Segment| {
+                res.push(segment);
+                Ok::<(), ParseError>(())
+            };
+            parser.feed(&template[.. i], &mut sink).unwrap();
+            parser.feed(&template[i ..], &mut sink).unwrap();
+            parser.finish(&mut sink).unwrap();
+            res.flush();
+            assert_eq!(res.res, expected.res, "split at {}", i);
+        }
+    }
+
+    #[test]
+    fn unbalanced_braces_at_any_split() {
+        let strict = ParseOptions::new().forbid_unbalanced_braces(true);
+        let templates = [
+            "{{}}{}", "{{}}x{{}{}}y{", "{{{}}}x{y}", "a}b", "}", "{", "}}", "{{", "x{", "{ x}",
+            "{% rax %}", "{%", "{% raw %}}{% endraw %}", "{# } #}{}", "{:>5}}}",
+        ];
+        for template in templates {
+            let expected = Format::parse_with(template, strict).map(|x| x.render(&["$"; 8]));
+            assert_eq!(parse_with(&[template], strict), expected, "{:?}", template);
+            for (i, _) in template.char_indices() {
+                let res = parse_with(&[&template[.. i], &template[i ..]], strict);
+                assert_eq!(res, expected, "{:?} split at {}", template, i);
+            }
+        }
+        let err = parse_with(&["ab", "c}d"], strict).unwrap_err();
+        assert_eq!((err.kind(), err.span()), (ParseErrorKind::UnbalancedBrace, 3 .. 4));
+    }
+
+    #[test]
+    fn args() {
+        assert_eq!(parse(&["a{", "}b{}", "{", "", "}"]).unwrap(), "a$b$$");
+    }
+
+    #[test]
+    fn unterminated() {
+        let err = parse(&["ab{", "# comment ", "#"]).unwrap_err();
+        assert_eq!(err.kind(), ParseErrorKind::UnterminatedComment);
+        assert_eq!(err.span(), 2 .. 14);
+        let err = parse(&["{:", "5"]).unwrap_err();
+        assert_eq!(err.kind(), ParseErrorKind::UnterminatedPlaceholder);
+        assert_eq!(err.span(), 0 .. 3);
+        let err = parse(&["{}{:", "5x}"]).unwrap_err();
+        assert_eq!(err.kind(), ParseErrorKind::InvalidSpec);
+        assert_eq!(err.span(), 2 .. 7);
+        let err = parse(&["{x", "1:>3"]).unwrap_err();
+        assert_eq!(err.kind(), ParseErrorKind::UnterminatedPlaceholder);
+        assert_eq!(err.span(), 0 .. 6);
+        let err = parse(&["{}{@", "x}"]).unwrap_err();
+        assert_eq!(err.kind(), ParseErrorKind::ReservedSyntax);
+        assert_eq!(err.span(), 2 .. 6);
+        let err = parse(&["{}{% ra", "w %}x{% endraw"]).unwrap_err();
+        assert_eq!(err.kind(), ParseErrorKind::UnterminatedRaw);
+        assert_eq!(err.span(), 2 .. 21);
+    }
+
+    #[test]
+    fn bounded_memory() {
+        let chunk = " ".repeat(1024);
+        let mut parser = Parser::new();
+        let mut sink = |_: Segment| Ok::<(), ParseError>(());
+        parser.feed("a{x", &mut sink).unwrap();
+        let err = (0 .. 1000).find_map(|_| {
+            let res = parser.feed(&chunk, &mut sink).err();
+            assert!(parser.pending.len() <= MAX_PLACEHOLDER_LEN);
+            res
+        }).unwrap();
+        assert_eq!((err.kind(), err.span().start), (ParseErrorKind::UnterminatedPlaceholder, 1));
+        assert!(err.span().end <= 1 + MAX_PLACEHOLDER_LEN + chunk.len());
+        for (open, close, output) in [("{% raw %}", "{% endraw %}", 1024 * 1000), ("{#", "#}", 0), ("{%", "", 1 + 1024 * 1000)] {
+            let mut parser = Parser::new();
+            let mut len = 0;
+            let mut sink = |segment: Segment| {
+                if let Segment::Literal(s) = segment { len += s.len(); }
+                Ok::<(), ParseError>(())
+            };
+            parser.feed(open, &mut sink).unwrap();
+            for _ in 0 .. 1000 {
+                parser.feed(&chunk, &mut sink).unwrap();
+                assert!(parser.pending.len() <= MAX_PLACEHOLDER_LEN, "{:?}", open);
+            }
+            parser.feed(close, &mut sink).unwrap();
+            parser.finish(&mut sink).unwrap();
+            assert_eq!(len, output, "{:?}", open);
+        }
+    }
+
+    struct Chunked<'a> {
+        data: &'a [u8],
+        chunk: usize,
+    }
+
+    impl<'a> Read for Chunked<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.chunk.min(buf.len()).min(self.data.len());
+            buf[.. n].copy_from_slice(&self.data[.. n]);
+            self.data = &self.data[n ..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn non_ascii_at_any_split() {
+        let template = "я{{€}}{:😀^5}ы{имя}}ж{:я>3.1}😀";
+        for i in (0 ..= template.len()).filter(|&i| template.is_char_boundary(i)) {
+            let res = parse_with(&[&template[.. i], &template[i ..]], ParseOptions::new()).unwrap();
+            assert_eq!(res, "я{€}😀😀$😀😀ыжяя$😀", "split at {}", i);
+        }
+        let err = parse_with(&["a{я", "ы"], ParseOptions::new()).unwrap_err();
+        assert_eq!((err.kind(), err.span()), (ParseErrorKind::UnterminatedPlaceholder, 1 .. 6));
+    }
+
+    #[test]
+    fn escapes_at_any_split() {
+        let escapes = ParseOptions::new().allow_escapes(true);
+        let template = r"a\tb{}\u{1F600}\\{{\u{44f}";
+        for i in 0 ..= template.len() {
+            let res = parse_with(&[&template[.. i], &template[i ..]], escapes).unwrap();
+            assert_eq!(res, "a\tb$\u{1F600}\\{я", "split at {}", i);
+        }
+        let err = parse_with(&["a\\u{", "1"], escapes).unwrap_err();
+        assert_eq!((err.kind(), err.span()), (ParseErrorKind::InvalidEscape, 1 .. 5));
+        let err = parse_with(&["a\\", "q"], escapes).unwrap_err();
+        assert_eq!((err.kind(), err.span()), (ParseErrorKind::InvalidEscape, 1 .. 3));
+    }
+
+    #[test]
+    fn mixed_numbering() {
+        let strict = ParseOptions::new().forbid_mixed_numbering(true);
+        assert_eq!(parse_with(&["{1}{x}{", "0}"], strict).unwrap(), "$$");
+        let err = parse_with(&["{0}a{", "}"], strict).unwrap_err();
+        assert_eq!((err.kind(), err.span()), (ParseErrorKind::MixedNumbering, 4 .. 6));
+        let err = parse_with(&["{:>2}a{1", "}"], strict).unwrap_err();
+        assert_eq!((err.kind(), err.span()), (ParseErrorKind::MixedNumbering, 6 .. 9));
+    }
+
+    #[test]
+    fn stream() {
+        let template = "абв{}{# коммент #}где{{{}}}{% raw %}{}{% endraw %}{0}";
+        for chunk in 1 .. 8 {
+            let mut output = Vec::new();
+            format_stream(Chunked { data: template.as_bytes(), chunk }, &mut output, &["ж", "з"]).unwrap();
+            assert_eq!(String::from_utf8(output).unwrap(), "абвжгде{з}{}ж");
+        }
+    }
+
+    #[test]
+    fn stream_errors() {
+        let mut output = Vec::new();
+        let err = format_stream(&b"{}\xff"[..], &mut output, &[1]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = format_stream(&b"{}\xd0"[..], &mut output, &[1]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = format_stream("{}{# x".as_bytes(), &mut output, &[1]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.into_inner().unwrap().downcast::<ParseError>().unwrap().kind(), ParseErrorKind::UnterminatedComment);
+    }
+}
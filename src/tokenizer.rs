@@ -0,0 +1,390 @@
+use crate::{ParseErrorKind, ParseOptions, Spec};
+use crate::parse::{TagMatch, Token, Tokens, char_len, match_tag, parse_escape};
+use core::ops::Range;
+
+/// A syntax element of a format string, reported by [`Tokenizer`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum SyntaxEvent {
+    /// A run of literal text, including the content of a raw block.
+    Literal,
+    /// A literal character written as `{{`, `}}`, or an escape sequence, e.g. `\n`.
+    Escape,
+    /// The `{` opening a placeholder, or the width or the precision given by an argument.
+    Open,
+    /// The `}` closing a placeholder, or the width or the precision given by an argument.
+    Close,
+    /// An argument index, e.g. `0` in `{0}` or `1` in `{:.{1}}`.
+    Index,
+    /// An argument name, e.g. `name` in `{name}` or `width` in `{:{width}}`.
+    Name,
+    /// The `:` separating the argument from the spec.
+    Colon,
+    /// The fill character of the spec.
+    Fill,
+    /// The alignment of the spec, `<`, `^`, `>`, or `d`.
+    Align,
+    /// The sign flag, `+` or `-`.
+    Sign,
+    /// The `#` flag.
+    Alternate,
+    /// The `0` flag.
+    Zero,
+    /// The width digits.
+    Width,
+    /// The `.` preceding the precision.
+    Dot,
+    /// The precision digits.
+    Precision,
+    /// The presentation type, e.g. `e` or `base64(url)`.
+    Presentation,
+    /// Characters of the spec skipped because of [`ParseOptions::ignore_unknown_flags`].
+    Unknown,
+    /// Whitespace inside a placeholder, see [`ParseOptions::allow_whitespace`].
+    Whitespace,
+    /// A `{# ... #}` comment.
+    Comment,
+    /// The `{% raw %}` tag.
+    RawOpen,
+    /// The `{% endraw %}` tag.
+    RawClose,
+    /// A single brace taken literally, see [`ParseOptions::forbid_unbalanced_braces`].
+    StrayBrace,
+    /// An erroneous fragment.
+    Error(ParseErrorKind),
+}
+
+/// The maximum number of events a placeholder is split into.
+const PLACEHOLDER_EVENTS: usize = 32;
+
+/// Splits a format string into [`SyntaxEvent`]s, each paired with its byte range,
+/// for syntax highlighting, autocompletion, and other editor features.
+///
+/// The events cover the whole format string without gaps and overlaps.
+/// An erroneous fragment is reported as a single [`Error`](SyntaxEvent::Error) event,
+/// and the text following it is split as usual, so one error does not spoil the rest of the template.
+/// Template-wide restrictions, such as [`ParseOptions::forbid_mixed_numbering`], are not checked,
+/// use [`validate`](crate::validate) for them.
+///
+/// The tokenizer does not allocate, so it is available in `no_std` environment.
+/// # Examples:
+/// ```rust
+/// # #[cfg(feature = "syntax-named")] {
+/// use dyn_fmt::{ParseOptions, SyntaxEvent, Tokenizer};
+/// let fmt = "Hi {name:>8}!{{";
+/// let events: Vec<_> = Tokenizer::new(fmt, ParseOptions::new()).map(|(span, e)| (&fmt[span], e)).collect();
+/// assert_eq!(events, [
+///     ("Hi ", SyntaxEvent::Literal),
+///     ("{", SyntaxEvent::Open),
+///     ("name", SyntaxEvent::Name),
+///     (":", SyntaxEvent::Colon),
+///     (">", SyntaxEvent::Align),
+///     ("8", SyntaxEvent::Width),
+///     ("}", SyntaxEvent::Close),
+///     ("!", SyntaxEvent::Literal),
+///     ("{{", SyntaxEvent::Escape),
+/// ]);
+/// # }
+/// ```
+pub struct Tokenizer<'a> {
+    fmt: &'a str,
+    options: ParseOptions,
+    tokens: Tokens<'a>,
+    /// The end of the last event.
+    pos: usize,
+    /// The token following a fragment which is not a token itself, e.g. a raw block tag.
+    pending: Option<(Range<usize>, Token<'a>)>,
+    /// The end of the literal with escape sequences being split.
+    escaped_end: usize,
+    placeholder: [(usize, usize, SyntaxEvent); PLACEHOLDER_EVENTS],
+    placeholder_len: usize,
+    placeholder_pos: usize,
+}
+
+impl<'a> Tokenizer<'a> {
+    /// Creates a tokenizer recognizing the syntax [`Format::parse_with`](crate::Format::parse_with) accepts
+    /// with the specified options.
+    pub fn new(fmt: &'a str, options: ParseOptions) -> Self {
+        Tokenizer {
+            fmt,
+            options,
+            tokens: Tokens::explicit(fmt, options),
+            pos: 0,
+            pending: None,
+            escaped_end: 0,
+            placeholder: [(0, 0, SyntaxEvent::Literal); PLACEHOLDER_EVENTS],
+            placeholder_len: 0,
+            placeholder_pos: 0,
+        }
+    }
+
+    fn emit(&mut self, end: usize, event: SyntaxEvent) -> (Range<usize>, SyntaxEvent) {
+        let start = self.pos;
+        self.pos = end;
+        (start .. end, event)
+    }
+
+    /// Splits a fragment preceding the next token, which is a raw block tag or the first brace of `{{` or `}}`.
+    fn gap(&mut self, end: usize) -> (Range<usize>, SyntaxEvent) {
+        let bytes = &self.fmt.as_bytes()[self.pos ..];
+        if let TagMatch::Matched(len) = match_tag(bytes, "raw") {
+            return self.emit(self.pos + len, SyntaxEvent::RawOpen);
+        }
+        if let TagMatch::Matched(len) = match_tag(bytes, "endraw") {
+            return self.emit(self.pos + len, SyntaxEvent::RawClose);
+        }
+        if bytes.len() >= 2 && matches!(bytes[0], b'{' | b'}') && bytes[1] == bytes[0] {
+            return self.emit(self.pos + 2, SyntaxEvent::Escape);
+        }
+        self.emit(end, SyntaxEvent::Literal)
+    }
+
+    fn escaped(&mut self) -> (Range<usize>, SyntaxEvent) {
+        let s = &self.fmt[self.pos .. self.escaped_end];
+        if s.starts_with('\\') {
+            if let Ok((_, len)) = parse_escape(s) { return self.emit(self.pos + len, SyntaxEvent::Escape); }
+        }
+        let first = char_len(s);
+        let len = s[first ..].find('\\').map_or(s.len(), |n| n + first);
+        self.emit(self.pos + len, SyntaxEvent::Literal)
+    }
+
+    fn push(&mut self, start: usize, end: usize, event: SyntaxEvent) {
+        if start == end { return; }
+        self.placeholder[self.placeholder_len] = (start, end, event);
+        self.placeholder_len += 1;
+    }
+
+    fn push_whitespace(&mut self, start: usize, s: &str) -> usize {
+        let len = s.len() - s.trim_start().len();
+        self.push(start, start + len, SyntaxEvent::Whitespace);
+        start + len
+    }
+
+    fn push_arg(&mut self, start: usize, s: &str) {
+        let event = if s.starts_with(|c: char| c.is_ascii_digit()) { SyntaxEvent::Index } else { SyntaxEvent::Name };
+        self.push(start, start + s.len(), event);
+    }
+
+    /// Splits a valid placeholder.
+    fn split_placeholder(&mut self, span: Range<usize>) {
+        self.placeholder_len = 0;
+        self.placeholder_pos = 0;
+        let whitespace = self.options.allows_whitespace();
+        let inner = &self.fmt[span.start + 1 .. span.end - 1];
+        self.push(span.start, span.start + 1, SyntaxEvent::Open);
+        let (arg, spec) = match inner.find(':') {
+            Some(colon) => (&inner[.. colon], Some(&inner[colon + 1 ..])),
+            None => (inner, None),
+        };
+        let mut pos = span.start + 1;
+        if whitespace { pos = self.push_whitespace(pos, arg); }
+        let name = if whitespace { arg.trim() } else { arg };
+        self.push_arg(pos, name);
+        pos += name.len();
+        self.push(pos, span.start + 1 + arg.len(), SyntaxEvent::Whitespace);
+        pos = span.start + 1 + arg.len();
+        if let Some(spec) = spec {
+            self.push(pos, pos + 1, SyntaxEvent::Colon);
+            pos += 1;
+            let unknown = self.options.ignores_unknown_flags();
+            let mut spec_text = if whitespace { spec.trim_end() } else { spec };
+            if whitespace && Spec::parse_counts(spec_text, unknown).is_err() {
+                pos = self.push_whitespace(pos, spec_text);
+                spec_text = spec_text.trim_start();
+            }
+            pos = self.split_spec(pos, spec_text, unknown);
+        }
+        self.push(pos, span.end - 1, SyntaxEvent::Whitespace);
+        self.push(span.end - 1, span.end, SyntaxEvent::Close);
+    }
+
+    /// Splits a valid spec starting at `start`, returning its end.
+    fn split_spec(&mut self, start: usize, spec: &str, unknown: bool) -> usize {
+        fn is_align(c: Option<char>) -> bool { matches!(c, Some('<' | '^' | '>' | 'd')) }
+        let mut pos = start;
+        let end = start + spec.len();
+        let rest = |pos: usize| &self.fmt[pos .. end];
+        let mut chars = spec.chars();
+        let first = chars.next();
+        if is_align(chars.next()) {
+            let fill = first.map_or(0, char::len_utf8);
+            self.push(pos, pos + fill, SyntaxEvent::Fill);
+            self.push(pos + fill, pos + fill + 1, SyntaxEvent::Align);
+            pos += fill + 1;
+        } else if is_align(first) {
+            self.push(pos, pos + 1, SyntaxEvent::Align);
+            pos += 1;
+        }
+        let skip = |this: &mut Self, pos: usize| {
+            if !unknown { return pos; }
+            let s = &this.fmt[pos .. end];
+            let len = s.len() - s.trim_start_matches(|c: char| !matches!(c, '<' | '^' | '>' | '+' | '-' | '#' | '.' | '{' | '0' ..= '9')).len();
+            this.push(pos, pos + len, SyntaxEvent::Unknown);
+            pos + len
+        };
+        pos = skip(self, pos);
+        if rest(pos).starts_with(['+', '-']) {
+            self.push(pos, pos + 1, SyntaxEvent::Sign);
+            pos += 1;
+        }
+        pos = skip(self, pos);
+        if rest(pos).starts_with('#') {
+            self.push(pos, pos + 1, SyntaxEvent::Alternate);
+            pos += 1;
+        }
+        pos = skip(self, pos);
+        if rest(pos).starts_with('0') {
+            self.push(pos, pos + 1, SyntaxEvent::Zero);
+            pos += 1;
+        }
+        pos = skip(self, pos);
+        pos = self.split_count(pos, end, SyntaxEvent::Width);
+        pos = skip(self, pos);
+        if rest(pos).starts_with('.') {
+            self.push(pos, pos + 1, SyntaxEvent::Dot);
+            pos = skip(self, pos + 1);
+            pos = self.split_count(pos, end, SyntaxEvent::Precision);
+        }
+        #[cfg(feature = "std")]
+        let presentation = crate::Presentation::parse(rest(pos)).is_some();
+        #[cfg(not(feature = "std"))]
+        let presentation = false;
+        self.push(pos, end, if presentation { SyntaxEvent::Presentation } else { SyntaxEvent::Unknown });
+        end
+    }
+
+    fn split_count(&mut self, start: usize, end: usize, digits: SyntaxEvent) -> usize {
+        let s = &self.fmt[start .. end];
+        if s.starts_with('{') {
+            let Some(close) = s.find('}') else { return start; };
+            self.push(start, start + 1, SyntaxEvent::Open);
+            self.push_arg(start + 1, &s[1 .. close]);
+            self.push(start + close, start + close + 1, SyntaxEvent::Close);
+            return start + close + 1;
+        }
+        let len = s.bytes().position(|b| !b.is_ascii_digit()).unwrap_or(s.len());
+        self.push(start, start + len, digits);
+        start + len
+    }
+}
+
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = (Range<usize>, SyntaxEvent);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.placeholder_pos < self.placeholder_len {
+                let (start, end, event) = self.placeholder[self.placeholder_pos];
+                self.placeholder_pos += 1;
+                self.pos = end;
+                return Some((start .. end, event));
+            }
+            if self.escaped_end > self.pos { return Some(self.escaped()); }
+            let Some((span, token)) = self.pending.take().or_else(|| self.tokens.next()) else {
+                if self.pos == self.fmt.len() { return None; }
+                return Some(self.gap(self.fmt.len()));
+            };
+            if span.end <= self.pos { continue; }
+            if span.start > self.pos {
+                let start = span.start;
+                self.pending = Some((span, token));
+                return Some(self.gap(start));
+            }
+            return Some(match token {
+                Token::Literal => self.emit(span.end, SyntaxEvent::Literal),
+                Token::Escaped => {
+                    self.escaped_end = span.end;
+                    continue;
+                },
+                Token::Arg(..) if span.start == self.pos => {
+                    self.split_placeholder(span);
+                    continue;
+                },
+                Token::Arg(..) => self.emit(span.end, SyntaxEvent::Literal),
+                Token::Comment => self.emit(span.end, SyntaxEvent::Comment),
+                Token::StrayBrace => self.emit(span.end, SyntaxEvent::StrayBrace),
+                Token::Error(kind) => self.emit(span.end, SyntaxEvent::Error(kind)),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ParseErrorKind, ParseOptions, SyntaxEvent, Tokenizer};
+    use SyntaxEvent::*;
+
+    fn check(fmt: &str, options: ParseOptions, expected: &[(&str, SyntaxEvent)]) {
+        let mut pos = 0;
+        let mut expected = expected.iter();
+        for (span, event) in Tokenizer::new(fmt, options) {
+            assert_eq!(span.start, pos, "{}", fmt);
+            assert_eq!(Some(&(&fmt[span.clone()], event)), expected.next(), "{}", fmt);
+            pos = span.end;
+        }
+        assert_eq!(pos, fmt.len(), "{}", fmt);
+        assert_eq!(expected.next(), None, "{}", fmt);
+    }
+
+    #[test]
+    fn tokenizer() {
+        let strict = ParseOptions::new();
+        check("", strict, &[]);
+        check("{}}}x{{", strict, &[("{", Open), ("}", Close), ("}}", Escape), ("x", Literal), ("{{", Escape)]);
+        check("{0:*^+#09.3}", strict, &[
+            ("{", Open), ("0", Index), (":", Colon), ("*", Fill), ("^", Align), ("+", Sign),
+            ("#", Alternate), ("0", Zero), ("9", Width), (".", Dot), ("3", Precision), ("}", Close),
+        ]);
+        #[cfg(feature = "syntax")]
+        check("{:{w}.{1}}", strict, &[
+            ("{", Open), (":", Colon), ("{", Open), ("w", Name), ("}", Close), (".", Dot),
+            ("{", Open), ("1", Index), ("}", Close), ("}", Close),
+        ]);
+        #[cfg(feature = "syntax")]
+        check("ä{#c#}{% raw %}{x}{%endraw%}{% raw %}{% endraw %}", strict, &[
+            ("ä", Literal), ("{#c#}", Comment), ("{% raw %}", RawOpen), ("{x}", Literal), ("{%endraw%}", RawClose),
+            ("{% raw %}", RawOpen), ("{% endraw %}", RawClose),
+        ]);
+        check("a{:q}b{x", strict, &[("a", Literal), ("{:q}", Error(ParseErrorKind::InvalidSpec)), ("b", Literal),
+            ("{x", Error(ParseErrorKind::UnterminatedPlaceholder))]);
+        check("a}b", strict, &[("a", Literal), ("}", StrayBrace), ("b", Literal)]);
+        check("a}b", strict.forbid_unbalanced_braces(true), &[("a", Literal), ("}", Error(ParseErrorKind::UnbalancedBrace)), ("b", Literal)]);
+    }
+
+    #[test]
+    fn tokenizer_options() {
+        #[cfg(feature = "syntax-escapes")]
+        {
+            let escapes = ParseOptions::new().allow_escapes(true);
+            check(r"a\n{{\tb\q{}", escapes, &[
+                ("a", Literal), (r"\n", Escape), ("{{", Escape), (r"\t", Escape), ("b", Literal),
+                (r"\q", Error(ParseErrorKind::InvalidEscape)), ("{", Open), ("}", Close),
+            ]);
+            check(r"ä\n", escapes, &[("ä", Literal), (r"\n", Escape)]);
+        }
+        let whitespace = ParseOptions::new().allow_whitespace(true);
+        #[cfg(feature = "syntax-named")]
+        check("{ x : >5 }", whitespace, &[
+            ("{", Open), (" ", Whitespace), ("x", Name), (" ", Whitespace), (":", Colon), (" ", Fill), (">", Align),
+            ("5", Width), (" ", Whitespace), ("}", Close),
+        ]);
+        check("{ :  <5}", whitespace, &[
+            ("{", Open), (" ", Whitespace), (":", Colon), ("  ", Whitespace), ("<", Align), ("5", Width), ("}", Close),
+        ]);
+        let unknown = ParseOptions::new().ignore_unknown_flags(true);
+        check("{:q+w5.y2u}", unknown, &[
+            ("{", Open), (":", Colon), ("q", Unknown), ("+", Sign), ("w", Unknown), ("5", Width), (".", Dot),
+            ("y", Unknown), ("2", Precision), ("u", Unknown), ("}", Close),
+        ]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn presentation_events() {
+        check("{:>8.2e}{:base64(url)}", ParseOptions::new(), &[
+            ("{", Open), (":", Colon), (">", Align), ("8", Width), (".", Dot), ("2", Precision), ("e", Presentation), ("}", Close),
+            ("{", Open), (":", Colon), ("base64(url)", Presentation), ("}", Close),
+        ]);
+    }
+}
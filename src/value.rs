@@ -0,0 +1,150 @@
+use core::fmt::{self, Debug, Display};
+
+/// A non-generic argument, which allows FFI layers, script bindings and plugin systems
+/// to build argument lists of mixed types without generic plumbing.
+///
+/// Numbers are displayed as numbers, so the spec applies to them the same way as to Rust integers and floats.
+/// Bytes are displayed as lowercase hexadecimal digits, and, unless in `no_std` environment,
+/// in other [`Encoding`](crate::Encoding)s with the corresponding presentation, e.g. `{:base64}`.
+/// # Examples:
+/// ```rust
+/// use dyn_fmt::{Arguments, Value};
+/// let level = "warn";
+/// let args = [Value::Str("disk"), Value::Float(93.456), Value::from(-2), Value::Display(&level), Value::Bytes(&[0xca, 0xfe])];
+/// let s = Arguments::new("{} at {:.1}%, delta {:+}, level {:>5}, tag {}", &args).to_string();
+/// assert_eq!(s, "disk at 93.5%, delta -2, level  warn, tag cafe");
+/// ```
+#[derive(Clone, Copy)]
+#[non_exhaustive]
+pub enum Value<'a> {
+    /// A string.
+    Str(&'a str),
+    /// A signed integer.
+    Int(i64),
+    /// An unsigned integer.
+    UInt(u64),
+    /// A floating-point number.
+    Float(f64),
+    /// A boolean, displayed as `true` or `false`.
+    Bool(bool),
+    /// Binary data, displayed as lowercase hexadecimal digits by default.
+    Bytes(&'a [u8]),
+    /// Any other displayable value.
+    Display(&'a dyn Display),
+}
+
+impl<'a> Display for Value<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Str(x) => Display::fmt(x, f),
+            Value::Int(x) => Display::fmt(x, f),
+            Value::UInt(x) => Display::fmt(x, f),
+            Value::Float(x) => Display::fmt(x, f),
+            Value::Bool(x) => Display::fmt(x, f),
+            #[cfg(feature = "std")]
+            Value::Bytes(x) => Display::fmt(&crate::Bytes(x), f),
+            #[cfg(not(feature = "std"))]
+            Value::Bytes(x) => fmt_hex(x, f),
+            Value::Display(x) => Display::fmt(x, f),
+        }
+    }
+}
+
+/// Writes the bytes as lowercase hexadecimal digits padded according to the formatter.
+#[cfg(not(feature = "std"))]
+fn fmt_hex(bytes: &[u8], f: &mut fmt::Formatter) -> fmt::Result {
+    use core::fmt::Write;
+
+    let padding = f.width().unwrap_or(0).saturating_sub(2 * bytes.len());
+    let (left, right) = match f.align() {
+        Some(fmt::Alignment::Right) => (padding, 0),
+        Some(fmt::Alignment::Center) => (padding / 2, (padding + 1) / 2),
+        Some(fmt::Alignment::Left) | None => (0, padding),
+    };
+    for _ in 0 .. left { f.write_char(f.fill())?; }
+    for b in bytes { write!(f, "{:02x}", b)?; }
+    for _ in 0 .. right { f.write_char(f.fill())?; }
+    Ok(())
+}
+
+impl<'a> Debug for Value<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Str(x) => f.debug_tuple("Str").field(x).finish(),
+            Value::Int(x) => f.debug_tuple("Int").field(x).finish(),
+            Value::UInt(x) => f.debug_tuple("UInt").field(x).finish(),
+            Value::Float(x) => f.debug_tuple("Float").field(x).finish(),
+            Value::Bool(x) => f.debug_tuple("Bool").field(x).finish(),
+            Value::Bytes(x) => f.debug_tuple("Bytes").field(x).finish(),
+            Value::Display(x) => f.debug_tuple("Display").field(&format_args!("{}", x)).finish(),
+        }
+    }
+}
+
+impl<'a> From<&'a str> for Value<'a> {
+    fn from(x: &'a str) -> Self { Value::Str(x) }
+}
+
+impl<'a> From<&'a [u8]> for Value<'a> {
+    fn from(x: &'a [u8]) -> Self { Value::Bytes(x) }
+}
+
+impl<'a> From<bool> for Value<'a> {
+    fn from(x: bool) -> Self { Value::Bool(x) }
+}
+
+macro_rules! from_number {
+    ($variant:ident: $($t:ty),*) => {
+        $(
+            impl<'a> From<$t> for Value<'a> {
+                fn from(x: $t) -> Self { Value::$variant(x.into()) }
+            }
+        )*
+    };
+}
+
+from_number!(Int: i8, i16, i32, i64);
+from_number!(UInt: u8, u16, u32, u64);
+from_number!(Float: f32, f64);
+
+#[cfg(test)]
+mod tests {
+    use crate::Value;
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn value() {
+        use crate::{Arguments, Format};
+        use std::collections::HashMap;
+
+        let pi = core::f64::consts::PI;
+        let args = [Value::from(u64::MAX), Value::from(-1i8), Value::from(2.5f32), Value::from(false), Value::Display(&pi)];
+        let s = Arguments::new("{} {:04} {:e} {:>6} {:.3}", &args).to_string();
+        assert_eq!(s, "18446744073709551615 -001 2.5e0  false 3.142");
+        let bytes = Value::from(&b"\x01\xff"[..]);
+        assert_eq!(Arguments::new("{}|{:*^8}|{:base64}|{:HEX}", &[bytes; 4]).to_string(), "01ff|**01ff**|Af8=|01FF");
+        let format = Format::parse("{user} ({id})").unwrap();
+        assert_eq!(format.render_from(HashMap::from([("user", Value::Str("ann")), ("id", Value::UInt(7))])), "ann (7)");
+        assert_eq!(format!("{:?}", [Value::Int(1), Value::Display(&"x")]), "[Int(1), Display(x)]");
+    }
+
+    #[test]
+    fn no_std_value() {
+        use core::fmt::Write;
+
+        struct Buf([u8; 64], usize);
+
+        impl Write for Buf {
+            fn write_str(&mut self, s: &str) -> core::fmt::Result {
+                self.0[self.1 .. self.1 + s.len()].copy_from_slice(s.as_bytes());
+                self.1 += s.len();
+                Ok(())
+            }
+        }
+
+        let mut buf = Buf([0; 64], 0);
+        let args = [Value::Bytes(&[0xab, 0x01]), Value::Bytes(&[0xab]), Value::Bool(true)];
+        write!(buf, "{}", crate::Arguments::new("{}|{:>4}|{:-^6}", &args)).unwrap();
+        assert_eq!(&buf.0[.. buf.1], b"ab01|  ab|-true-");
+    }
+}
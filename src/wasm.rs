@@ -0,0 +1,99 @@
+//! The JavaScript interface, exported with [`wasm-bindgen`](https://docs.rs/wasm-bindgen).
+//!
+//! The functions use the same template syntax and engine as [`Format`]:
+//! a template is parsed strictly, the arguments are an array of positional values,
+//! or an object with named ones. Booleans and numbers keep their type,
+//! so the spec applies to them as to numbers, e.g. `{:.2}`, other values are converted with `String`.
+//!
+//! Available with the `"wasm"` feature.
+//! # Examples:
+//! ```js
+//! import { format, validate } from "./pkg/templates.js";
+//!
+//! format("{} has {:>3} items", ["cart", 7]); // "cart has   7 items"
+//! format("{user} ({id:04})", { user: "ann", id: 7 }); // "ann (0007)"
+//! const error = validate("{:q}");
+//! console.log(error.message, error.start, error.end); // "invalid format spec" 0 4
+//! ```
+
+use crate::Format;
+use crate::script::{self, ScriptArgs, ScriptValue};
+use js_sys::{Array, Object};
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_name = String)]
+    fn js_string(x: &JsValue) -> String;
+}
+
+/// The largest integer exactly representable in JavaScript.
+const MAX_SAFE_INTEGER: f64 = 9007199254740991.0;
+
+fn value(x: &JsValue) -> ScriptValue {
+    if let Some(x) = x.as_bool() { return ScriptValue::Bool(x); }
+    if let Some(x) = x.as_f64() {
+        return if x.fract() == 0.0 && x.abs() <= MAX_SAFE_INTEGER { ScriptValue::Int(x as i64) } else { ScriptValue::Float(x) };
+    }
+    if let Some(x) = x.as_string() { return ScriptValue::Text(x); }
+    ScriptValue::Text(js_string(x))
+}
+
+fn arguments(x: &JsValue) -> Result<ScriptArgs, JsError> {
+    let mut res = ScriptArgs::default();
+    if x.is_undefined() || x.is_null() { return Ok(res); }
+    if Array::is_array(x) {
+        res.positional = Array::from(x).iter().map(|x| value(&x)).collect();
+    } else if x.is_object() {
+        for entry in Object::entries(x.unchecked_ref()).iter() {
+            let entry = Array::from(&entry);
+            res.named.insert(js_string(&entry.get(0)), value(&entry.get(1)));
+        }
+    } else {
+        return Err(JsError::new("format arguments should be an array or an object"));
+    }
+    Ok(res)
+}
+
+/// Renders the template with an array of positional arguments, or an object of named ones.
+///
+/// Throws an `Error` if the template is malformed, or the arguments are neither an array nor an object.
+#[wasm_bindgen]
+pub fn format(template: &str, args: JsValue) -> Result<String, JsError> {
+    script::format(template, &arguments(&args)?).map_err(|e| JsError::new(&e))
+}
+
+/// Checks the template syntax, returning the first error, or `undefined` if the template is valid.
+#[wasm_bindgen]
+pub fn validate(template: &str) -> Option<ValidationError> {
+    let utf16_offset = |i: usize| template[.. i].encode_utf16().count();
+    Format::parse(template).err().map(|e| ValidationError {
+        message: e.kind().to_string(),
+        start: utf16_offset(e.span().start),
+        end: utf16_offset(e.span().end),
+    })
+}
+
+/// A template syntax error returned by [`validate`].
+#[wasm_bindgen]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ValidationError {
+    message: String,
+    start: usize,
+    end: usize,
+}
+
+#[wasm_bindgen]
+impl ValidationError {
+    /// The error description, e.g. `invalid format spec`.
+    #[wasm_bindgen(getter)]
+    pub fn message(&self) -> String { self.message.clone() }
+
+    /// The offset of the erroneous fragment start in UTF-16 code units, as JavaScript string indices count.
+    #[wasm_bindgen(getter)]
+    pub fn start(&self) -> usize { self.start }
+
+    /// The offset of the erroneous fragment end in UTF-16 code units.
+    #[wasm_bindgen(getter)]
+    pub fn end(&self) -> usize { self.end }
+}
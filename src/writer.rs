@@ -0,0 +1,91 @@
+use core::fmt::{self, Write};
+use core::str;
+
+/// A [`fmt::Write`] implementation writing into a byte buffer, e.g. a stack array in `no_std` environment.
+///
+/// A string which does not fit the rest of the buffer is rejected as a whole with [`fmt::Error`],
+/// so the written text is always valid UTF-8 and can be accessed as a string slice.
+/// # Examples:
+/// ```rust
+/// use core::fmt::Write;
+/// use dyn_fmt::{SliceWriter, dyn_write};
+/// let mut buf = [0u8; 16];
+/// let mut writer = SliceWriter::new(&mut buf);
+/// dyn_write!(writer, "{}a{}b", &[1, 2]).unwrap();
+/// assert_eq!(writer.as_str(), "1a2b");
+/// assert!(writer.write_str("too long to fit").is_err());
+/// assert_eq!(writer.as_str(), "1a2b");
+/// ```
+#[derive(Debug)]
+pub struct SliceWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> SliceWriter<'a> {
+    /// Creates an empty writer over the buffer.
+    pub fn new(buf: &'a mut [u8]) -> Self { SliceWriter { buf, len: 0 } }
+
+    /// The number of bytes written.
+    pub fn len(&self) -> usize { self.len }
+
+    /// Returns `true` if nothing is written.
+    pub fn is_empty(&self) -> bool { self.len == 0 }
+
+    /// The buffer size in bytes.
+    pub fn capacity(&self) -> usize { self.buf.len() }
+
+    /// The number of bytes which can be written yet.
+    pub fn remaining(&self) -> usize { self.buf.len() - self.len }
+
+    /// Discards the written text.
+    pub fn clear(&mut self) { self.len = 0; }
+
+    /// Returns the written text.
+    pub fn as_str(&self) -> &str {
+        // Only whole strings are copied into the buffer, so it is valid UTF-8.
+        unsafe { str::from_utf8_unchecked(&self.buf[.. self.len]) }
+    }
+
+    /// Returns the written text, borrowed for the lifetime of the buffer.
+    pub fn into_str(self) -> &'a str {
+        // Only whole strings are copied into the buffer, so it is valid UTF-8.
+        unsafe { str::from_utf8_unchecked(&self.buf[.. self.len]) }
+    }
+}
+
+impl<'a> Write for SliceWriter<'a> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        if end > self.buf.len() { return Err(fmt::Error); }
+        self.buf[self.len .. end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::SliceWriter;
+    use core::fmt::Write;
+
+    #[test]
+    fn slice_writer() {
+        let mut buf = [0u8; 8];
+        let mut writer = SliceWriter::new(&mut buf);
+        assert!(writer.is_empty());
+        write!(writer, "ab{}", 1).unwrap();
+        assert_eq!((writer.as_str(), writer.len(), writer.remaining(), writer.capacity()), ("ab1", 3, 5, 8));
+        assert!(writer.write_str("яяя").is_err());
+        writer.write_str("яя").unwrap();
+        assert!(writer.write_char('я').is_err());
+        writer.write_char('x').unwrap();
+        writer.write_str("").unwrap();
+        assert!(writer.write_char('x').is_err());
+        assert_eq!((writer.as_str(), writer.remaining()), ("ab1яяx", 0));
+        writer.clear();
+        writer.write_str("x").unwrap();
+        assert_eq!(writer.into_str(), "x");
+        assert_eq!(SliceWriter::new(&mut []).into_str(), "");
+    }
+}